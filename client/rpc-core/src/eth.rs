@@ -18,6 +18,8 @@
 
 //! Eth rpc interface.
 
+use std::collections::BTreeMap;
+
 use ethereum_types::{H160, H256, H64, U256, U64};
 use jsonrpsee::{core::RpcResult as Result, proc_macros::rpc};
 
@@ -125,6 +127,20 @@ pub trait EthApi {
 	#[method(name = "eth_getTransactionReceipt")]
 	async fn transaction_receipt(&self, hash: H256) -> Result<Option<Receipt>>;
 
+	/// Returns transaction receipts by transaction hashes, in the requested order.
+	/// Requests of more than 1000 hashes are rejected.
+	#[method(name = "eth_getTransactionReceipts")]
+	async fn transaction_receipts(&self, hashes: Vec<H256>) -> Result<Vec<Option<Receipt>>>;
+
+	/// Returns the receipts of all the transactions of a block.
+	#[method(name = "eth_getBlockReceipts")]
+	async fn block_receipts(&self, number: BlockNumber) -> Result<Option<Vec<Receipt>>>;
+
+	/// Returns a provisional receipt of a pending transaction, executing it on top of
+	/// the pending state.
+	#[method(name = "eth_getPendingReceipt")]
+	fn pending_receipt(&self, hash: H256) -> Result<Option<PendingReceipt>>;
+
 	// ########################################################################
 	// State
 	// ########################################################################
@@ -149,9 +165,34 @@ pub trait EthApi {
 	// Execute
 	// ########################################################################
 
-	/// Call contract, returning the output data.
+	/// Call contract, returning the output data. The call executes on top of the
+	/// state overrides of the given accounts, if any.
 	#[method(name = "eth_call")]
-	fn call(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<Bytes>;
+	fn call(
+		&self,
+		request: CallRequest,
+		number: Option<BlockNumber>,
+		state_overrides: Option<BTreeMap<H160, CallStateOverride>>,
+	) -> Result<Bytes>;
+
+	/// Call contract, returning the output data decoded as values of the given ABI
+	/// output types.
+	#[method(name = "eth_callDecoded")]
+	fn call_decoded(
+		&self,
+		request: CallRequest,
+		output_types: Vec<AbiType>,
+		number: Option<BlockNumber>,
+	) -> Result<Vec<AbiValue>>;
+
+	/// Call contracts independently against the same block state, returning the
+	/// outcome of each call.
+	#[method(name = "eth_multicall")]
+	fn multicall(
+		&self,
+		requests: Vec<CallRequest>,
+		number: Option<BlockNumber>,
+	) -> Result<Vec<CallResult>>;
 
 	/// Estimate gas needed for execution of given contract.
 	#[method(name = "eth_estimateGas")]
@@ -216,6 +257,10 @@ pub trait EthApi {
 	/// Sends signed transaction, returning its hash.
 	#[method(name = "eth_sendRawTransaction")]
 	async fn send_raw_transaction(&self, bytes: Bytes) -> Result<H256>;
+
+	/// Decodes a signed transaction and recovers its sender, without submitting it.
+	#[method(name = "eth_decodeRawTransaction")]
+	fn decode_raw_transaction(&self, bytes: Bytes) -> Result<Transaction>;
 }
 
 /// Eth filters rpc api (polling).
@@ -248,4 +293,19 @@ pub trait EthFilterApi {
 	/// Returns logs matching given filter object.
 	#[method(name = "eth_getLogs")]
 	async fn logs(&self, filter: Filter) -> Result<Vec<Log>>;
+
+	/// Returns a page of at most `limit` logs matching given filter object,
+	/// resuming from the cursor returned with the previous page.
+	#[method(name = "eth_getLogsPage")]
+	async fn logs_page(
+		&self,
+		filter: Filter,
+		cursor: Option<LogsCursor>,
+		limit: Option<Index>,
+	) -> Result<LogsPage>;
+
+	/// Returns the installed filters, for diagnosing filters left unpolled. Unsafe,
+	/// denied on public endpoints.
+	#[method(name = "debug_getFilters")]
+	fn filters(&self) -> Result<Vec<FilterInfo>>;
 }
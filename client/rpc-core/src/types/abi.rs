@@ -0,0 +1,385 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+
+use ethereum_types::{H160, U256};
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use crate::types::Bytes;
+
+/// Size in bytes of an ABI word.
+const WORD: usize = 32;
+
+/// Maximum nesting of arrays in an ABI type.
+const MAX_DEPTH: usize = 8;
+
+/// ABI type of a returned value, such as `uint256`, `address[]` or `bytes32[2]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+	/// `uint<M>`, of `M` bits
+	Uint(usize),
+	/// `address`
+	Address,
+	/// `bool`
+	Bool,
+	/// `string`
+	String,
+	/// `bytes`
+	Bytes,
+	/// `bytes<M>`, of `M` bytes
+	FixedBytes(usize),
+	/// `T[]`
+	Array(Box<AbiType>),
+	/// `T[k]`
+	FixedArray(Box<AbiType>, usize),
+}
+
+impl AbiType {
+	fn is_dynamic(&self) -> bool {
+		match self {
+			AbiType::String | AbiType::Bytes | AbiType::Array(_) => true,
+			AbiType::FixedArray(inner, _) => inner.is_dynamic(),
+			_ => false,
+		}
+	}
+
+	/// Size of the value in the head of its enclosing sequence, an offset to its
+	/// tail for dynamic values.
+	fn head_size(&self) -> usize {
+		match self {
+			AbiType::FixedArray(inner, len) if !self.is_dynamic() => {
+				inner.head_size().saturating_mul(*len)
+			}
+			_ => WORD,
+		}
+	}
+}
+
+impl FromStr for AbiType {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let unsupported = || format!("unsupported ABI type {}", s);
+		if s.matches('[').count() > MAX_DEPTH {
+			return Err(unsupported());
+		}
+		if let Some(inner) = s.strip_suffix("[]") {
+			return Ok(AbiType::Array(Box::new(inner.parse()?)));
+		}
+		if let Some((inner, len)) = s.strip_suffix(']').and_then(|s| s.rsplit_once('[')) {
+			let len = len
+				.parse::<usize>()
+				.ok()
+				.filter(|len| *len > 0)
+				.ok_or_else(unsupported)?;
+			return Ok(AbiType::FixedArray(Box::new(inner.parse()?), len));
+		}
+		let size = |prefix: &str| s.strip_prefix(prefix).and_then(|n| n.parse::<usize>().ok());
+		match s {
+			"uint" => Ok(AbiType::Uint(256)),
+			"address" => Ok(AbiType::Address),
+			"bool" => Ok(AbiType::Bool),
+			"string" => Ok(AbiType::String),
+			"bytes" => Ok(AbiType::Bytes),
+			_ => match (size("uint"), size("bytes")) {
+				(Some(bits), _) if bits > 0 && bits <= 256 && bits % 8 == 0 => {
+					Ok(AbiType::Uint(bits))
+				}
+				(_, Some(len)) if len > 0 && len <= WORD => Ok(AbiType::FixedBytes(len)),
+				_ => Err(unsupported()),
+			},
+		}
+	}
+}
+
+impl<'a> Deserialize<'a> for AbiType {
+	fn deserialize<D>(deserializer: D) -> Result<AbiType, D::Error>
+	where
+		D: Deserializer<'a>,
+	{
+		String::deserialize(deserializer)?
+			.parse()
+			.map_err(D::Error::custom)
+	}
+}
+
+/// Value decoded from ABI encoded data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum AbiValue {
+	/// Unsigned integer, as a quantity
+	Uint(U256),
+	/// Address
+	Address(H160),
+	/// Boolean
+	Bool(bool),
+	/// String
+	String(String),
+	/// Dynamic or fixed size bytes
+	Bytes(Bytes),
+	/// Elements of a dynamic or fixed size array
+	Array(Vec<AbiValue>),
+}
+
+impl AbiValue {
+	/// Decodes `data`, such as the return data of a call, as a sequence of values of
+	/// the given `types`. Returns `None` for data not encoding such values.
+	pub fn decode(types: &[AbiType], data: &[u8]) -> Option<Vec<AbiValue>> {
+		// Offsets may point to the same tail, letting small data encode values growing
+		// exponentially with their nesting: the decoded values are limited to one per
+		// word of data, fixed size arrays aside as they hold at least one value.
+		let mut elements = data.len() / WORD;
+		decode_sequence(types.iter(), data, &mut elements, 0)
+	}
+}
+
+/// Decodes a sequence of values of the given `types` at the given nesting `depth`,
+/// each value but fixed size arrays drawing one of the remaining `elements`.
+fn decode_sequence<'a>(
+	types: impl Iterator<Item = &'a AbiType>,
+	data: &[u8],
+	elements: &mut usize,
+	depth: usize,
+) -> Option<Vec<AbiValue>> {
+	if depth > MAX_DEPTH {
+		return None;
+	}
+	let mut values = Vec::new();
+	let mut head = 0usize;
+	for ty in types {
+		if !matches!(ty, AbiType::FixedArray(..)) {
+			*elements = elements.checked_sub(1)?;
+		}
+		let value = if ty.is_dynamic() {
+			let offset = word_usize(data, head)?;
+			decode_value(ty, data.get(offset..)?, elements, depth)?
+		} else {
+			decode_value(ty, data.get(head..)?, elements, depth)?
+		};
+		values.push(value);
+		head = head.checked_add(ty.head_size())?;
+	}
+	Some(values)
+}
+
+fn decode_value(ty: &AbiType, data: &[u8], elements: &mut usize, depth: usize) -> Option<AbiValue> {
+	match ty {
+		AbiType::Uint(bits) => {
+			let word = word(data, 0)?;
+			padded(word, WORD - bits / 8)?;
+			Some(AbiValue::Uint(U256::from_big_endian(word)))
+		}
+		AbiType::Address => {
+			let word = word(data, 0)?;
+			Some(AbiValue::Address(H160::from_slice(padded(word, 12)?)))
+		}
+		AbiType::Bool => match padded(word(data, 0)?, WORD - 1)? {
+			[0] => Some(AbiValue::Bool(false)),
+			[1] => Some(AbiValue::Bool(true)),
+			_ => None,
+		},
+		AbiType::FixedBytes(len) => {
+			let word = word(data, 0)?;
+			if word[*len..].iter().any(|byte| *byte != 0) {
+				return None;
+			}
+			Some(AbiValue::Bytes(Bytes(word[..*len].to_vec())))
+		}
+		AbiType::Bytes | AbiType::String => {
+			let len = word_usize(data, 0)?;
+			let bytes = data.get(WORD..WORD.checked_add(len)?)?.to_vec();
+			match ty {
+				AbiType::String => String::from_utf8(bytes).ok().map(AbiValue::String),
+				_ => Some(AbiValue::Bytes(Bytes(bytes))),
+			}
+		}
+		AbiType::Array(inner) => {
+			let len = word_usize(data, 0)?;
+			let types = std::iter::repeat(&**inner).take(len);
+			decode_sequence(types, data.get(WORD..)?, elements, depth + 1).map(AbiValue::Array)
+		}
+		AbiType::FixedArray(_, 0) => None,
+		AbiType::FixedArray(inner, len) => {
+			let types = std::iter::repeat(&**inner).take(*len);
+			decode_sequence(types, data, elements, depth + 1).map(AbiValue::Array)
+		}
+	}
+}
+
+fn word(data: &[u8], at: usize) -> Option<&[u8]> {
+	data.get(at..at.checked_add(WORD)?)
+}
+
+/// The bytes of `word` after its `padding` leading bytes, which must be zero.
+fn padded(word: &[u8], padding: usize) -> Option<&[u8]> {
+	if word[..padding].iter().any(|byte| *byte != 0) {
+		return None;
+	}
+	Some(&word[padding..])
+}
+
+/// A length or an offset, which cannot exceed the length of the data.
+fn word_usize(data: &[u8], at: usize) -> Option<usize> {
+	let value = U256::from_big_endian(word(data, at)?);
+	if value > U256::from(data.len()) {
+		return None;
+	}
+	Some(value.as_usize())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rustc_hex::FromHex;
+
+	fn types(types: &[&str]) -> Vec<AbiType> {
+		types.iter().map(|ty| ty.parse().unwrap()).collect()
+	}
+
+	fn words(words: &[&str]) -> Vec<u8> {
+		words
+			.iter()
+			.flat_map(|word| format!("{:0>64}", word).from_hex::<Vec<u8>>().unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn abi_types_parse() {
+		assert_eq!("uint".parse(), Ok(AbiType::Uint(256)));
+		assert_eq!("uint8".parse(), Ok(AbiType::Uint(8)));
+		assert_eq!("bytes32".parse(), Ok(AbiType::FixedBytes(32)));
+		assert_eq!(
+			"address[][2]".parse(),
+			Ok(AbiType::FixedArray(
+				Box::new(AbiType::Array(Box::new(AbiType::Address))),
+				2
+			))
+		);
+		for unsupported in [
+			"uint7",
+			"uint264",
+			"bytes33",
+			"int256",
+			"(uint256,bool)",
+			"bool[x]",
+			"bool[0]",
+			"uint256[][][][][][][][][]",
+		] {
+			assert!(unsupported.parse::<AbiType>().is_err());
+		}
+	}
+
+	#[test]
+	fn decodes_static_values() {
+		let address = H160::repeat_byte(0xaa);
+		let data = words(&[
+			"2a",
+			"000000000000000000000000aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+			"1",
+		]);
+
+		let values = AbiValue::decode(&types(&["uint256", "address", "bool"]), &data).unwrap();
+		assert_eq!(
+			values,
+			vec![
+				AbiValue::Uint(U256::from(42)),
+				AbiValue::Address(address),
+				AbiValue::Bool(true),
+			]
+		);
+		assert_eq!(
+			serde_json::to_string(&values).unwrap(),
+			r#"["0x2a","0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",true]"#
+		);
+
+		// Too short, or out of the range of the type.
+		assert_eq!(AbiValue::decode(&types(&["uint256"]), &data[..31]), None);
+		assert_eq!(AbiValue::decode(&types(&["uint8"]), &words(&["100"])), None);
+		assert_eq!(AbiValue::decode(&types(&["bool"]), &words(&["2"])), None);
+	}
+
+	#[test]
+	fn decodes_dynamic_values() {
+		// ("hi", [1, 2], 7)
+		let data = words(&[
+			"60",
+			"a0",
+			"7",
+			"2",
+			"6869000000000000000000000000000000000000000000000000000000000000",
+			"2",
+			"1",
+			"2",
+		]);
+
+		let values = AbiValue::decode(&types(&["string", "uint256[]", "uint8"]), &data).unwrap();
+		assert_eq!(
+			values,
+			vec![
+				AbiValue::String("hi".into()),
+				AbiValue::Array(vec![
+					AbiValue::Uint(U256::from(1)),
+					AbiValue::Uint(U256::from(2))
+				]),
+				AbiValue::Uint(U256::from(7)),
+			]
+		);
+
+		// An offset past the data.
+		let mut data = data;
+		data[31] = 0xff;
+		assert_eq!(AbiValue::decode(&types(&["string"]), &data), None);
+	}
+
+	#[test]
+	fn decoding_is_bounded_by_the_data() {
+		// [[1, 2]]
+		let data = words(&["20", "1", "20", "2", "1", "2"]);
+		assert_eq!(
+			AbiValue::decode(&types(&["uint256[][]"]), &data),
+			Some(vec![AbiValue::Array(vec![AbiValue::Array(vec![
+				AbiValue::Uint(U256::from(1)),
+				AbiValue::Uint(U256::from(2))
+			])])])
+		);
+
+		// Static fixed size arrays are not counted.
+		assert_eq!(
+			AbiValue::decode(&types(&["uint8[2]"]), &words(&["1", "2"])),
+			Some(vec![AbiValue::Array(vec![
+				AbiValue::Uint(U256::from(1)),
+				AbiValue::Uint(U256::from(2))
+			])])
+		);
+
+		// Four offsets to the same inner array: more values than words of data.
+		let data = words(&["20", "4", "80", "80", "80", "80", "4", "1", "2", "3", "4"]);
+		assert_eq!(AbiValue::decode(&types(&["uint256[][]"]), &data), None);
+
+		// Nested deeper than a type can be parsed.
+		let data = words(&["1"]);
+		let mut ty = AbiType::Uint(256);
+		for _ in 0..MAX_DEPTH {
+			ty = AbiType::FixedArray(Box::new(ty), 1);
+		}
+		assert!(AbiValue::decode(&[ty.clone()], &data).is_some());
+		let ty = AbiType::FixedArray(Box::new(ty), 1);
+		assert_eq!(AbiValue::decode(&[ty], &data), None);
+	}
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use ethereum_types::U256;
+use serde::Serialize;
+
+/// Data of the error of a state read at a block the node pruned the state of,
+/// telling clients to route the request to an archive node.
+#[derive(Debug, PartialEq, Eq, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveHint {
+	/// Always true, the state is only available from an archive node
+	pub requires_archive: bool,
+	/// First block with state still available on this node
+	pub earliest_available_block: U256,
+}
+
+impl ArchiveHint {
+	pub fn new(earliest_available_block: U256) -> Self {
+		Self {
+			requires_archive: true,
+			earliest_available_block,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn archive_hint_serialize() {
+		assert_eq!(
+			serde_json::to_string(&ArchiveHint::new(U256::from(7))).unwrap(),
+			r#"{"requiresArchive":true,"earliestAvailableBlock":"0x7"}"#
+		);
+	}
+}
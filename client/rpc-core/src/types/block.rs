@@ -23,7 +23,7 @@ use ethereum_types::{Bloom as H2048, H160, H256, H64, U256};
 use serde::{ser::Error, Serialize, Serializer};
 
 /// Block Transactions
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BlockTransactions {
 	/// Only hashes
 	Hashes(Vec<H256>),
@@ -44,7 +44,7 @@ impl Serialize for BlockTransactions {
 }
 
 /// Block representation
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Block {
 	/// Header of the block
@@ -58,9 +58,23 @@ pub struct Block {
 	pub transactions: BlockTransactions,
 	/// Size in bytes
 	pub size: Option<U256>,
-	/// Base Fee for post-EIP1559 blocks.
+	/// Withdrawals for post-Shanghai blocks.
 	#[serde(skip_serializing_if = "Option::is_none")]
-	pub base_fee_per_gas: Option<U256>,
+	pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+/// Validator withdrawal. Frontier blocks do not include any.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+	/// Index of the withdrawal
+	pub index: U256,
+	/// Index of the withdrawing validator
+	pub validator_index: U256,
+	/// Recipient address
+	pub address: H160,
+	/// Amount in Gwei
+	pub amount: U256,
 }
 
 /// Block header representation.
@@ -98,10 +112,18 @@ pub struct Header {
 	pub timestamp: U256,
 	/// Difficulty
 	pub difficulty: U256,
+	/// Mix hash
+	pub mix_hash: H256,
 	/// Nonce
 	pub nonce: Option<H64>,
 	/// Size in bytes
 	pub size: Option<U256>,
+	/// Base Fee for post-EIP1559 blocks.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub base_fee_per_gas: Option<U256>,
+	/// Withdrawals root hash for post-Shanghai blocks
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub withdrawals_root: Option<H256>,
 }
 
 /// Block representation with additional info.
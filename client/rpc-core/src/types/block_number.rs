@@ -23,6 +23,8 @@ use serde::{
 };
 use std::fmt;
 
+use crate::types::{format_quantity, parse_quantity};
+
 /// Represents rpc api block number param.
 #[derive(Debug, Copy, PartialEq, Clone, Hash, Eq)]
 pub enum BlockNumber {
@@ -47,6 +49,10 @@ pub enum BlockNumber {
 	Safe,
 	/// The most recent crypto-economically secure block.
 	Finalized,
+	/// Unix time in seconds, only supported by eth_getLogs: resolved to the first
+	/// block produced at or after it as `fromBlock`, to the last one produced at or
+	/// before it as `toBlock`.
+	Timestamp(u64),
 }
 
 impl Default for BlockNumber {
@@ -88,12 +94,15 @@ impl Serialize for BlockNumber {
 				"{{ 'hash': '{}', 'requireCanonical': '{}'  }}",
 				hash, require_canonical
 			)),
-			BlockNumber::Num(ref x) => serializer.serialize_str(&format!("0x{:x}", x)),
+			BlockNumber::Num(x) => serializer.serialize_str(&format_quantity(x)),
 			BlockNumber::Latest => serializer.serialize_str("latest"),
 			BlockNumber::Earliest => serializer.serialize_str("earliest"),
 			BlockNumber::Pending => serializer.serialize_str("pending"),
 			BlockNumber::Safe => serializer.serialize_str("safe"),
 			BlockNumber::Finalized => serializer.serialize_str("finalized"),
+			BlockNumber::Timestamp(x) => {
+				serializer.serialize_str(&format!("{{ 'timestamp': '{}' }}", format_quantity(x)))
+			}
 		}
 	}
 }
@@ -114,8 +123,8 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 	where
 		V: MapAccess<'a>,
 	{
-		let (mut require_canonical, mut block_number, mut block_hash) =
-			(false, None::<u64>, None::<H256>);
+		let (mut require_canonical, mut block_number, mut block_hash, mut timestamp) =
+			(false, None::<u64>, None::<H256>, None::<u64>);
 
 		loop {
 			let key_str: Option<String> = visitor.next_key()?;
@@ -124,18 +133,10 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 				Some(key) => match key.as_str() {
 					"blockNumber" => {
 						let value: String = visitor.next_value()?;
-						if let Some(stripped) = value.strip_prefix("0x") {
-							let number = u64::from_str_radix(stripped, 16).map_err(|e| {
-								Error::custom(format!("Invalid block number: {}", e))
-							})?;
-
-							block_number = Some(number);
-							break;
-						} else {
-							return Err(Error::custom(
-								"Invalid block number: missing 0x prefix".to_string(),
-							));
-						}
+						let number = parse_quantity(&value)
+							.map_err(|e| Error::custom(format!("Invalid block number: {}", e)))?;
+						block_number = Some(number);
+						break;
 					}
 					"blockHash" => {
 						block_hash = Some(visitor.next_value()?);
@@ -143,6 +144,12 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 					"requireCanonical" => {
 						require_canonical = visitor.next_value()?;
 					}
+					"timestamp" => {
+						let value: String = visitor.next_value()?;
+						let seconds = parse_quantity(&value)
+							.map_err(|e| Error::custom(format!("Invalid timestamp: {}", e)))?;
+						timestamp = Some(seconds);
+					}
 					key => return Err(Error::custom(format!("Unknown key: {}", key))),
 				},
 				None => break,
@@ -160,6 +167,10 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 			});
 		}
 
+		if let Some(seconds) = timestamp {
+			return Ok(BlockNumber::Timestamp(seconds));
+		}
+
 		Err(Error::custom("Invalid input"))
 	}
 
@@ -173,7 +184,7 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 			"pending" => Ok(BlockNumber::Pending),
 			"safe" => Ok(BlockNumber::Safe),
 			"finalized" => Ok(BlockNumber::Finalized),
-			_ if value.starts_with("0x") => u64::from_str_radix(&value[2..], 16)
+			_ if value.starts_with("0x") => parse_quantity(value)
 				.map(BlockNumber::Num)
 				.map_err(|e| Error::custom(format!("Invalid block number: {}", e))),
 			_ => value.parse::<u64>().map(BlockNumber::Num).map_err(|_| {
@@ -233,4 +244,12 @@ mod tests {
 		assert_eq!(match_block_number(bn_tag_finalized).unwrap(), 999);
 		assert_eq!(match_block_number(bn_tag_pending).unwrap(), 1001);
 	}
+
+	#[test]
+	fn block_timestamp_deserialize() {
+		let timestamp: BlockNumber =
+			serde_json::from_str(r#"{"timestamp": "0x6553f100"}"#).unwrap();
+		assert_eq!(timestamp, BlockNumber::Timestamp(1_700_000_000));
+		assert!(serde_json::from_str::<BlockNumber>(r#"{"timestamp": "soon"}"#).is_err());
+	}
 }
@@ -18,13 +18,15 @@
 
 //! Serializable wrapper around vector of bytes
 
-use rustc_hex::{FromHex, ToHex};
+use rustc_hex::FromHex;
 use serde::{
 	de::{Error, Visitor},
 	Deserialize, Deserializer, Serialize, Serializer,
 };
 use std::fmt;
 
+use crate::types::format_hex;
+
 /// Wrapper structure around vector of bytes.
 #[derive(Debug, PartialEq, Eq, Default, Hash, Clone)]
 pub struct Bytes(pub Vec<u8>);
@@ -57,9 +59,7 @@ impl Serialize for Bytes {
 	where
 		S: Serializer,
 	{
-		let mut serialized = "0x".to_owned();
-		serialized.push_str(self.0.to_hex::<String>().as_ref());
-		serializer.serialize_str(serialized.as_ref())
+		serializer.serialize_str(&format_hex(&self.0))
 	}
 }
 
@@ -16,15 +16,16 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::types::Bytes;
+use std::collections::BTreeMap;
+
+use crate::types::{hex_format::deserialize_quantity, Bytes};
 use ethereum::AccessListItem;
-use ethereum_types::{H160, U256};
+use ethereum_types::{H160, H256, U256};
 use serde::Deserialize;
 
 /// Call request
 #[derive(Debug, Default, PartialEq, Deserialize, Clone)]
-#[serde(deny_unknown_fields)]
-#[serde(rename_all = "camelCase")]
+#[serde(try_from = "CallRequestFields")]
 pub struct CallRequest {
 	/// From
 	pub from: Option<H160>,
@@ -40,13 +41,178 @@ pub struct CallRequest {
 	pub gas: Option<U256>,
 	/// Value
 	pub value: Option<U256>,
-	/// Data
+	/// Data, sent as either `input` or `data`
 	pub data: Option<Bytes>,
 	/// Nonce
 	pub nonce: Option<U256>,
 	/// AccessList
 	pub access_list: Option<Vec<AccessListItem>>,
 	/// EIP-2718 type
-	#[serde(rename = "type")]
 	pub transaction_type: Option<U256>,
 }
+
+/// Call request as sent, with both names of its data.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+struct CallRequestFields {
+	from: Option<H160>,
+	to: Option<H160>,
+	gas_price: Option<U256>,
+	max_fee_per_gas: Option<U256>,
+	max_priority_fee_per_gas: Option<U256>,
+	#[serde(default, deserialize_with = "deserialize_quantity")]
+	gas: Option<U256>,
+	#[serde(default, deserialize_with = "deserialize_quantity")]
+	value: Option<U256>,
+	input: Option<Bytes>,
+	data: Option<Bytes>,
+	#[serde(default, deserialize_with = "deserialize_quantity")]
+	nonce: Option<U256>,
+	access_list: Option<Vec<AccessListItem>>,
+	#[serde(rename = "type")]
+	transaction_type: Option<U256>,
+}
+
+impl TryFrom<CallRequestFields> for CallRequest {
+	type Error = String;
+
+	fn try_from(fields: CallRequestFields) -> Result<Self, Self::Error> {
+		let data = match (fields.input, fields.data) {
+			(Some(input), Some(data)) if input != data => {
+				return Err(
+					"both \"data\" and \"input\" are set and not equal, please use \"input\" \
+					 to pass transaction call data"
+						.to_string(),
+				);
+			}
+			(Some(input), _) => Some(input),
+			(None, data) => data,
+		};
+		Ok(CallRequest {
+			from: fields.from,
+			to: fields.to,
+			gas_price: fields.gas_price,
+			max_fee_per_gas: fields.max_fee_per_gas,
+			max_priority_fee_per_gas: fields.max_priority_fee_per_gas,
+			gas: fields.gas,
+			value: fields.value,
+			data,
+			nonce: fields.nonce,
+			access_list: fields.access_list,
+			transaction_type: fields.transaction_type,
+		})
+	}
+}
+
+/// Block context overrides of a simulated call.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockOverrides {
+	/// Block number
+	pub number: Option<U256>,
+	/// Block timestamp, in seconds
+	pub time: Option<U256>,
+	/// Base fee per gas
+	pub base_fee: Option<U256>,
+}
+
+/// Account state overrides of a simulated call.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStateOverride {
+	/// Balance
+	pub balance: Option<U256>,
+	/// Nonce
+	pub nonce: Option<U256>,
+	/// Code, replacing the one of the account
+	pub code: Option<Bytes>,
+	/// Storage, replacing the whole storage of the account
+	pub state: Option<BTreeMap<H256, H256>>,
+	/// Storage slots, replacing the ones of the account
+	pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn block_overrides_deserialize() {
+		let overrides: BlockOverrides =
+			serde_json::from_str(r#"{"number":"0x10","time":"0x64","baseFee":"0x7"}"#).unwrap();
+		assert_eq!(
+			overrides,
+			BlockOverrides {
+				number: Some(U256::from(0x10)),
+				time: Some(U256::from(100)),
+				base_fee: Some(U256::from(7)),
+			}
+		);
+		assert!(serde_json::from_str::<BlockOverrides>(r#"{"coinbase":"0x00"}"#).is_err());
+	}
+
+	#[test]
+	fn state_override_deserialize() {
+		let state_override: CallStateOverride = serde_json::from_str(
+			r#"{"code":"0x6001","stateDiff":{"0x0000000000000000000000000000000000000000000000000000000000000001":"0x000000000000000000000000000000000000000000000000000000000000002a"}}"#,
+		)
+		.unwrap();
+		assert_eq!(
+			state_override,
+			CallStateOverride {
+				code: Some(Bytes(vec![0x60, 0x01])),
+				state_diff: Some(
+					[(H256::from_low_u64_be(1), H256::from_low_u64_be(42))]
+						.into_iter()
+						.collect()
+				),
+				..Default::default()
+			}
+		);
+		assert!(serde_json::from_str::<CallStateOverride>(r#"{"storage":{}}"#).is_err());
+	}
+
+	#[test]
+	fn call_request_data_deserialize() {
+		let data = |json: &str| serde_json::from_str::<CallRequest>(json).map(|r| r.data);
+		let expected = Some(Bytes(vec![0x12, 0x34]));
+
+		assert_eq!(data(r#"{"data":"0x1234"}"#).unwrap(), expected);
+		assert_eq!(data(r#"{"input":"0x1234"}"#).unwrap(), expected);
+		assert_eq!(
+			data(r#"{"input":"0x1234","data":"0x1234"}"#).unwrap(),
+			expected
+		);
+		assert_eq!(data(r#"{}"#).unwrap(), None);
+		let err = data(r#"{"input":"0x1234","data":"0x5678"}"#).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("both \"data\" and \"input\" are set"));
+		assert!(serde_json::from_str::<CallRequest>(r#"{"inputs":"0x"}"#).is_err());
+	}
+
+	#[test]
+	fn call_request_quantities_deserialize() {
+		let request: CallRequest =
+			serde_json::from_str(r#"{"gas":"0x5208","value":"0x1","nonce":"0x0"}"#).unwrap();
+		assert_eq!(request.gas, Some(U256::from(21_000)));
+		assert_eq!(request.value, Some(U256::one()));
+		assert_eq!(request.nonce, Some(U256::zero()));
+
+		for field in ["gas", "value", "nonce"] {
+			let json = format!(r#"{{"{}":"0x01"}}"#, field);
+			let request = serde_json::from_str::<CallRequest>(&json);
+			if cfg!(feature = "strict_quantities") {
+				assert!(request
+					.unwrap_err()
+					.to_string()
+					.contains("0x01 has leading zeros"));
+			} else {
+				assert!(request.is_ok());
+			}
+		}
+	}
+}
@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::Bytes;
+use serde::Serialize;
+
+/// Outcome of one of the calls of a multicall.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CallResult {
+	/// Whether the call succeeded
+	pub success: bool,
+	/// Returned data, or revert data of a failed call
+	pub return_data: Bytes,
+	/// Error message of a failed call
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
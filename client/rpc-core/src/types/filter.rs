@@ -26,7 +26,9 @@ use serde_json::{from_value, Value};
 use std::{
 	collections::BTreeMap,
 	sync::{Arc, Mutex},
+	time::Instant,
 };
+use tiny_keccak::{Hasher, Keccak};
 
 use crate::types::{BlockNumber, Log};
 
@@ -65,8 +67,42 @@ where
 	}
 }
 
+impl<T> Serialize for VariadicValue<T>
+where
+	T: DeserializeOwned + Serialize,
+{
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match self {
+			VariadicValue::Single(value) => value.serialize(serializer),
+			VariadicValue::Multiple(values) => values.serialize(serializer),
+			VariadicValue::Null => serializer.serialize_none(),
+		}
+	}
+}
+
 /// Filter Address
 pub type FilterAddress = VariadicValue<H160>;
+
+impl FilterAddress {
+	/// The same address set, listing each address once in order of first appearance.
+	pub fn deduplicated(&self) -> Self {
+		match self {
+			VariadicValue::Multiple(addresses) => {
+				let mut unique = Vec::with_capacity(addresses.len());
+				for address in addresses {
+					if !unique.contains(address) {
+						unique.push(*address);
+					}
+				}
+				VariadicValue::Multiple(unique)
+			}
+			other => other.clone(),
+		}
+	}
+}
 /// Topic, supports `A` | `null` | `[A,B,C]` | `[A,[B,C]]` | [null,[B,C]] | [null,[null,C]]
 pub type Topic = VariadicValue<Option<VariadicValue<Option<H256>>>>;
 /// FlatTopic, simplifies the matching logic.
@@ -77,7 +113,7 @@ pub type BloomFilter<'a> = Vec<Option<Bloom>>;
 impl From<&VariadicValue<H160>> for Vec<Option<Bloom>> {
 	fn from(address: &VariadicValue<H160>) -> Self {
 		let mut blooms = BloomFilter::new();
-		match address {
+		match address.deduplicated() {
 			VariadicValue::Single(address) => {
 				let bloom: Bloom = BloomInput::Raw(address.as_ref()).into();
 				blooms.push(Some(bloom))
@@ -131,7 +167,7 @@ impl From<&VariadicValue<Option<H256>>> for Vec<Option<Bloom>> {
 }
 
 /// Filter
-#[derive(Debug, PartialEq, Clone, Deserialize, Eq, Hash)]
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, Eq, Hash)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Filter {
@@ -141,6 +177,8 @@ pub struct Filter {
 	pub to_block: Option<BlockNumber>,
 	/// Block hash
 	pub block_hash: Option<H256>,
+	/// Transaction hash, restricting the logs to those of a single transaction
+	pub transaction_hash: Option<H256>,
 	/// Address
 	pub address: Option<FilterAddress>,
 	/// Topics
@@ -157,7 +195,9 @@ pub struct FilteredParams {
 
 impl FilteredParams {
 	pub fn new(f: Option<Filter>) -> Self {
-		if let Some(f) = f {
+		if let Some(mut f) = f {
+			// Duplicated addresses match the same logs.
+			f.address = f.address.map(|address| address.deduplicated());
 			return FilteredParams {
 				filter: Some(f.clone()),
 				flat_topics: {
@@ -400,16 +440,20 @@ impl FilteredParams {
 					{
 						new_multi.pop();
 					}
-					// We can discard right away any logs with lesser topics than the filter.
+					// Logs with lesser topics than the filter constrains do not match this
+					// combination, like anonymous events missing the signature topic0.
 					if new_multi.len() > log.topics.len() {
 						out = false;
-						break;
+						continue;
 					}
 					let replaced: Option<Vec<H256>> =
 						self.replace(log, VariadicValue::Multiple(new_multi));
-					if let Some(replaced) = replaced {
-						out = false;
-						if log.topics.starts_with(&replaced[..]) {
+					match replaced {
+						Some(replaced) if !log.topics.starts_with(&replaced[..]) => {
+							out = false;
+						}
+						// Matching, or leaving all the topics unconstrained.
+						_ => {
 							out = true;
 							break;
 						}
@@ -448,6 +492,87 @@ impl Serialize for FilterChanges {
 	}
 }
 
+/// Opaque position to resume a paginated `eth_getLogsPage` query from.
+///
+/// Serialized as a hex string carrying a fingerprint of the filter it was
+/// issued for, the block to resume scanning at and the first log index of that
+/// block not returned yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogsCursor {
+	filter_hash: u64,
+	pub block_number: u64,
+	pub log_index: u32,
+}
+
+impl LogsCursor {
+	pub fn new(filter: &Filter, block_number: u64, log_index: u32) -> Self {
+		Self {
+			filter_hash: Self::filter_hash(filter),
+			block_number,
+			log_index,
+		}
+	}
+
+	/// Whether the cursor was issued for the given filter.
+	pub fn matches(&self, filter: &Filter) -> bool {
+		self.filter_hash == Self::filter_hash(filter)
+	}
+
+	/// First 8 bytes of the keccak of the JSON encoding of `filter`, which unlike
+	/// `DefaultHasher` stays the same across builds of the node.
+	fn filter_hash(filter: &Filter) -> u64 {
+		let encoded = serde_json::to_vec(filter).expect("filters encode to JSON; qed");
+		let mut hash = [0u8; 32];
+		let mut keccak = Keccak::v256();
+		keccak.update(&encoded);
+		keccak.finalize(&mut hash);
+		let mut prefix = [0u8; 8];
+		prefix.copy_from_slice(&hash[..8]);
+		u64::from_be_bytes(prefix)
+	}
+}
+
+impl Serialize for LogsCursor {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(&format!(
+			"0x{:016x}{:016x}{:08x}",
+			self.filter_hash, self.block_number, self.log_index
+		))
+	}
+}
+
+impl<'a> Deserialize<'a> for LogsCursor {
+	fn deserialize<D>(deserializer: D) -> Result<LogsCursor, D::Error>
+	where
+		D: Deserializer<'a>,
+	{
+		let value = String::deserialize(deserializer)?;
+		let hex = value
+			.strip_prefix("0x")
+			.filter(|hex| hex.len() == 40 && hex.is_ascii())
+			.ok_or_else(|| D::Error::custom("Invalid logs cursor"))?;
+		let invalid = |_| D::Error::custom("Invalid logs cursor");
+		Ok(LogsCursor {
+			filter_hash: u64::from_str_radix(&hex[0..16], 16).map_err(invalid)?,
+			block_number: u64::from_str_radix(&hex[16..32], 16).map_err(invalid)?,
+			log_index: u32::from_str_radix(&hex[32..40], 16).map_err(invalid)?,
+		})
+	}
+}
+
+/// A page of logs returned by `eth_getLogsPage`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsPage {
+	/// Logs in this page.
+	pub logs: Vec<Log>,
+	/// Cursor to request the next page with, `None` once the range is exhausted.
+	pub next_cursor: Option<LogsCursor>,
+}
+
 #[derive(Debug, Clone)]
 pub enum FilterType {
 	Block,
@@ -460,6 +585,56 @@ pub struct FilterPoolItem {
 	pub last_poll: BlockNumber,
 	pub filter_type: FilterType,
 	pub at_block: u64,
+	/// When the filter was created or last polled.
+	pub last_poll_at: Instant,
+}
+
+/// Kind of an installed filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterKind {
+	Block,
+	PendingTransaction,
+	Log,
+}
+
+impl From<&FilterType> for FilterKind {
+	fn from(filter_type: &FilterType) -> Self {
+		match filter_type {
+			FilterType::Block => FilterKind::Block,
+			FilterType::PendingTransaction => FilterKind::PendingTransaction,
+			FilterType::Log(_) => FilterKind::Log,
+		}
+	}
+}
+
+/// Filter of the filter pool, as listed by `debug_getFilters`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterInfo {
+	/// Id of the filter
+	pub id: U256,
+	/// Kind of the filter
+	#[serde(rename = "type")]
+	pub kind: FilterKind,
+	/// Block the next poll of the filter returns changes from
+	pub last_poll_block: U256,
+	/// Seconds since the filter was created or last polled
+	pub idle_seconds: u64,
+}
+
+impl FilterInfo {
+	/// Listing of the filters of `pool` at `now`, by id.
+	pub fn list(pool: &BTreeMap<U256, FilterPoolItem>, now: Instant) -> Vec<FilterInfo> {
+		pool.iter()
+			.map(|(id, item)| FilterInfo {
+				id: *id,
+				kind: (&item.filter_type).into(),
+				last_poll_block: item.last_poll.to_min_block_num().unwrap_or_default().into(),
+				idle_seconds: now.saturating_duration_since(item.last_poll_at).as_secs(),
+			})
+			.collect()
+	}
 }
 
 /// On-memory stored filters created through the `eth_newFilter` RPC.
@@ -493,6 +668,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: Some(VariadicValue::Single(test_address)),
 			topics: None,
 		};
@@ -510,6 +686,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: Some(VariadicValue::Single(test_address)),
 			topics: None,
 		};
@@ -534,6 +711,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: None,
 			topics: Some(VariadicValue::Multiple(vec![
 				Some(VariadicValue::Single(Some(topic1))),
@@ -567,6 +745,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: None,
 			topics: Some(VariadicValue::Multiple(vec![
 				Some(VariadicValue::Single(Some(topic1))),
@@ -591,6 +770,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: None,
 			topics: Some(VariadicValue::Multiple(vec![])),
 		};
@@ -622,6 +802,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: Some(VariadicValue::Single(test_address)),
 			topics: Some(VariadicValue::Multiple(vec![
 				Some(VariadicValue::Single(Some(topic1))),
@@ -656,6 +837,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: Some(VariadicValue::Single(test_address)),
 			topics: Some(VariadicValue::Multiple(vec![
 				Some(VariadicValue::Single(Some(topic1))),
@@ -686,6 +868,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: None,
 			topics: Some(VariadicValue::Multiple(vec![
 				None,
@@ -716,6 +899,7 @@ mod tests {
 			from_block: None,
 			to_block: None,
 			block_hash: None,
+			transaction_hash: None,
 			address: None,
 			topics: Some(VariadicValue::Multiple(vec![
 				None,
@@ -734,4 +918,195 @@ mod tests {
 			&topics_bloom
 		));
 	}
+
+	#[test]
+	fn logs_cursor_roundtrips_and_is_bound_to_its_filter() {
+		let filter = Filter {
+			from_block: Some(BlockNumber::Num(1)),
+			to_block: Some(BlockNumber::Num(100)),
+			block_hash: None,
+			transaction_hash: None,
+			address: None,
+			topics: None,
+		};
+		let other = Filter {
+			to_block: Some(BlockNumber::Num(200)),
+			..filter.clone()
+		};
+
+		let cursor = LogsCursor::new(&filter, 42, 7);
+		let encoded = serde_json::to_value(&cursor).unwrap();
+		let decoded: LogsCursor = serde_json::from_value(encoded).unwrap();
+
+		assert_eq!(decoded, cursor);
+		assert_eq!(decoded.block_number, 42);
+		assert_eq!(decoded.log_index, 7);
+		assert!(decoded.matches(&filter));
+		assert!(!decoded.matches(&other));
+
+		assert!(serde_json::from_str::<LogsCursor>(r#""0x1234""#).is_err());
+	}
+
+	#[test]
+	fn logs_cursor_filter_hash_is_stable() {
+		let filter = Filter {
+			from_block: None,
+			to_block: None,
+			block_hash: None,
+			transaction_hash: None,
+			address: Some(VariadicValue::Single(H160::repeat_byte(0xaa))),
+			topics: None,
+		};
+
+		// Keccak of the JSON encoding of the filter, prefixing the cursor.
+		assert_eq!(
+			serde_json::to_string(&filter).unwrap(),
+			r#"{"fromBlock":null,"toBlock":null,"blockHash":null,"transactionHash":null,"address":"0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa","topics":null}"#
+		);
+		assert_eq!(
+			serde_json::to_value(LogsCursor::new(&filter, 1, 2)).unwrap(),
+			"0xe649b0df943a3329000000000000000100000002"
+		);
+	}
+
+	#[test]
+	fn duplicated_addresses_match_once() {
+		let a = H160::repeat_byte(0xaa);
+		let b = H160::repeat_byte(0xbb);
+		let filter = Filter {
+			from_block: None,
+			to_block: None,
+			block_hash: None,
+			transaction_hash: None,
+			address: Some(VariadicValue::Multiple(vec![a, a, b])),
+			topics: None,
+		};
+
+		let params = FilteredParams::new(Some(filter.clone()));
+		assert_eq!(
+			params.filter.as_ref().and_then(|f| f.address.clone()),
+			Some(VariadicValue::Multiple(vec![a, b]))
+		);
+		assert_eq!(
+			FilteredParams::adresses_bloom_filter(&filter.address).len(),
+			2
+		);
+
+		let logs: Vec<Log> = [a, b, H160::repeat_byte(0xcc)]
+			.into_iter()
+			.map(|address| Log {
+				address,
+				topics: Vec::new(),
+				data: Default::default(),
+				block_hash: None,
+				block_number: None,
+				transaction_hash: None,
+				transaction_index: None,
+				log_index: None,
+				transaction_log_index: None,
+				removed: false,
+			})
+			.collect();
+		let matched: Vec<H160> = logs
+			.iter()
+			.filter(|log| params.filter_address(log))
+			.map(|log| log.address)
+			.collect();
+		assert_eq!(matched, vec![a, b]);
+	}
+
+	#[test]
+	fn anonymous_event_logs_match_by_their_topics() {
+		let signature = H256::repeat_byte(0x01);
+		let indexed = H256::repeat_byte(0x02);
+		let other = H256::repeat_byte(0x03);
+		// An anonymous event with a single indexed argument, and no signature topic0.
+		let log = Log {
+			address: H160::repeat_byte(0xaa),
+			topics: vec![indexed],
+			data: Default::default(),
+			block_hash: None,
+			block_number: None,
+			transaction_hash: None,
+			transaction_index: None,
+			log_index: None,
+			transaction_log_index: None,
+			removed: false,
+		};
+		let matches = |topics: Topic| {
+			FilteredParams::new(Some(Filter {
+				from_block: None,
+				to_block: None,
+				block_hash: None,
+				transaction_hash: None,
+				address: None,
+				topics: Some(topics),
+			}))
+			.filter_topics(&log)
+		};
+		let topic = |topic: H256| Some(VariadicValue::Single(Some(topic)));
+
+		assert!(matches(VariadicValue::Multiple(vec![topic(indexed)])));
+		assert!(matches(VariadicValue::Multiple(vec![None])));
+		assert!(matches(VariadicValue::Multiple(vec![topic(indexed), None])));
+		assert!(matches(VariadicValue::Multiple(vec![Some(
+			VariadicValue::Multiple(vec![Some(other), None])
+		)])));
+		assert!(!matches(VariadicValue::Multiple(vec![topic(signature)])));
+		// Constraining a position past its topics, as a signature-based filter would.
+		assert!(!matches(VariadicValue::Multiple(vec![
+			topic(signature),
+			topic(indexed)
+		])));
+		assert!(!matches(VariadicValue::Multiple(vec![
+			None,
+			topic(indexed)
+		])));
+		// Combinations constraining past the topics do not stop the others matching.
+		assert!(matches(VariadicValue::Multiple(vec![
+			topic(indexed),
+			Some(VariadicValue::Multiple(vec![Some(other), None]))
+		])));
+	}
+
+	#[test]
+	fn filter_pool_listing() {
+		let now = Instant::now();
+		let item = |filter_type: FilterType, last_poll: u64| FilterPoolItem {
+			last_poll: BlockNumber::Num(last_poll),
+			filter_type,
+			at_block: 1,
+			last_poll_at: now,
+		};
+		let mut pool = BTreeMap::new();
+		pool.insert(U256::from(2), item(FilterType::Block, 3));
+		pool.insert(
+			U256::from(1),
+			item(FilterType::Log(serde_json::from_str("{}").unwrap()), 1),
+		);
+
+		let later = now + std::time::Duration::from_secs(30);
+		let listing = FilterInfo::list(&pool, later);
+		assert_eq!(
+			listing,
+			vec![
+				FilterInfo {
+					id: U256::from(1),
+					kind: FilterKind::Log,
+					last_poll_block: U256::from(1),
+					idle_seconds: 30,
+				},
+				FilterInfo {
+					id: U256::from(2),
+					kind: FilterKind::Block,
+					last_poll_block: U256::from(3),
+					idle_seconds: 30,
+				},
+			]
+		);
+		assert_eq!(
+			serde_json::to_string(&listing[1]).unwrap(),
+			r#"{"id":"0x2","type":"block","lastPollBlock":"0x3","idleSeconds":30}"#
+		);
+	}
 }
@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Hex formatting shared by all RPC outputs, and parsing of quantity inputs.
+
+use ethereum_types::U256;
+use rustc_hex::ToHex;
+use serde::{
+	de::{Error, IntoDeserializer},
+	Deserialize, Deserializer,
+};
+
+/// Whether quantity inputs with leading zeros, which the spec does not allow but
+/// lenient parsing accepts, are rejected. Lenient unless the `strict_quantities`
+/// feature is enabled.
+const STRICT_QUANTITIES: bool = cfg!(feature = "strict_quantities");
+
+/// Formats data (bytes, addresses, hashes) as lowercase `0x`-prefixed hex, two
+/// digits per byte.
+pub fn format_hex(data: &[u8]) -> String {
+	format!("0x{}", data.to_hex::<String>())
+}
+
+/// Formats a quantity as lowercase `0x`-prefixed hex without leading zeros,
+/// zero being `0x0`.
+pub fn format_quantity<T: Into<U256>>(value: T) -> String {
+	format!("0x{:x}", value.into())
+}
+
+/// Parses a `0x`-prefixed hex quantity, e.g. a block number. With the
+/// `strict_quantities` feature, quantities with leading zeros other than `0x0` fail.
+pub fn parse_quantity(value: &str) -> Result<u64, String> {
+	parse_quantity_with(value, STRICT_QUANTITIES)
+}
+
+fn parse_quantity_with(value: &str, strict: bool) -> Result<u64, String> {
+	let digits = value
+		.strip_prefix("0x")
+		.ok_or_else(|| "missing 0x prefix".to_string())?;
+	if strict && has_leading_zeros(digits) {
+		return Err(format!("{} has leading zeros", value));
+	}
+	u64::from_str_radix(digits, 16).map_err(|e| e.to_string())
+}
+
+/// Deserializes an optional `U256` quantity, e.g. the gas of a call request, failing
+/// on leading zeros like `parse_quantity`.
+pub(crate) fn deserialize_quantity<'de, D>(deserializer: D) -> Result<Option<U256>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	deserialize_quantity_with(deserializer, STRICT_QUANTITIES)
+}
+
+fn deserialize_quantity_with<'de, D>(
+	deserializer: D,
+	strict: bool,
+) -> Result<Option<U256>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let value = match Option::<String>::deserialize(deserializer)? {
+		Some(value) => value,
+		None => return Ok(None),
+	};
+	if strict && value.strip_prefix("0x").map_or(false, has_leading_zeros) {
+		return Err(D::Error::custom(format!("{} has leading zeros", value)));
+	}
+	U256::deserialize(value.as_str().into_deserializer()).map(Some)
+}
+
+fn has_leading_zeros(digits: &str) -> bool {
+	digits.len() > 1 && digits.starts_with('0')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ethereum_types::{H160, H256};
+
+	#[test]
+	fn formats_data_and_quantities() {
+		let address = H160::from_low_u64_be(0xABCD);
+		assert_eq!(
+			format_hex(address.as_bytes()),
+			"0x000000000000000000000000000000000000abcd"
+		);
+
+		let hash = H256::repeat_byte(0xEF);
+		assert_eq!(
+			format_hex(hash.as_bytes()),
+			format!("0x{}", "ef".repeat(32))
+		);
+		assert_eq!(format_hex(&[]), "0x");
+
+		// Quantities drop leading zeros.
+		assert_eq!(
+			format_quantity(U256::from_big_endian(&[0x00, 0x0A, 0xBC])),
+			"0xabc"
+		);
+		assert_eq!(format_quantity(0u64), "0x0");
+	}
+
+	#[test]
+	fn strict_quantities_have_no_leading_zeros() {
+		assert_eq!(parse_quantity_with("0x01", false), Ok(1));
+		assert_eq!(
+			parse_quantity_with("0x01", true),
+			Err("0x01 has leading zeros".to_string())
+		);
+		assert_eq!(parse_quantity_with("0x0", true), Ok(0));
+		assert_eq!(parse_quantity_with("0x1", true), Ok(1));
+		assert_eq!(parse_quantity_with("0x1f", true), Ok(0x1f));
+		assert!(parse_quantity_with("0x", true).is_err());
+		assert!(parse_quantity_with("1", false).is_err());
+
+		let quantity = |value: &str, strict| {
+			deserialize_quantity_with(serde_json::Value::String(value.into()), strict)
+		};
+		assert_eq!(quantity("0x01", false).unwrap(), Some(U256::one()));
+		assert_eq!(
+			quantity("0x01", true).unwrap_err().to_string(),
+			"0x01 has leading zeros"
+		);
+		assert_eq!(quantity("0x0", true).unwrap(), Some(U256::zero()));
+		assert_eq!(quantity("0x1f", true).unwrap(), Some(U256::from(0x1f)));
+		assert_eq!(
+			deserialize_quantity_with(serde_json::Value::Null, true).unwrap(),
+			None
+		);
+	}
+}
@@ -22,6 +22,8 @@ use serde::{
 };
 use std::fmt;
 
+use crate::types::parse_quantity;
+
 /// Represents usize.
 #[derive(Debug, PartialEq)]
 pub struct Index(usize);
@@ -56,8 +58,8 @@ impl<'a> Visitor<'a> for IndexVisitor {
 		E: Error,
 	{
 		match value {
-			_ if value.starts_with("0x") => usize::from_str_radix(&value[2..], 16)
-				.map(Index)
+			_ if value.starts_with("0x") => parse_quantity(value)
+				.map(|index| Index(index as usize))
 				.map_err(|e| Error::custom(format!("Invalid index: {}", e))),
 			_ => value
 				.parse::<usize>()
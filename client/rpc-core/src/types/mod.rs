@@ -18,13 +18,17 @@
 
 //! RPC types
 
+mod abi;
 mod account_info;
+mod archive_hint;
 mod block;
 mod block_number;
 mod bytes;
 mod call_request;
+mod call_result;
 mod fee;
 mod filter;
+mod hex_format;
 mod index;
 mod log;
 mod receipt;
@@ -36,19 +40,23 @@ mod work;
 pub mod pubsub;
 
 pub use self::{
+	abi::{AbiType, AbiValue},
 	account_info::{AccountInfo, EthAccount, ExtAccountInfo, RecoveredAccount, StorageProof},
-	block::{Block, BlockTransactions, Header, Rich, RichBlock, RichHeader},
+	archive_hint::ArchiveHint,
+	block::{Block, BlockTransactions, Header, Rich, RichBlock, RichHeader, Withdrawal},
 	block_number::BlockNumber,
 	bytes::Bytes,
-	call_request::CallRequest,
+	call_request::{BlockOverrides, CallRequest, CallStateOverride},
+	call_result::CallResult,
 	fee::{FeeHistory, FeeHistoryCache, FeeHistoryCacheItem, FeeHistoryCacheLimit},
 	filter::{
-		Filter, FilterAddress, FilterChanges, FilterPool, FilterPoolItem, FilterType,
-		FilteredParams, Topic, VariadicValue,
+		Filter, FilterAddress, FilterChanges, FilterInfo, FilterKind, FilterPool, FilterPoolItem,
+		FilterType, FilteredParams, LogsCursor, LogsPage, Topic, VariadicValue,
 	},
+	hex_format::{format_hex, format_quantity, parse_quantity},
 	index::Index,
 	log::Log,
-	receipt::Receipt,
+	receipt::{PendingReceipt, Receipt},
 	sync::{
 		ChainStatus, EthProtocolInfo, PeerCount, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
 		Peers, PipProtocolInfo, SyncInfo, SyncStatus, TransactionStats,
@@ -19,8 +19,8 @@
 //! Pub-Sub types.
 
 use crate::types::{Filter, Log, RichHeader};
-use ethereum_types::H256;
-use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use ethereum_types::{H256, U256};
+use serde::{de::Error, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{from_value, Value};
 
 /// Subscription result.
@@ -34,6 +34,19 @@ pub enum Result {
 	TransactionHash(H256),
 	/// SyncStatus
 	SyncState(PubSubSyncStatus),
+	/// Reorg too deep to be followed log by log
+	Resync(Resync),
+}
+
+/// Signal of a logs subscription skipping the removed logs of a deep reorg, for the
+/// subscriber to query the logs of the new chain from `from_block` on.
+#[derive(Debug, Serialize, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Resync {
+	/// Number of blocks retracted by the reorg
+	pub retracted: U256,
+	/// First block of the new chain after the common ancestor
+	pub from_block: U256,
 }
 #[derive(Debug, Serialize, Eq, PartialEq, Clone)]
 #[serde(untagged)]
@@ -47,10 +60,10 @@ pub enum PubSubSyncStatus {
 #[serde(rename_all = "camelCase")]
 pub struct SyncStatusMetadata {
 	pub syncing: bool,
-	pub starting_block: u64,
-	pub current_block: u64,
+	pub starting_block: U256,
+	pub current_block: U256,
 	#[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
-	pub highest_block: Option<u64>,
+	pub highest_block: Option<U256>,
 }
 
 impl Serialize for Result {
@@ -63,6 +76,11 @@ impl Serialize for Result {
 			Result::Log(ref log) => log.serialize(serializer),
 			Result::TransactionHash(ref hash) => hash.serialize(serializer),
 			Result::SyncState(ref sync) => sync.serialize(serializer),
+			Result::Resync(ref resync) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("resync", resync)?;
+				map.end()
+			}
 		}
 	}
 }
@@ -82,6 +100,21 @@ pub enum Kind {
 	Syncing,
 }
 
+impl std::str::FromStr for Kind {
+	type Err = String;
+
+	/// Parses a kind from its `eth_subscribe` name, e.g. for node options.
+	fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+		match s {
+			"newHeads" => Ok(Kind::NewHeads),
+			"logs" => Ok(Kind::Logs),
+			"newPendingTransactions" => Ok(Kind::NewPendingTransactions),
+			"syncing" => Ok(Kind::Syncing),
+			_ => Err(format!("unknown subscription kind {}", s)),
+		}
+	}
+}
+
 /// Subscription kind.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Params {
@@ -113,3 +146,45 @@ impl<'a> Deserialize<'a> for Params {
 			.map_err(|e| D::Error::custom(format!("Invalid Pub-Sub parameters: {}", e)))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn kinds_parse_from_their_subscription_names() {
+		for name in ["newHeads", "logs", "newPendingTransactions", "syncing"] {
+			let kind: Kind = serde_json::from_value(Value::String(name.into())).unwrap();
+			assert_eq!(name.parse::<Kind>(), Ok(kind));
+		}
+		assert!("NewHeads".parse::<Kind>().is_err());
+	}
+
+	#[test]
+	fn sync_status_blocks_are_quantities() {
+		let status = PubSubSyncStatus::Detailed(SyncStatusMetadata {
+			syncing: true,
+			starting_block: U256::zero(),
+			current_block: U256::from(0x10u64),
+			highest_block: Some(U256::from(0x400u64)),
+		});
+
+		assert_eq!(
+			serde_json::to_string(&status).unwrap(),
+			r#"{"syncing":true,"startingBlock":"0x0","currentBlock":"0x10","highestBlock":"0x400"}"#
+		);
+	}
+
+	#[test]
+	fn resync_signals_are_tagged() {
+		let resync = Result::Resync(Resync {
+			retracted: U256::from(100u64),
+			from_block: U256::from(0x21u64),
+		});
+
+		assert_eq!(
+			serde_json::to_string(&resync).unwrap(),
+			r#"{"resync":{"retracted":"0x64","fromBlock":"0x21"}}"#
+		);
+	}
+}
@@ -60,3 +60,15 @@ pub struct Receipt {
 	#[serde(rename = "type")]
 	pub transaction_type: U256,
 }
+
+/// Provisional receipt of a pending transaction, from executing it on top of the
+/// pending state. It may differ from the receipt of the mined transaction.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingReceipt {
+	/// Receipt fields, without those of the including block
+	#[serde(flatten)]
+	pub receipt: Receipt,
+	/// Always true, the receipt is the outcome of a speculative execution
+	pub speculative: bool,
+}
@@ -253,3 +253,25 @@ pub struct RichRawTransaction {
 	#[serde(rename = "tx")]
 	pub transaction: Transaction,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn quantities_and_data_are_encoded_apart() {
+		let transaction = Transaction {
+			block_number: Some(U256::zero()),
+			..Default::default()
+		};
+		let json = serde_json::to_value(&transaction).unwrap();
+
+		// Quantities are minimal, data is byte aligned.
+		assert_eq!(json["nonce"], "0x0");
+		assert_eq!(json["gas"], "0x0");
+		assert_eq!(json["value"], "0x0");
+		assert_eq!(json["blockNumber"], "0x0");
+		assert_eq!(json["input"], "0x");
+		assert_eq!(json["hash"], format!("0x{}", "00".repeat(32)));
+	}
+}
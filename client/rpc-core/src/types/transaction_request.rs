@@ -18,7 +18,7 @@
 
 //! `TransactionRequest` type
 
-use crate::types::Bytes;
+use crate::types::{Bytes, CallRequest};
 use ethereum::{
 	AccessListItem, EIP1559TransactionMessage, EIP2930TransactionMessage, LegacyTransactionMessage,
 };
@@ -65,6 +65,24 @@ pub struct TransactionRequest {
 	pub transaction_type: Option<U256>,
 }
 
+impl From<TransactionRequest> for CallRequest {
+	fn from(req: TransactionRequest) -> Self {
+		CallRequest {
+			from: req.from,
+			to: req.to,
+			gas_price: req.gas_price,
+			max_fee_per_gas: req.max_fee_per_gas,
+			max_priority_fee_per_gas: req.max_priority_fee_per_gas,
+			gas: req.gas,
+			value: req.value,
+			data: req.data,
+			nonce: req.nonce,
+			access_list: req.access_list,
+			transaction_type: req.transaction_type,
+		}
+	}
+}
+
 impl From<TransactionRequest> for Option<TransactionMessage> {
 	fn from(req: TransactionRequest) -> Self {
 		match (req.gas_price, req.max_fee_per_gas, req.access_list.clone()) {
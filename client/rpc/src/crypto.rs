@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Hashing shared by the apis and signers.
+
+use ethereum_types::{H160, H256};
+// Substrate
+use sp_core::hashing::keccak_256;
+
+/// Keccak-256 of `data`, as computed by `web3_sha3` and the `SHA3` opcode.
+pub fn keccak256(data: &[u8]) -> H256 {
+	H256::from(keccak_256(data))
+}
+
+/// Address of an uncompressed public key without its `0x04` prefix: the last 20
+/// bytes of its Keccak-256.
+pub fn public_key_address(public_key: &[u8; 64]) -> H160 {
+	H160::from(keccak256(public_key))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keccak256_vectors() {
+		assert_eq!(
+			keccak256(b""),
+			H256::from_slice(
+				&hex::decode("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+					.unwrap()
+			)
+		);
+		assert_eq!(
+			keccak256(b"abc"),
+			H256::from_slice(
+				&hex::decode("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45")
+					.unwrap()
+			)
+		);
+	}
+
+	#[test]
+	fn derives_the_address_of_a_public_key() {
+		// Public key of the 0x4646..46 secret key of the EIP-155 example.
+		let secret = libsecp256k1::SecretKey::parse(&[0x46; 32]).unwrap();
+		let mut public_key = [0u8; 64];
+		public_key
+			.copy_from_slice(&libsecp256k1::PublicKey::from_secret_key(&secret).serialize()[1..]);
+
+		assert_eq!(
+			public_key_address(&public_key),
+			H160::from_slice(&hex::decode("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap())
+		);
+	}
+}
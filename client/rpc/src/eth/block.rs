@@ -16,10 +16,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{
+	future::Future,
+	num::NonZeroUsize,
+	sync::{Arc, Mutex},
+};
 
 use ethereum_types::{H256, U256};
 use jsonrpsee::core::RpcResult as Result;
+use lru::LruCache;
 // Substrate
 use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
 use sc_network::ExHashT;
@@ -62,30 +67,41 @@ where
 			.expect_block_hash_from_id(&id)
 			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
 
-		let schema =
-			frontier_backend_client::onchain_storage_schema::<B, C, BE>(client.as_ref(), id);
-		let handler = overrides
-			.schemas
-			.get(&schema)
-			.unwrap_or(&overrides.fallback);
-
-		let block = block_data_cache.current_block(schema, substrate_hash).await;
-		let statuses = block_data_cache
-			.current_transaction_statuses(schema, substrate_hash)
-			.await;
-
-		let base_fee = handler.base_fee(&id);
-
-		match (block, statuses) {
-			(Some(block), Some(statuses)) => Ok(Some(rich_block_build(
-				block,
-				statuses.into_iter().map(Option::Some).collect(),
-				Some(hash),
-				full,
-				base_fee,
-			))),
-			_ => Ok(None),
-		}
+		self.rich_block_cache
+			.get_or_format(substrate_hash, full, move || async move {
+				let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
+					client.as_ref(),
+					id,
+				);
+				let handler = overrides
+					.schemas
+					.get(&schema)
+					.unwrap_or(&overrides.fallback);
+
+				let block = block_data_cache.current_block(schema, substrate_hash).await;
+				let statuses = block_data_cache
+					.current_transaction_statuses(schema, substrate_hash)
+					.await;
+
+				let base_fee = handler.base_fee(&id);
+
+				match (block, statuses) {
+					(Some(block), Some(statuses)) => {
+						let with_withdrawals = self.with_withdrawals(block.header.number);
+
+						Ok(Some(rich_block_build(
+							block,
+							statuses.into_iter().map(Option::Some).collect(),
+							Some(hash),
+							full,
+							base_fee,
+							with_withdrawals,
+						)))
+					}
+					_ => Ok(None),
+				}
+			})
+			.await
 	}
 
 	pub async fn block_by_number(
@@ -110,34 +126,42 @@ where
 			.expect_block_hash_from_id(&id)
 			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
 
-		let schema =
-			frontier_backend_client::onchain_storage_schema::<B, C, BE>(client.as_ref(), id);
-		let handler = overrides
-			.schemas
-			.get(&schema)
-			.unwrap_or(&overrides.fallback);
+		self.rich_block_cache
+			.get_or_format(substrate_hash, full, move || async move {
+				let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
+					client.as_ref(),
+					id,
+				);
+				let handler = overrides
+					.schemas
+					.get(&schema)
+					.unwrap_or(&overrides.fallback);
 
-		let block = block_data_cache.current_block(schema, substrate_hash).await;
-		let statuses = block_data_cache
-			.current_transaction_statuses(schema, substrate_hash)
-			.await;
+				let block = block_data_cache.current_block(schema, substrate_hash).await;
+				let statuses = block_data_cache
+					.current_transaction_statuses(schema, substrate_hash)
+					.await;
 
-		let base_fee = handler.base_fee(&id);
+				let base_fee = handler.base_fee(&id);
 
-		match (block, statuses) {
-			(Some(block), Some(statuses)) => {
-				let hash = H256::from(keccak_256(&rlp::encode(&block.header)));
+				match (block, statuses) {
+					(Some(block), Some(statuses)) => {
+						let hash = H256::from(keccak_256(&rlp::encode(&block.header)));
+						let with_withdrawals = self.with_withdrawals(block.header.number);
 
-				Ok(Some(rich_block_build(
-					block,
-					statuses.into_iter().map(Option::Some).collect(),
-					Some(hash),
-					full,
-					base_fee,
-				)))
-			}
-			_ => Ok(None),
-		}
+						Ok(Some(rich_block_build(
+							block,
+							statuses.into_iter().map(Option::Some).collect(),
+							Some(hash),
+							full,
+							base_fee,
+							with_withdrawals,
+						)))
+					}
+					_ => Ok(None),
+				}
+			})
+			.await
 	}
 
 	pub fn block_transaction_count_by_hash(&self, hash: H256) -> Result<Option<U256>> {
@@ -216,4 +240,128 @@ where
 	) -> Result<Option<RichBlock>> {
 		Ok(None)
 	}
+
+	fn with_withdrawals(&self, number: U256) -> bool {
+		self.config
+			.withdrawals_block
+			.map_or(false, |fork| number >= U256::from(fork))
+	}
+}
+
+/// Number of formatted blocks kept by `RichBlockCache`.
+pub(crate) const RICH_BLOCK_CACHE_SIZE: usize = 64;
+
+/// Blocks returned by eth_getBlockByHash and eth_getBlockByNumber, keyed by the
+/// substrate hash of the block and whether transactions are returned in full.
+///
+/// A substrate hash always designates the same block, so entries never go stale:
+/// after a reorg, a number resolves to the hash of the new canonical block and
+/// misses the entries of the retracted one, which eventually get evicted.
+pub(crate) struct RichBlockCache(Mutex<LruCache<(H256, bool), RichBlock>>);
+
+impl RichBlockCache {
+	pub fn new(capacity: usize) -> Self {
+		let capacity = NonZeroUsize::new(capacity.max(1)).expect("capacity is not zero; qed");
+		Self(Mutex::new(LruCache::new(capacity)))
+	}
+
+	/// The cached block, or the one loaded and formatted by `format`, cached unless
+	/// it is missing.
+	pub async fn get_or_format<F, Fut>(
+		&self,
+		substrate_hash: H256,
+		full: bool,
+		format: F,
+	) -> Result<Option<RichBlock>>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<Option<RichBlock>>>,
+	{
+		let key = (substrate_hash, full);
+		let cached = self
+			.0
+			.lock()
+			.ok()
+			.and_then(|mut cache| cache.get(&key).cloned());
+		if let Some(block) = cached {
+			return Ok(Some(block));
+		}
+
+		let block = format().await?;
+		if let (Some(block), Ok(mut cache)) = (&block, self.0.lock()) {
+			cache.put(key, block.clone());
+		}
+		Ok(block)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::partial_header;
+	use ethereum::BlockV2 as EthereumBlock;
+	use std::cell::Cell;
+
+	fn block(number: u64) -> EthereumBlock {
+		EthereumBlock::new(
+			ethereum::PartialHeader {
+				gas_limit: U256::from(8_000_000u64),
+				..partial_header(number)
+			},
+			vec![],
+			vec![],
+		)
+	}
+
+	#[test]
+	fn blocks_are_formatted_once_per_mode() {
+		let cache = RichBlockCache::new(RICH_BLOCK_CACHE_SIZE);
+		let formatted = Cell::new(0);
+		let get = |substrate_hash: H256, full: bool| {
+			futures::executor::block_on(cache.get_or_format(substrate_hash, full, || async {
+				formatted.set(formatted.get() + 1);
+				Ok(Some(rich_block_build(
+					block(1),
+					vec![],
+					None,
+					full,
+					None,
+					false,
+				)))
+			}))
+			.unwrap()
+			.unwrap()
+		};
+
+		let first = get(H256::repeat_byte(1), false);
+		let second = get(H256::repeat_byte(1), false);
+		assert_eq!(formatted.get(), 1);
+		assert_eq!(first.header, second.header);
+
+		// Full transactions, and other blocks, are entries of their own.
+		get(H256::repeat_byte(1), true);
+		get(H256::repeat_byte(2), false);
+		assert_eq!(formatted.get(), 3);
+		get(H256::repeat_byte(1), true);
+		assert_eq!(formatted.get(), 3);
+	}
+
+	#[test]
+	fn missing_blocks_are_not_cached() {
+		let cache = RichBlockCache::new(RICH_BLOCK_CACHE_SIZE);
+		let formatted = Cell::new(0);
+		for _ in 0..2 {
+			let block = futures::executor::block_on(cache.get_or_format(
+				H256::repeat_byte(1),
+				false,
+				|| async {
+					formatted.set(formatted.get() + 1);
+					Ok(None)
+				},
+			))
+			.unwrap();
+			assert!(block.is_none());
+		}
+		assert_eq!(formatted.get(), 2);
+	}
 }
@@ -407,19 +407,13 @@ where
 				transactions.sort_by(|a, b| a.effective_reward.cmp(&b.effective_reward));
 
 				// Calculate percentile rewards.
+				let transactions: Vec<(u64, u64)> = transactions
+					.iter()
+					.map(|tx| (tx.gas_used, tx.effective_reward))
+					.collect();
 				result.rewards = reward_percentiles
 					.into_iter()
-					.filter_map(|p| {
-						let target_gas = (p * gas_used / 100f64) as u64;
-						let mut sum_gas = 0;
-						for tx in &transactions {
-							sum_gas += tx.gas_used;
-							if target_gas <= sum_gas {
-								return Some(tx.effective_reward);
-							}
-						}
-						None
-					})
+					.map(|p| reward_at_percentile(&transactions, gas_used, p))
 					.collect();
 			} else {
 				result.rewards = reward_percentiles.iter().map(|_| 0).collect();
@@ -466,20 +460,18 @@ where
 				let elasticity = handler.elasticity(&id).unwrap_or(default_elasticity);
 				// In case a re-org happened on import.
 				if let Some(tree_route) = notification.tree_route {
-					if let Ok(fee_history_cache) = &mut fee_history_cache.lock() {
-						// Remove retracted.
-						let _lock = tree_route.retracted().iter().map(|hash_and_number| {
-							let n = UniqueSaturatedInto::<u64>::unique_saturated_into(
+					evict_retracted(
+						&fee_history_cache,
+						tree_route.retracted().iter().map(|hash_and_number| {
+							UniqueSaturatedInto::<u64>::unique_saturated_into(
 								hash_and_number.number,
-							);
-							fee_history_cache.remove(&n);
-						});
-						// Insert enacted.
-						let _ = tree_route.enacted().iter().map(|hash_and_number| {
-							let (result, block_number) =
-								fee_history_cache_item(hash_and_number.hash, elasticity);
-							commit_if_any(result, block_number);
-						});
+							)
+						}),
+					);
+					for hash_and_number in tree_route.enacted() {
+						let (result, block_number) =
+							fee_history_cache_item(hash_and_number.hash, elasticity);
+						commit_if_any(result, block_number);
 					}
 				}
 				// Cache the imported block.
@@ -489,3 +481,82 @@ where
 		}
 	}
 }
+
+/// Evicts the fee history cached for blocks retracted by a re-org, so that it is
+/// not served in place of their replacements at the same heights.
+fn evict_retracted(fee_history_cache: &FeeHistoryCache, retracted: impl IntoIterator<Item = u64>) {
+	if let Ok(fee_history_cache) = &mut fee_history_cache.lock() {
+		for block_number in retracted {
+			fee_history_cache.remove(&block_number);
+		}
+	}
+}
+
+/// Effective reward paid by the transaction reaching the `percentile` of the
+/// block's used gas, with `transactions` as `(gas used, effective reward)`
+/// sorted by reward.
+///
+/// Always yields a value, so every block caches one reward per percentile: blocks
+/// without transactions reward zero, and float rounding can't leave the 100th
+/// percentile unmatched.
+fn reward_at_percentile(transactions: &[(u64, u64)], block_gas_used: f64, percentile: f64) -> u64 {
+	let target_gas = (percentile * block_gas_used / 100f64) as u64;
+	let mut sum_gas = 0;
+	for (gas_used, effective_reward) in transactions {
+		sum_gas += gas_used;
+		if target_gas <= sum_gas {
+			return *effective_reward;
+		}
+	}
+	transactions
+		.last()
+		.map(|(_, effective_reward)| *effective_reward)
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rewards_at_percentile_boundaries() {
+		let transactions = [(21_000, 1), (42_000, 5), (21_000, 9)];
+		let gas_used = 84_000f64;
+
+		assert_eq!(reward_at_percentile(&transactions, gas_used, 0.0), 1);
+		assert_eq!(reward_at_percentile(&transactions, gas_used, 50.0), 5);
+		assert_eq!(reward_at_percentile(&transactions, gas_used, 100.0), 9);
+		// Gas used slightly over the sum of the receipts still gets the max.
+		assert_eq!(
+			reward_at_percentile(&transactions, gas_used + 1.0, 100.0),
+			9
+		);
+	}
+
+	#[test]
+	fn empty_block_rewards_zero() {
+		for p in [0.0, 50.0, 100.0] {
+			assert_eq!(reward_at_percentile(&[], 0f64, p), 0);
+		}
+	}
+
+	#[test]
+	fn reorg_evicts_retracted_blocks() {
+		let fee_history_cache: FeeHistoryCache = Arc::new(Mutex::new(BTreeMap::new()));
+		for block_number in 1..=3u64 {
+			fee_history_cache.lock().unwrap().insert(
+				block_number,
+				FeeHistoryCacheItem {
+					base_fee: block_number,
+					gas_used_ratio: 0f64,
+					rewards: Vec::new(),
+				},
+			);
+		}
+
+		evict_retracted(&fee_history_cache, vec![2, 3]);
+
+		let cached: Vec<u64> = fee_history_cache.lock().unwrap().keys().copied().collect();
+		assert_eq!(cached, vec![1]);
+	}
+}
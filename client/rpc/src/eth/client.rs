@@ -33,7 +33,7 @@ use sp_runtime::{
 use fc_rpc_core::types::*;
 use fp_rpc::EthereumRuntimeRPCApi;
 
-use crate::{eth::Eth, frontier_backend_client, internal_err};
+use crate::{eth::Eth, frontier_backend_client, internal_err, signer::signer_accounts};
 
 impl<B, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A>
 where
@@ -75,7 +75,7 @@ where
 			block,
 		);
 
-		Ok(self
+		let beneficiary = self
 			.overrides
 			.schemas
 			.get(&schema)
@@ -83,15 +83,12 @@ where
 			.current_block(&block)
 			.ok_or_else(|| internal_err("fetching author through override failed"))?
 			.header
-			.beneficiary)
+			.beneficiary;
+		Ok(coinbase(beneficiary, self.config.default_coinbase))
 	}
 
 	pub fn accounts(&self) -> Result<Vec<H160>> {
-		let mut accounts = Vec::new();
-		for signer in &*self.signers {
-			accounts.append(&mut signer.accounts());
-		}
-		Ok(accounts)
+		Ok(signer_accounts(&self.signers))
 	}
 
 	pub fn block_number(&self) -> Result<U256> {
@@ -111,3 +108,32 @@ where
 		))
 	}
 }
+
+/// The runtime maps the block author to its beneficiary address, the zero address
+/// when the author has none, in which case the configured default is reported.
+fn coinbase(beneficiary: H160, default_coinbase: Option<H160>) -> H160 {
+	match default_coinbase {
+		Some(default_coinbase) if beneficiary.is_zero() => default_coinbase,
+		_ => beneficiary,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn coinbase_is_the_mapped_author() {
+		let author = H160::repeat_byte(0xa1);
+		let default_coinbase = H160::repeat_byte(0xde);
+
+		assert_eq!(coinbase(author, None), author);
+		assert_eq!(coinbase(author, Some(default_coinbase)), author);
+		// No mapping for the author.
+		assert_eq!(
+			coinbase(H160::zero(), Some(default_coinbase)),
+			default_coinbase
+		);
+		assert_eq!(coinbase(H160::zero(), None), H160::zero());
+	}
+}
@@ -16,30 +16,42 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
-use ethereum_types::{H256, U256};
+use ethereum::{TransactionAction, TransactionV2 as EthereumTransaction};
+use ethereum_types::{Bloom, BloomInput, H160, H256, U256, U64};
 use evm::{ExitError, ExitReason};
-use jsonrpsee::core::RpcResult as Result;
+use jsonrpsee::{core::RpcResult as Result, types::error::CallError};
 // Substrate
 use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
 use sc_network::ExHashT;
 use sc_transaction_pool::ChainApi;
+use sc_transaction_pool_api::InPoolTransaction;
 use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_block_builder::BlockBuilder as BlockBuilderApi;
 use sp_blockchain::{BlockStatus, HeaderBackend};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{BlakeTwo256, Block as BlockT},
-	SaturatedConversion,
+	SaturatedConversion, TransactionOutcome,
 };
 // Frontier
 use fc_rpc_core::types::*;
 use fp_rpc::EthereumRuntimeRPCApi;
 
 use crate::{
-	eth::{pending_runtime_api, Eth},
+	crypto::public_key_address,
+	eth::{
+		filter::MAX_PENDING_TRANSACTIONS, pending_runtime_api, pending_runtime_api_with, Eth,
+		LinearPrecompileCost,
+	},
 	frontier_backend_client, internal_err,
+	overlay::intrinsic_gas,
+	public_key, transaction_logs, METHOD_NOT_SUPPORTED,
 };
 
 /// Default JSONRPC error code return by geth
@@ -74,36 +86,75 @@ where
 	A: ChainApi<Block = B> + 'static,
 	EGA: EstimateGasAdapter,
 {
-	pub fn call(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<Bytes> {
-		let CallRequest {
-			from,
-			to,
-			gas_price,
-			max_fee_per_gas,
-			max_priority_fee_per_gas,
-			gas,
-			value,
-			data,
-			nonce,
-			access_list,
-			..
-		} = request;
+	pub fn call(
+		&self,
+		request: CallRequest,
+		number: Option<BlockNumber>,
+		state_overrides: Option<BTreeMap<H160, CallStateOverride>>,
+	) -> Result<Bytes> {
+		let (id, api, api_version, max_gas_limit) = self.call_state(number)?;
+		if let Some(state_overrides) = state_overrides {
+			apply_state_overrides(&*api, &id, api_version, state_overrides)?;
+		}
+		self.call_at(&api, &id, api_version, max_gas_limit, request)
+	}
 
-		let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = {
-			let details = fee_details(gas_price, max_fee_per_gas, max_priority_fee_per_gas)?;
-			(
-				details.gas_price,
-				details.max_fee_per_gas,
-				details.max_priority_fee_per_gas,
-			)
-		};
+	pub fn call_decoded(
+		&self,
+		request: CallRequest,
+		output_types: Vec<AbiType>,
+		number: Option<BlockNumber>,
+	) -> Result<Vec<AbiValue>> {
+		let returned = self.call(request, number, None)?;
+		AbiValue::decode(&output_types, &returned.0).ok_or_else(|| {
+			internal_err(format!(
+				"return data 0x{} does not decode as the output types",
+				hex::encode(&returned.0)
+			))
+		})
+	}
 
+	pub fn multicall(
+		&self,
+		requests: Vec<CallRequest>,
+		number: Option<BlockNumber>,
+	) -> Result<Vec<CallResult>> {
+		ensure_multicall_size(requests.len(), self.config.max_multicall_calls)?;
+		let (id, api, api_version, max_gas_limit) = self.call_state(number)?;
+		Ok(requests
+			.into_iter()
+			.map(|request| {
+				// Changes made by a call are discarded before running the next one.
+				let result = api.execute_in_transaction(|api| {
+					TransactionOutcome::Rollback(self.call_at(
+						api,
+						&id,
+						api_version,
+						max_gas_limit,
+						request,
+					))
+				});
+				call_result(result)
+			})
+			.collect())
+	}
+
+	/// Block state to execute calls against: its id, the runtime api, its version
+	/// and the highest gas limit of a call.
+	fn call_state(
+		&self,
+		number: Option<BlockNumber>,
+	) -> Result<(BlockId<B>, sp_api::ApiRef<'_, C::Api>, u32, U256)> {
 		let (id, api) = match frontier_backend_client::native_block_id::<B, C>(
 			self.client.as_ref(),
 			self.backend.as_ref(),
 			number,
 		)? {
 			Some(id) => (id, self.client.runtime_api()),
+			// Like the other state queries, calls at an unknown block fail.
+			None if matches!(number, Some(BlockNumber::Hash { .. })) => {
+				return Err(crate::err(JSON_RPC_ERROR_DEFAULT, "header not found", None));
+			}
 			None => {
 				// Not mapped in the db, assume pending.
 				let id = BlockId::Hash(self.client.info().best_hash);
@@ -123,45 +174,70 @@ where
 				return Err(internal_err("failed to retrieve Runtime Api version"));
 			};
 
-		let block = if api_version > 1 {
-			api.current_block(&id)
-				.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
-		} else {
-			#[allow(deprecated)]
-			let legacy_block = api
-				.current_block_before_version_2(&id)
-				.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
-			legacy_block.map(|block| block.into())
-		};
-
-		let block_gas_limit = block
+		let block_gas_limit = current_block(&*api, &id, api_version)?
 			.ok_or_else(|| internal_err("block unavailable, cannot query gas limit"))?
 			.header
 			.gas_limit;
 		let max_gas_limit = block_gas_limit * self.execute_gas_limit_multiplier;
 
-		// use given gas limit or query current block's limit
-		let gas_limit = match gas {
-			Some(amount) => {
-				if amount > max_gas_limit {
-					return Err(internal_err(format!(
-						"provided gas limit is too high (can be up to {}x the block gas limit)",
-						self.execute_gas_limit_multiplier
-					)));
-				}
-				amount
-			}
-			None => max_gas_limit,
+		Ok((id, api, api_version, max_gas_limit))
+	}
+
+	fn call_at(
+		&self,
+		api: &C::Api,
+		id: &BlockId<B>,
+		api_version: u32,
+		max_gas_limit: U256,
+		request: CallRequest,
+	) -> Result<Bytes> {
+		ensure_intrinsic_gas(&self.config.evm_config, &request)?;
+
+		let CallRequest {
+			from,
+			to,
+			gas_price,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			gas,
+			value,
+			data,
+			nonce,
+			access_list,
+			..
+		} = request;
+
+		let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = {
+			let details = fee_details(gas_price, max_fee_per_gas, max_priority_fee_per_gas)?;
+			(
+				details.gas_price,
+				details.max_fee_per_gas,
+				details.max_priority_fee_per_gas,
+			)
 		};
 
+		// use given gas limit or query current block's limit
+		let gas_limit = execution_gas_limit(
+			gas,
+			max_gas_limit,
+			self.execute_gas_limit_multiplier,
+			self.config.rpc_gas_cap,
+		)?;
+
 		let data = data.map(|d| d.0).unwrap_or_default();
-		match to {
+		let returned = match to {
 			Some(to) => {
+				if self.config.require_callee_code && !data.is_empty() {
+					ensure_callee_has_code(&self.config.precompile_addresses, to, || {
+						api.account_code_at(id, to)
+							.map_err(|err| internal_err(format!("runtime error: {:?}", err)))
+					})?;
+				}
 				if api_version == 1 {
 					// Legacy pre-london
 					#[allow(deprecated)]
 					let info = api.call_before_version_2(
-						&id,
+						id,
 						from.unwrap_or_default(),
 						to,
 						data,
@@ -174,13 +250,17 @@ where
 					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
 					.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
 
-					error_on_execution_failure(&info.exit_reason, &info.value)?;
+					error_on_execution_failure(
+						&info.exit_reason,
+						&info.value,
+						self.config.max_revert_reason_len,
+					)?;
 					Ok(Bytes(info.value))
 				} else if api_version >= 2 && api_version < 4 {
 					// Post-london
 					#[allow(deprecated)]
 					let info = api.call_before_version_4(
-						&id,
+						id,
 						from.unwrap_or_default(),
 						to,
 						data,
@@ -194,14 +274,18 @@ where
 					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
 					.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
 
-					error_on_execution_failure(&info.exit_reason, &info.value)?;
+					error_on_execution_failure(
+						&info.exit_reason,
+						&info.value,
+						self.config.max_revert_reason_len,
+					)?;
 					Ok(Bytes(info.value))
-				} else if api_version == 4 {
+				} else if api_version >= 4 {
 					// Post-london + access list support
 					let access_list = access_list.unwrap_or_default();
 					let info = api
 						.call(
-							&id,
+							id,
 							from.unwrap_or_default(),
 							to,
 							data,
@@ -221,7 +305,11 @@ where
 						.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
 						.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
 
-					error_on_execution_failure(&info.exit_reason, &info.value)?;
+					error_on_execution_failure(
+						&info.exit_reason,
+						&info.value,
+						self.config.max_revert_reason_len,
+					)?;
 					Ok(Bytes(info.value))
 				} else {
 					Err(internal_err("failed to retrieve Runtime Api version"))
@@ -232,7 +320,7 @@ where
 					// Legacy pre-london
 					#[allow(deprecated)]
 					let info = api.create_before_version_2(
-						&id,
+						id,
 						from.unwrap_or_default(),
 						data,
 						value.unwrap_or_default(),
@@ -244,17 +332,21 @@ where
 					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
 					.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
 
-					error_on_execution_failure(&info.exit_reason, &[])?;
+					error_on_execution_failure(
+						&info.exit_reason,
+						&[],
+						self.config.max_revert_reason_len,
+					)?;
 
 					let code = api
-						.account_code_at(&id, info.value)
+						.account_code_at(id, info.value)
 						.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
 					Ok(Bytes(code))
 				} else if api_version >= 2 && api_version < 4 {
 					// Post-london
 					#[allow(deprecated)]
 					let info = api.create_before_version_4(
-						&id,
+						id,
 						from.unwrap_or_default(),
 						data,
 						value.unwrap_or_default(),
@@ -267,18 +359,22 @@ where
 					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
 					.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
 
-					error_on_execution_failure(&info.exit_reason, &[])?;
+					error_on_execution_failure(
+						&info.exit_reason,
+						&[],
+						self.config.max_revert_reason_len,
+					)?;
 
 					let code = api
-						.account_code_at(&id, info.value)
+						.account_code_at(id, info.value)
 						.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
 					Ok(Bytes(code))
-				} else if api_version == 4 {
+				} else if api_version >= 4 {
 					// Post-london + access list support
 					let access_list = access_list.unwrap_or_default();
 					let info = api
 						.create(
-							&id,
+							id,
 							from.unwrap_or_default(),
 							data,
 							value.unwrap_or_default(),
@@ -297,17 +393,24 @@ where
 						.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
 						.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
 
-					error_on_execution_failure(&info.exit_reason, &[])?;
+					error_on_execution_failure(
+						&info.exit_reason,
+						&[],
+						self.config.max_revert_reason_len,
+					)?;
 
 					let code = api
-						.account_code_at(&id, info.value)
+						.account_code_at(id, info.value)
 						.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
 					Ok(Bytes(code))
 				} else {
 					Err(internal_err("failed to retrieve Runtime Api version"))
 				}
 			}
-		}
+		}?;
+
+		ensure_return_data_size(&returned.0, self.config.max_call_return_data_size)?;
+		Ok(returned)
 	}
 
 	pub async fn estimate_gas(&self, request: CallRequest, _: Option<BlockNumber>) -> Result<U256> {
@@ -322,6 +425,7 @@ where
 
 		// Adapt request for gas estimation.
 		let request = EGA::adapt_request(request);
+		ensure_intrinsic_gas(&self.config.evm_config, &request)?;
 
 		// For simple transfer to simple account, return MIN_GAS_PER_TX directly
 		let is_simple_transfer = match &request.data {
@@ -334,7 +438,8 @@ where
 					.runtime_api()
 					.account_code_at(&BlockId::Hash(best_hash), to)
 					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
-				if to_code.is_empty() {
+				// Precompiles run without any code at their address.
+				if to_code.is_empty() && !self.config.precompile_addresses.contains(&to) {
 					return Ok(MIN_GAS_PER_TX);
 				}
 			}
@@ -368,18 +473,27 @@ where
 		let max_gas_limit = block_gas_limit * self.execute_gas_limit_multiplier;
 
 		// Determine the highest possible gas limits
-		let mut highest = match request.gas {
-			Some(amount) => {
-				if amount > max_gas_limit {
-					return Err(internal_err(format!(
-						"provided gas limit is too high (can be up to {}x the block gas limit)",
-						self.execute_gas_limit_multiplier
-					)));
+		let mut highest = execution_gas_limit(
+			request.gas,
+			max_gas_limit,
+			self.execute_gas_limit_multiplier,
+			self.config.rpc_gas_cap,
+		)?;
+
+		// Calls to precompiles of linear cost have a cost known from their input.
+		if let (Some(to), None) = (request.to, &request.access_list) {
+			let input = request.data.as_ref().map(|d| &d.0[..]).unwrap_or_default();
+			if let Some(gas) = precompile_gas_estimate(
+				&self.config.evm_config,
+				&self.config.precompile_linear_costs,
+				to,
+				input,
+			) {
+				if gas <= highest {
+					return Ok(gas);
 				}
-				amount
 			}
-			None => max_gas_limit,
-		};
+		}
 
 		let api = client.runtime_api();
 
@@ -623,7 +737,12 @@ where
 						used_gas: _,
 					} = executable(
 						request.clone(),
-						max_gas_limit,
+						execution_gas_limit(
+							None,
+							max_gas_limit,
+							self.execute_gas_limit_multiplier,
+							self.config.rpc_gas_cap,
+						)?,
 						api_version,
 						client.runtime_api(),
 						estimate_mode,
@@ -636,14 +755,22 @@ where
 							)))
 						}
 						// The execution has been done with block gas limit, so it is not a lack of gas from the user.
-						other => error_on_execution_failure(&other, &data)?,
+						other => error_on_execution_failure(
+							&other,
+							&data,
+							self.config.max_revert_reason_len,
+						)?,
 					}
 				} else {
 					// The execution has already been done with block gas limit, so it is not a lack of gas from the user.
-					error_on_execution_failure(&ExitReason::Revert(revert), &data)?
+					error_on_execution_failure(
+						&ExitReason::Revert(revert),
+						&data,
+						self.config.max_revert_reason_len,
+					)?
 				}
 			}
-			other => error_on_execution_failure(&other, &data)?,
+			other => error_on_execution_failure(&other, &data, self.config.max_revert_reason_len)?,
 		};
 
 		#[cfg(not(feature = "rpc_binary_search_estimate"))]
@@ -654,52 +781,467 @@ where
 		{
 			// On binary search, evm estimate mode is disabled
 			let estimate_mode = false;
-			// Define the lower bound of the binary search
-			let mut lowest = MIN_GAS_PER_TX;
+			// The search starts at the intrinsic gas, no lower limit can succeed.
+			let max_revert_reason_len = self.config.max_revert_reason_len;
+			search_gas_limit(
+				intrinsic_gas_floor(&self.config.evm_config, &request),
+				highest,
+				used_gas,
+				self.config.estimate_gas_timeout,
+				|gas_limit| {
+					let ExecutableResult {
+						data,
+						exit_reason,
+						used_gas: _,
+					} = executable(
+						request.clone(),
+						gas_limit,
+						api_version,
+						client.runtime_api(),
+						estimate_mode,
+					)?;
+					gas_search_outcome(&exit_reason, &data, max_revert_reason_len)
+				},
+			)
+		}
+	}
 
-			// Start close to the used gas for faster binary search
-			let mut mid = std::cmp::min(used_gas * 3, (highest + lowest) / 2);
+	/// Provisional receipt of a transaction in the ready queue, from executing it on
+	/// top of the best block's state and the ready transactions queued ahead of it.
+	/// `None` when the transaction is not in the ready queue.
+	pub fn pending_receipt(&self, hash: H256) -> Result<Option<PendingReceipt>> {
+		if !self.config.pending_receipts {
+			return Err(crate::err(
+				METHOD_NOT_SUPPORTED,
+				"eth_getPendingReceipt is not enabled",
+				None,
+			));
+		}
 
-			// Execute the binary search and hone in on an executable gas limit.
-			let mut previous_highest = highest;
-			while (highest - lowest) > U256::one() {
-				let ExecutableResult {
-					data,
-					exit_reason,
-					used_gas: _,
-				} = executable(
-					request.clone(),
-					mid,
-					api_version,
-					client.runtime_api(),
-					estimate_mode,
-				)?;
-				match exit_reason {
-					ExitReason::Succeed(_) => {
-						highest = mid;
-						// If the variation in the estimate is less than 10%,
-						// then the estimate is considered sufficiently accurate.
-						if (previous_highest - highest) * 10 / previous_highest < U256::one() {
-							return Ok(highest);
-						}
-						previous_highest = highest;
-					}
-					ExitReason::Revert(_)
-					| ExitReason::Error(ExitError::OutOfGas)
-					| ExitReason::Error(ExitError::InvalidCode(_)) => {
-						lowest = mid;
-					}
-					other => error_on_execution_failure(&other, &data)?,
-				}
-				mid = (highest + lowest) / 2;
+		let best = BlockId::Hash(self.client.info().best_hash);
+		let api = self.client.runtime_api();
+		let api_version = api
+			.api_version::<dyn EthereumRuntimeRPCApi<B>>(&best)
+			.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
+			.ok_or_else(|| internal_err("failed to retrieve Runtime Api version"))?;
+		if api_version < 4 {
+			return Err(internal_err(
+				"pending receipts need version 4 of the Runtime Api",
+			));
+		}
+
+		// Find the transaction, keeping the ones that execute before it.
+		let mut ahead = Vec::new();
+		let mut found = None;
+		for (index, xt) in self.graph.validated_pool().ready().enumerate() {
+			if index >= MAX_PENDING_TRANSACTIONS {
+				return Err(internal_err(format!(
+					"query executes more than {} pending transactions",
+					MAX_PENDING_TRANSACTIONS
+				)));
+			}
+			let xt = xt.data().clone();
+			let transaction = api
+				.extrinsic_filter(&best, vec![xt.clone()])
+				.map_err(|err| {
+					internal_err(format!("fetch runtime extrinsic filter failed: {:?}", err))
+				})?
+				.into_iter()
+				.find(|transaction| transaction.hash() == hash);
+			if transaction.is_some() {
+				found = transaction;
+				break;
 			}
+			ahead.push(xt);
+		}
+		let transaction = match found {
+			Some(transaction) => transaction,
+			None => return Ok(None),
+		};
+
+		let from = public_key(&transaction)
+			.map(|pubkey| public_key_address(&pubkey))
+			.map_err(|_| internal_err("invalid transaction signature"))?;
+		let (action, input, value, gas_limit, max_fee, max_priority_fee, nonce, access_list) =
+			match &transaction {
+				EthereumTransaction::Legacy(t) => (
+					&t.action,
+					t.input.clone(),
+					t.value,
+					t.gas_limit,
+					t.gas_price,
+					t.gas_price,
+					t.nonce,
+					Vec::new(),
+				),
+				EthereumTransaction::EIP2930(t) => (
+					&t.action,
+					t.input.clone(),
+					t.value,
+					t.gas_limit,
+					t.gas_price,
+					t.gas_price,
+					t.nonce,
+					t.access_list.clone(),
+				),
+				EthereumTransaction::EIP1559(t) => (
+					&t.action,
+					t.input.clone(),
+					t.value,
+					t.gas_limit,
+					t.max_fee_per_gas,
+					t.max_priority_fee_per_gas,
+					t.nonce,
+					t.access_list.clone(),
+				),
+			};
+		let access_list = access_list
+			.into_iter()
+			.map(|item| (item.address, item.storage_keys))
+			.collect();
+
+		let api = pending_runtime_api_with(self.client.as_ref(), ahead)?;
+		let (to, contract_address, exit_reason, used_gas, logs) = match action {
+			TransactionAction::Call(to) => {
+				let info = api
+					.call(
+						&best,
+						from,
+						*to,
+						input,
+						value,
+						gas_limit,
+						Some(max_fee),
+						Some(max_priority_fee),
+						Some(nonce),
+						false,
+						Some(access_list),
+					)
+					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
+					.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
+				(Some(*to), None, info.exit_reason, info.used_gas, info.logs)
+			}
+			TransactionAction::Create => {
+				let info = api
+					.create(
+						&best,
+						from,
+						input,
+						value,
+						gas_limit,
+						Some(max_fee),
+						Some(max_priority_fee),
+						Some(nonce),
+						false,
+						Some(access_list),
+					)
+					.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
+					.map_err(|err| internal_err(format!("execution fatal: {:?}", err)))?;
+				(
+					None,
+					Some(info.value),
+					info.exit_reason,
+					info.used_gas,
+					info.logs,
+				)
+			}
+		};
 
-			Ok(highest)
+		Ok(Some(pending_receipt_build(
+			&transaction,
+			self.pending_base_fee(),
+			from,
+			to,
+			contract_address,
+			&exit_reason,
+			used_gas,
+			&logs,
+		)))
+	}
+}
+
+/// Gas a transaction for `request` is charged before executing, the lowest gas
+/// limit it can succeed with: the base cost of a call or creation, and the cost of
+/// its calldata and access list under `config`.
+fn intrinsic_gas_floor(config: &evm::Config, request: &CallRequest) -> U256 {
+	let data = request.data.as_ref().map(|d| &d.0[..]).unwrap_or_default();
+	let access_list: Vec<(H160, Vec<H256>)> = request
+		.access_list
+		.iter()
+		.flatten()
+		.map(|item| (item.address, item.storage_keys.clone()))
+		.collect();
+	U256::from(intrinsic_gas(
+		config,
+		data,
+		&access_list,
+		request.to.is_none(),
+	))
+}
+
+/// Fails a request whose gas limit does not even cover its intrinsic gas, as it
+/// could never execute.
+fn ensure_intrinsic_gas(config: &evm::Config, request: &CallRequest) -> Result<()> {
+	if let Some(gas) = request.gas {
+		let floor = intrinsic_gas_floor(config, request);
+		if gas < floor {
+			return Err(internal_err(format!(
+				"intrinsic gas too low: have {}, want {}",
+				gas, floor
+			)));
 		}
 	}
+	Ok(())
+}
+
+/// Binary searches the lowest gas limit from `lowest` to `highest` that `executes`
+/// succeeds with, starting close to the `used_gas` of an execution with `highest`.
+///
+/// The search stops early once a succeeding limit is within 10% of the previous one.
+#[cfg_attr(not(feature = "rpc_binary_search_estimate"), allow(dead_code))]
+fn search_gas_limit(
+	mut lowest: U256,
+	mut highest: U256,
+	used_gas: U256,
+	timeout: Option<Duration>,
+	mut executes: impl FnMut(U256) -> Result<bool>,
+) -> Result<U256> {
+	let started = Instant::now();
+	// Start close to the used gas for faster binary search
+	let mut mid = std::cmp::min(used_gas * 3, (highest + lowest) / 2);
+
+	// Execute the binary search and hone in on an executable gas limit.
+	let mut previous_highest = highest;
+	while (highest - lowest) > U256::one() {
+		if let Some(timeout) = timeout {
+			if started.elapsed() >= timeout {
+				return Err(internal_err(format!(
+					"gas estimation timeout of {} ms exceeded, gas limit between {} and {}",
+					timeout.as_millis(),
+					lowest,
+					highest
+				)));
+			}
+		}
+		if executes(mid)? {
+			highest = mid;
+			// If the variation in the estimate is less than 10%,
+			// then the estimate is considered sufficiently accurate.
+			if (previous_highest - highest) * 10 / previous_highest < U256::one() {
+				return Ok(highest);
+			}
+			previous_highest = highest;
+		} else {
+			lowest = mid;
+		}
+		mid = (highest + lowest) / 2;
+	}
+
+	Ok(highest)
+}
+
+/// Whether an execution of the gas binary search succeeded, or needs a higher limit.
+///
+/// Only running out of gas is fixed by more gas. Any other failure, such as a revert,
+/// an invalid opcode or a fatal error, fails the search with its reason.
+#[cfg_attr(not(feature = "rpc_binary_search_estimate"), allow(dead_code))]
+fn gas_search_outcome(
+	exit_reason: &ExitReason,
+	data: &[u8],
+	max_revert_reason_len: usize,
+) -> Result<bool> {
+	match exit_reason {
+		ExitReason::Succeed(_) => Ok(true),
+		ExitReason::Error(ExitError::OutOfGas) => Ok(false),
+		other => error_on_execution_failure(other, data, max_revert_reason_len).map(|_| false),
+	}
+}
+
+/// Gas used by a transaction calling a precompile with `input`, if `to` is one
+/// of the precompiles of `linear_costs`, whose cost only depends on the input
+/// length. The intrinsic gas is priced under `config`.
+fn precompile_gas_estimate(
+	config: &evm::Config,
+	linear_costs: &BTreeMap<H160, LinearPrecompileCost>,
+	to: H160,
+	input: &[u8],
+) -> Option<U256> {
+	let cost = linear_costs.get(&to)?;
+	let words = (input.len() as u64 + 31) / 32;
+	let precompile_gas = cost.base.checked_add(cost.word.checked_mul(words)?)?;
+	let intrinsic_gas = intrinsic_gas(config, input, &[], false);
+
+	Some(U256::from(intrinsic_gas) + U256::from(precompile_gas))
+}
+
+/// Current Ethereum block at `id`, read with the runtime api of version `api_version`.
+fn current_block<B: BlockT, A: EthereumRuntimeRPCApi<B>>(
+	api: &A,
+	id: &BlockId<B>,
+	api_version: u32,
+) -> Result<Option<ethereum::BlockV2>> {
+	if api_version > 1 {
+		api.current_block(id)
+			.map_err(|err| internal_err(format!("runtime error: {:?}", err)))
+	} else {
+		#[allow(deprecated)]
+		let legacy_block = api
+			.current_block_before_version_2(id)
+			.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
+		Ok(legacy_block.map(|block| block.into()))
+	}
+}
+
+/// Applies `state_overrides` to the state the calls made next with `api` execute
+/// on. They are applied by the runtime, the calls executing there as without
+/// overrides, with the EVM configuration and precompiles of the runtime.
+fn apply_state_overrides<B: BlockT, A: EthereumRuntimeRPCApi<B>>(
+	api: &A,
+	id: &BlockId<B>,
+	api_version: u32,
+	state_overrides: BTreeMap<H160, CallStateOverride>,
+) -> Result<()> {
+	if api_version < 5 {
+		return Err(internal_err(
+			"state overrides are not supported by the runtime",
+		));
+	}
+	let state_overrides = state_overrides
+		.into_iter()
+		.map(|(address, state_override)| {
+			if state_override.state.is_some() && state_override.state_diff.is_some() {
+				return Err(internal_err(format!(
+					"account {:?} has both state and stateDiff overrides",
+					address
+				)));
+			}
+			Ok((
+				address,
+				fp_rpc::StateOverride {
+					balance: state_override.balance,
+					nonce: state_override.nonce,
+					code: state_override.code.map(|code| code.0),
+					state: state_override
+						.state
+						.map(|state| state.into_iter().collect()),
+					state_diff: state_override
+						.state_diff
+						.map(|state_diff| state_diff.into_iter().collect()),
+				},
+			))
+		})
+		.collect::<Result<Vec<_>>>()?;
+	api.apply_state_overrides(id, state_overrides)
+		.map_err(|err| internal_err(format!("runtime error: {:?}", err)))
+}
+
+/// Gas limit to execute a call with: the requested amount, or the highest
+/// allowed one when unset, bounded by `rpc_gas_cap`.
+fn execution_gas_limit(
+	requested: Option<U256>,
+	max_gas_limit: U256,
+	execute_gas_limit_multiplier: u64,
+	rpc_gas_cap: Option<u64>,
+) -> Result<U256> {
+	let cap = match rpc_gas_cap {
+		Some(rpc_gas_cap) => core::cmp::min(max_gas_limit, U256::from(rpc_gas_cap)),
+		None => max_gas_limit,
+	};
+	match requested {
+		Some(amount) if amount > max_gas_limit => Err(internal_err(format!(
+			"provided gas limit is too high (can be up to {}x the block gas limit)",
+			execute_gas_limit_multiplier
+		))),
+		Some(amount) => Ok(core::cmp::min(amount, cap)),
+		None => Ok(cap),
+	}
+}
+
+/// Outcome of a call of a multicall, keeping the revert data of failed calls.
+fn call_result(result: Result<Bytes>) -> CallResult {
+	match result {
+		Ok(return_data) => CallResult {
+			success: true,
+			return_data,
+			error: None,
+		},
+		Err(jsonrpsee::core::Error::Call(CallError::Custom(err))) => CallResult {
+			success: false,
+			return_data: Bytes(
+				err.data()
+					.and_then(|data| {
+						hex::decode(data.get().trim_matches('"').trim_start_matches("0x")).ok()
+					})
+					.unwrap_or_default(),
+			),
+			error: Some(err.message().to_string()),
+		},
+		Err(err) => CallResult {
+			success: false,
+			return_data: Bytes::default(),
+			error: Some(err.to_string()),
+		},
+	}
 }
 
-pub fn error_on_execution_failure(reason: &ExitReason, data: &[u8]) -> Result<()> {
+/// Rejects call results larger than `limit` bytes, to keep responses of public
+/// endpoints bounded.
+fn ensure_return_data_size(returned: &[u8], limit: usize) -> Result<()> {
+	if returned.len() > limit {
+		return Err(crate::err(
+			JSON_RPC_ERROR_DEFAULT,
+			format!(
+				"call returned {} bytes of data, exceeding the limit of {} bytes",
+				returned.len(),
+				limit
+			),
+			None,
+		));
+	}
+	Ok(())
+}
+
+/// Rejects multicalls of more than `limit` calls, each of which is executed
+/// up to the gas cap.
+fn ensure_multicall_size(calls: usize, limit: usize) -> Result<()> {
+	if calls > limit {
+		return Err(crate::err(
+			JSON_RPC_ERROR_DEFAULT,
+			format!(
+				"multicall of {} calls exceeds the limit of {} calls",
+				calls, limit
+			),
+			None,
+		));
+	}
+	Ok(())
+}
+
+/// Fails a call sending data to an address without code, most likely a mistyped one,
+/// which would otherwise succeed returning nothing. Precompiles have no code but
+/// are executed, so that their `code` is not read.
+fn ensure_callee_has_code(
+	precompile_addresses: &BTreeSet<H160>,
+	to: H160,
+	code: impl FnOnce() -> Result<Vec<u8>>,
+) -> Result<()> {
+	if !precompile_addresses.contains(&to) && code()?.is_empty() {
+		return Err(crate::err(
+			JSON_RPC_ERROR_DEFAULT,
+			"called address has no code",
+			None,
+		));
+	}
+	Ok(())
+}
+
+/// Turns a failed execution into an error, with the revert reason decoded from
+/// `data` cut to `max_revert_reason_len` bytes in the message.
+pub fn error_on_execution_failure(
+	reason: &ExitReason,
+	data: &[u8],
+	max_revert_reason_len: usize,
+) -> Result<()> {
 	match reason {
 		ExitReason::Succeed(_) => Ok(()),
 		ExitReason::Error(e) => {
@@ -707,10 +1249,35 @@ pub fn error_on_execution_failure(reason: &ExitReason, data: &[u8]) -> Result<()
 				// `ServerError(0)` will be useful in estimate gas
 				return Err(internal_err("out of gas"));
 			}
-			Err(crate::internal_err_with_data(
-				format!("evm error: {:?}", e),
-				&[],
-			))
+			// The runtime does not report the program counter of the failure, only the
+			// opcode for invalid instructions, returned as error data.
+			match e {
+				ExitError::InvalidCode(opcode) => Err(crate::internal_err_with_data(
+					format!(
+						"evm error: invalid opcode {}",
+						format_hex(&[opcode.as_u8()])
+					),
+					&[opcode.as_u8()],
+				)),
+				ExitError::DesignatedInvalid => Err(crate::internal_err_with_data(
+					"evm error: invalid opcode 0xfe",
+					&[0xfe],
+				)),
+				// Failures of precompiles, their reason readable as is.
+				ExitError::Other(message) => Err(crate::internal_err_with_data(
+					format!("evm error: {}", message),
+					&[],
+				)),
+				// The creation targets an address that already has code or a nonce.
+				ExitError::CreateCollision => Err(crate::internal_err_with_data(
+					"evm error: contract creation collision",
+					&[],
+				)),
+				_ => Err(crate::internal_err_with_data(
+					format!("evm error: {:?}", e),
+					&[],
+				)),
+			}
 		}
 		ExitReason::Revert(_) => {
 			const LEN_START: usize = 36;
@@ -727,7 +1294,11 @@ pub fn error_on_execution_failure(reason: &ExitReason, data: &[u8]) -> Result<()
 				if data.len() >= message_end {
 					let body: &[u8] = &data[MESSAGE_START..message_end];
 					if let Ok(reason) = std::str::from_utf8(body) {
-						message = format!("{} {}", message, reason);
+						message = format!(
+							"{} {}",
+							message,
+							truncate_revert_reason(reason, max_revert_reason_len)
+						);
 					}
 				}
 			}
@@ -740,6 +1311,19 @@ pub fn error_on_execution_failure(reason: &ExitReason, data: &[u8]) -> Result<()
 	}
 }
 
+/// Cuts `reason` to at most `max_len` bytes, on a character boundary, marking
+/// the cut with an ellipsis.
+fn truncate_revert_reason(reason: &str, max_len: usize) -> String {
+	if reason.len() <= max_len {
+		return reason.to_string();
+	}
+	let mut end = max_len;
+	while !reason.is_char_boundary(end) {
+		end -= 1;
+	}
+	format!("{}...", &reason[..end])
+}
+
 struct FeeDetails {
 	gas_price: Option<U256>,
 	max_fee_per_gas: Option<U256>,
@@ -791,3 +1375,900 @@ fn fee_details(
 		}
 	}
 }
+
+/// Receipt of `transaction` from its speculative execution, without the fields of
+/// the block it is not part of yet.
+fn pending_receipt_build(
+	transaction: &EthereumTransaction,
+	base_fee: Option<U256>,
+	from: H160,
+	to: Option<H160>,
+	contract_address: Option<H160>,
+	exit_reason: &ExitReason,
+	used_gas: U256,
+	logs: &[ethereum::Log],
+) -> PendingReceipt {
+	let transaction_hash = transaction.hash();
+	let mut logs_bloom = Bloom::default();
+	for log in logs {
+		logs_bloom.accrue(BloomInput::Raw(&log.address[..]));
+		for topic in &log.topics {
+			logs_bloom.accrue(BloomInput::Raw(&topic[..]));
+		}
+	}
+	// An EIP-1559 transaction pays the base fee projected for the pending block
+	// plus its tip, within its fee cap; the cap alone when there is no projection.
+	let (effective_gas_price, transaction_type) = match transaction {
+		EthereumTransaction::Legacy(t) => (t.gas_price, 0u8),
+		EthereumTransaction::EIP2930(t) => (t.gas_price, 1u8),
+		EthereumTransaction::EIP1559(t) => (
+			base_fee.map_or(t.max_fee_per_gas, |base_fee| {
+				t.max_fee_per_gas
+					.min(base_fee.saturating_add(t.max_priority_fee_per_gas))
+			}),
+			2u8,
+		),
+	};
+	let status_code = match exit_reason {
+		ExitReason::Succeed(_) => 1u64,
+		_ => 0u64,
+	};
+
+	PendingReceipt {
+		receipt: Receipt {
+			transaction_hash: Some(transaction_hash),
+			transaction_index: None,
+			block_hash: None,
+			from: Some(from),
+			to,
+			block_number: None,
+			// Gas of the transactions ahead of it in the block is not known yet.
+			cumulative_gas_used: used_gas,
+			gas_used: Some(used_gas),
+			contract_address,
+			// The block fields of the logs are left out like the receipt's.
+			logs: transaction_logs(H256::zero(), U256::zero(), transaction_hash, 0, 0, logs)
+				.into_iter()
+				.map(|log| Log {
+					block_hash: None,
+					block_number: None,
+					transaction_index: None,
+					log_index: None,
+					..log
+				})
+				.collect(),
+			state_root: None,
+			logs_bloom,
+			status_code: Some(U64::from(status_code)),
+			effective_gas_price,
+			transaction_type: U256::from(transaction_type),
+		},
+		speculative: true,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::vicinity;
+	use evm::{
+		backend::{MemoryAccount, MemoryBackend},
+		executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata},
+		Config,
+	};
+	use std::collections::BTreeMap;
+
+	/// Calls a contract storing a non-zero value with `gas_limit`.
+	fn transact_store(gas_limit: U256) -> ExitReason {
+		// PUSH1 1 PUSH1 0 SSTORE STOP
+		transact(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], gas_limit)
+	}
+
+	/// Calls a contract running `code` with `gas_limit`.
+	fn transact(code: Vec<u8>, gas_limit: U256) -> ExitReason {
+		transact_with_data(code, gas_limit).0
+	}
+
+	/// Calls a contract running `code` with `gas_limit`, with the returned data.
+	fn transact_with_data(code: Vec<u8>, gas_limit: U256) -> (ExitReason, Vec<u8>) {
+		let contract = H160::repeat_byte(0xc0);
+		let vicinity = vicinity();
+		let mut state = BTreeMap::new();
+		state.insert(
+			contract,
+			MemoryAccount {
+				nonce: U256::one(),
+				balance: U256::zero(),
+				storage: BTreeMap::new(),
+				code,
+			},
+		);
+		let backend = MemoryBackend::new(&vicinity, state);
+
+		let config = Config::london();
+		let gas_limit = gas_limit.low_u64();
+		let metadata = StackSubstateMetadata::new(gas_limit, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+		executor.transact_call(
+			H160::repeat_byte(0xca),
+			contract,
+			U256::zero(),
+			Vec::new(),
+			gas_limit,
+			Vec::new(),
+		)
+	}
+
+	#[test]
+	fn gas_search_starts_at_the_intrinsic_gas() {
+		let request = CallRequest {
+			to: Some(H160::repeat_byte(0x11)),
+			data: Some(Bytes(vec![0xff; 10_000])),
+			..Default::default()
+		};
+		let config = Config::london();
+		let floor = intrinsic_gas_floor(&config, &request);
+		assert_eq!(floor, U256::from(21_000 + 10_000 * 16));
+		let create = CallRequest {
+			to: None,
+			..request.clone()
+		};
+		assert_eq!(intrinsic_gas_floor(&config, &create), floor + 32_000);
+		// Calldata cost more before Istanbul.
+		assert_eq!(
+			intrinsic_gas_floor(&Config::frontier(), &request),
+			U256::from(21_000 + 10_000 * 68)
+		);
+
+		let needed = floor + 5_000;
+		let search = |lowest| {
+			let mut executions = 0;
+			let estimate =
+				search_gas_limit(lowest, U256::from(15_000_000u64), needed, None, |gas| {
+					executions += 1;
+					Ok(gas >= needed)
+				})
+				.unwrap();
+			(estimate, executions)
+		};
+
+		let (flat_estimate, flat_executions) = search(U256::from(21_000));
+		let (estimate, executions) = search(floor);
+		assert!(executions < flat_executions);
+		assert!(estimate >= needed && estimate < needed * 11 / 10);
+		assert!(flat_estimate >= needed);
+	}
+
+	#[test]
+	fn gas_below_intrinsic_is_rejected() {
+		let request = CallRequest {
+			to: Some(H160::repeat_byte(0x11)),
+			data: Some(Bytes(vec![0xff; 10])),
+			gas: Some(U256::from(21_000)),
+			..Default::default()
+		};
+		let config = Config::london();
+		let err = ensure_intrinsic_gas(&config, &request).unwrap_err();
+		assert_eq!(
+			err.message(),
+			"intrinsic gas too low: have 21000, want 21160"
+		);
+
+		let request = CallRequest {
+			gas: Some(U256::from(21_160)),
+			..request
+		};
+		assert!(ensure_intrinsic_gas(&config, &request).is_ok());
+		assert!(ensure_intrinsic_gas(&config, &CallRequest::default()).is_ok());
+	}
+
+	#[test]
+	fn gas_search_times_out_with_the_current_bounds() {
+		// JUMPDEST PUSH1 0 JUMP, looping until out of gas.
+		let slow = || vec![0x5b, 0x60, 0x00, 0x56];
+		let highest = U256::from(15_000_000u64);
+		let err = search_gas_limit(
+			U256::from(21_000),
+			highest,
+			highest,
+			Some(Duration::from_millis(1)),
+			|gas| Ok(transact(slow(), gas).is_succeed()),
+		)
+		.unwrap_err()
+		.to_string();
+		assert!(err.contains("gas estimation timeout of 1 ms exceeded"));
+		// The first execution ran out of gas at the midpoint, raising the lower bound.
+		assert!(err.contains(&format!(
+			"gas limit between {} and {}",
+			(highest + 21_000) / 2,
+			highest
+		)));
+	}
+
+	#[test]
+	fn reverts_fail_the_estimate_at_any_limit() {
+		// Error("no"), reverted whatever the gas.
+		let mut reason = vec![0x08, 0xc3, 0x79, 0xa0];
+		reason.extend_from_slice(&H256::from_low_u64_be(32).0);
+		reason.extend_from_slice(&H256::from_low_u64_be(2).0);
+		reason.extend_from_slice(&H256::from_slice(&[&b"no"[..], &[0u8; 30][..]].concat()).0);
+		// PUSH32 word PUSH1 offset MSTORE for each word, then PUSH1 len PUSH1 0 REVERT.
+		let mut code = Vec::new();
+		for (index, word) in reason.chunks(32).enumerate() {
+			let mut padded = [0u8; 32];
+			padded[..word.len()].copy_from_slice(word);
+			code.push(0x7f);
+			code.extend_from_slice(&padded);
+			code.extend_from_slice(&[0x60, (index * 32) as u8, 0x52]);
+		}
+		code.extend_from_slice(&[0x60, reason.len() as u8, 0x60, 0x00, 0xfd]);
+
+		let highest = U256::from(15_000_000u64);
+		let (exit_reason, data) = transact_with_data(code.clone(), highest);
+		assert!(matches!(exit_reason, ExitReason::Revert(_)));
+		assert_eq!(data, reason);
+
+		// Reverting with the highest limit fails the estimate with the revert error.
+		let expect_revert = |err: jsonrpsee::core::Error| {
+			let err = match err {
+				jsonrpsee::core::Error::Call(CallError::Custom(err)) => err,
+				_ => panic!("revert is an error with data"),
+			};
+			assert_eq!(
+				err.message(),
+				"VM Exception while processing transaction: revert no"
+			);
+			assert_eq!(
+				err.data().map(|data| data.get().to_string()),
+				Some(format!("\"{}\"", format_hex(&reason)))
+			);
+		};
+		expect_revert(gas_search_outcome(&exit_reason, &data, 64).unwrap_err());
+
+		// Below the highest limit, the first execution of the search fails it the same.
+		let mut executions = 0;
+		let err = search_gas_limit(
+			U256::from(21_000),
+			highest,
+			U256::from(21_000),
+			None,
+			|gas| {
+				executions += 1;
+				assert!(gas < highest);
+				let (exit_reason, data) = transact_with_data(code.clone(), gas);
+				gas_search_outcome(&exit_reason, &data, 64)
+			},
+		)
+		.unwrap_err();
+		assert_eq!(executions, 1);
+		expect_revert(err);
+	}
+
+	#[test]
+	fn gas_search_only_raises_the_limit_when_out_of_gas() {
+		let highest = U256::from(15_000_000u64);
+		let mut out_of_gas = 0;
+		let estimate = search_gas_limit(
+			U256::from(21_000),
+			highest,
+			U256::from(10_000),
+			None,
+			|gas| {
+				let exit_reason = transact_store(gas);
+				if exit_reason == ExitReason::Error(ExitError::OutOfGas) {
+					out_of_gas += 1;
+				}
+				gas_search_outcome(&exit_reason, &[], 64)
+			},
+		)
+		.unwrap();
+		// The first executions run out of gas, the search converging above them.
+		assert!(out_of_gas > 0);
+		assert!(transact_store(estimate).is_succeed());
+
+		// INVALID: the first execution fails the search.
+		let mut executions = 0;
+		let err = search_gas_limit(U256::from(21_000), highest, highest, None, |gas| {
+			executions += 1;
+			gas_search_outcome(&transact(vec![0xfe], gas), &[], 64)
+		})
+		.unwrap_err();
+		assert_eq!(executions, 1);
+		assert!(err.to_string().contains("evm error: invalid opcode 0xfe"));
+
+		let fatal = ExitReason::Fatal(evm::ExitFatal::NotSupported);
+		assert!(gas_search_outcome(&fatal, &[], 64).is_err());
+		let too_deep = ExitReason::Error(ExitError::CallTooDeep);
+		assert!(gas_search_outcome(&too_deep, &[], 64).is_err());
+	}
+
+	#[test]
+	fn gas_search_fails_fast_on_a_revert() {
+		// GAS PUSH3 50000 LT PUSH1 0x0e JUMPI PUSH1 0 PUSH1 0 REVERT JUMPDEST STOP:
+		// reverts unless more than 50000 gas is left.
+		let code = vec![
+			0x5a, 0x62, 0x00, 0xc3, 0x50, 0x10, 0x60, 0x0e, 0x57, 0x60, 0x00, 0x60, 0x00, 0xfd,
+			0x5b, 0x00,
+		];
+		let highest = U256::from(15_000_000u64);
+		assert!(transact(code.clone(), highest).is_succeed());
+		assert!(matches!(
+			transact(code.clone(), U256::from(60_000)),
+			ExitReason::Revert(_)
+		));
+
+		// The search stops at the first revert rather than raising the limit.
+		let mut reverts = 0;
+		let err = search_gas_limit(U256::from(21_000), highest, highest, None, |gas| {
+			let (exit_reason, data) = transact_with_data(code.clone(), gas);
+			if matches!(exit_reason, ExitReason::Revert(_)) {
+				reverts += 1;
+			}
+			gas_search_outcome(&exit_reason, &data, 64)
+		})
+		.unwrap_err();
+		assert_eq!(reverts, 1);
+		assert!(err
+			.to_string()
+			.contains("VM Exception while processing transaction: revert"));
+	}
+
+	/// Addresses of the standard precompiles, `1..=9`.
+	fn standard_precompiles() -> BTreeSet<H160> {
+		(1..=9).map(H160::from_low_u64_be).collect()
+	}
+
+	/// Costs of the standard precompiles of linear cost, at `1..=4`.
+	fn standard_linear_costs() -> BTreeMap<H160, LinearPrecompileCost> {
+		[(1, 3_000, 0), (2, 60, 12), (3, 600, 120), (4, 15, 3)]
+			.into_iter()
+			.map(|(address, base, word)| {
+				(
+					H160::from_low_u64_be(address),
+					LinearPrecompileCost { base, word },
+				)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn precompile_gas_estimates() {
+		let config = Config::london();
+		let costs = standard_linear_costs();
+		// 33 bytes, 32 of them non-zero: two words.
+		let mut input = vec![0xffu8; 32];
+		input.push(0);
+		let intrinsic = 21_000 + 32 * 16 + 4;
+
+		let identity = H160::from_low_u64_be(4);
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, identity, &input),
+			Some(U256::from(intrinsic + 15 + 3 * 2))
+		);
+		let sha256 = H160::from_low_u64_be(2);
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, sha256, &input),
+			Some(U256::from(intrinsic + 60 + 12 * 2))
+		);
+		let ripemd160 = H160::from_low_u64_be(3);
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, ripemd160, &input),
+			Some(U256::from(intrinsic + 600 + 120 * 2))
+		);
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, identity, &[]),
+			Some(U256::from(21_000 + 15))
+		);
+
+		let ecrecover = H160::from_low_u64_be(1);
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, ecrecover, &[0u8; 128]),
+			Some(U256::from(21_000 + 128 * 4 + 3_000))
+		);
+
+		// Calldata cost more before Istanbul.
+		assert_eq!(
+			precompile_gas_estimate(&Config::frontier(), &costs, identity, &input),
+			Some(U256::from(21_000 + 32 * 68 + 4 + 15 + 3 * 2))
+		);
+
+		// Modexp depends on the input values, other addresses are not precompiles.
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, H160::from_low_u64_be(5), &input),
+			None
+		);
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, H160::repeat_byte(0x11), &input),
+			None
+		);
+		let mut high = [0u8; 20];
+		high[0] = 1;
+		high[19] = 4;
+		assert_eq!(
+			precompile_gas_estimate(&config, &costs, H160::from(high), &input),
+			None
+		);
+	}
+
+	/// Identity precompile at 0x400, outside of the standard range.
+	struct HighIdentity;
+
+	impl evm::executor::stack::PrecompileSet for HighIdentity {
+		fn execute(
+			&self,
+			handle: &mut impl fp_evm::PrecompileHandle,
+		) -> Option<fp_evm::PrecompileResult> {
+			if handle.code_address() != H160::from_low_u64_be(0x400) {
+				return None;
+			}
+			let words = (handle.input().len() as u64 + 31) / 32;
+			if let Err(exit_status) = handle.record_cost(15 + 3 * words) {
+				return Some(Err(fp_evm::PrecompileFailure::Error { exit_status }));
+			}
+			Some(Ok(fp_evm::PrecompileOutput {
+				exit_status: evm::ExitSucceed::Returned,
+				output: handle.input().to_vec(),
+			}))
+		}
+
+		fn is_precompile(&self, address: H160) -> bool {
+			address == H160::from_low_u64_be(0x400)
+		}
+	}
+
+	/// Modexp at 0x05, and a precompile at 0x400 reverting with `Error("bad input")`
+	/// unless given some input.
+	struct FailingPrecompiles;
+
+	impl evm::executor::stack::PrecompileSet for FailingPrecompiles {
+		fn execute(
+			&self,
+			handle: &mut impl fp_evm::PrecompileHandle,
+		) -> Option<fp_evm::PrecompileResult> {
+			use fp_evm::Precompile;
+
+			let address = handle.code_address();
+			if address == H160::from_low_u64_be(5) {
+				return Some(pallet_evm_precompile_modexp::Modexp::execute(handle));
+			}
+			if address != H160::from_low_u64_be(0x400) {
+				return None;
+			}
+			if handle.input().is_empty() {
+				return Some(Err(fp_evm::PrecompileFailure::Revert {
+					exit_status: evm::ExitRevert::Reverted,
+					output: revert_reason("bad input"),
+				}));
+			}
+			Some(Ok(fp_evm::PrecompileOutput {
+				exit_status: evm::ExitSucceed::Returned,
+				output: Vec::new(),
+			}))
+		}
+
+		fn is_precompile(&self, address: H160) -> bool {
+			address == H160::from_low_u64_be(5) || address == H160::from_low_u64_be(0x400)
+		}
+	}
+
+	/// ABI encoded `Error(reason)`.
+	fn revert_reason(reason: &str) -> Vec<u8> {
+		let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+		data.extend_from_slice(&H256::from_low_u64_be(32).0);
+		data.extend_from_slice(&H256::from_low_u64_be(reason.len() as u64).0);
+		let mut padded = reason.as_bytes().to_vec();
+		padded.resize((reason.len() + 31) / 32 * 32, 0);
+		data.extend_from_slice(&padded);
+		data
+	}
+
+	#[test]
+	fn precompile_failures_are_reported() {
+		let vicinity = vicinity();
+		let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+		let config = Config::london();
+		let call = |to: u64, input: Vec<u8>| {
+			let metadata = StackSubstateMetadata::new(100_000, &config);
+			let state = MemoryStackState::new(metadata, &backend);
+			let mut executor =
+				StackExecutor::new_with_precompiles(state, &config, &FailingPrecompiles);
+			let (exit_reason, data) = executor.transact_call(
+				H160::repeat_byte(0xca),
+				H160::from_low_u64_be(to),
+				U256::zero(),
+				input,
+				100_000,
+				Vec::new(),
+			);
+			let err = match error_on_execution_failure(&exit_reason, &data, 64) {
+				Err(jsonrpsee::core::Error::Call(CallError::Custom(err))) => err,
+				_ => panic!("precompile failure is an error with data"),
+			};
+			(
+				err.message().to_string(),
+				err.data().map(|data| data.get().to_string()),
+			)
+		};
+
+		// Modexp fails on an input too short to hold the lengths of its operands.
+		assert_eq!(
+			call(5, vec![0u8; 32]),
+			(
+				"evm error: input must contain at least 96 bytes".to_string(),
+				Some("\"0x\"".to_string())
+			)
+		);
+		// A revert keeps its reason and output.
+		assert_eq!(
+			call(0x400, Vec::new()),
+			(
+				"VM Exception while processing transaction: revert bad input".to_string(),
+				Some(format!("\"{}\"", format_hex(&revert_reason("bad input"))))
+			)
+		);
+	}
+
+	#[test]
+	fn configured_precompile_addresses_are_recognized() {
+		let identity = H160::from_low_u64_be(4);
+		let high_identity = H160::from_low_u64_be(0x400);
+		let sha256 = H160::from_low_u64_be(2);
+		let config = Config::london();
+		let costs: BTreeMap<H160, LinearPrecompileCost> = [
+			(identity, LinearPrecompileCost { base: 15, word: 3 }),
+			(high_identity, LinearPrecompileCost { base: 15, word: 3 }),
+		]
+		.into_iter()
+		.collect();
+
+		// Only precompiles of a configured cost are estimated.
+		assert!(precompile_gas_estimate(&config, &costs, identity, &[]).is_some());
+		assert_eq!(precompile_gas_estimate(&config, &costs, sha256, &[]), None);
+		let estimate = precompile_gas_estimate(&config, &costs, high_identity, &[]);
+		assert_eq!(estimate, Some(U256::from(21_000 + 15)));
+		// No address is assumed to be a precompile.
+		assert_eq!(
+			precompile_gas_estimate(&config, &BTreeMap::new(), identity, &[]),
+			None
+		);
+
+		// The estimate is the gas used calling it, more than a transfer to an
+		// address without code.
+		let vicinity = vicinity();
+		let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+		let metadata = StackSubstateMetadata::new(100_000, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &HighIdentity);
+		let (exit_reason, _) = executor.transact_call(
+			H160::repeat_byte(0xca),
+			high_identity,
+			U256::zero(),
+			Vec::new(),
+			100_000,
+			Vec::new(),
+		);
+		assert_eq!(exit_reason, ExitReason::Succeed(evm::ExitSucceed::Returned));
+		assert_eq!(U256::from(executor.used_gas()), estimate.unwrap());
+	}
+
+	#[test]
+	fn zero_gas_price_calls_set_no_fee() {
+		// The runtime charges no fee for calls without a max fee, and reads a zero
+		// `GASPRICE` in them.
+		let legacy = fee_details(Some(U256::zero()), None, None).unwrap();
+		assert_eq!(legacy.gas_price, None);
+		assert_eq!(legacy.max_fee_per_gas, None);
+		let eip1559 = fee_details(None, Some(U256::zero()), None).unwrap();
+		assert_eq!(eip1559.max_fee_per_gas, None);
+	}
+
+	#[test]
+	fn return_data_size_limit() {
+		let returned = vec![0xffu8; 64 * 1024];
+
+		let err = ensure_return_data_size(&returned, 1024).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("call returned 65536 bytes of data, exceeding the limit of 1024 bytes"));
+
+		assert!(ensure_return_data_size(&returned, 10 * 1024 * 1024).is_ok());
+		assert!(ensure_return_data_size(&returned, returned.len()).is_ok());
+	}
+
+	#[test]
+	fn multicall_size_limit() {
+		let err = ensure_multicall_size(101, 100).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("multicall of 101 calls exceeds the limit of 100 calls"));
+
+		assert!(ensure_multicall_size(100, 100).is_ok());
+		assert!(ensure_multicall_size(0, 100).is_ok());
+	}
+
+	#[test]
+	fn rpc_gas_cap_bounds_execution_gas() {
+		let max_gas_limit = U256::from(15_000_000u64);
+
+		assert_eq!(
+			execution_gas_limit(None, max_gas_limit, 10, None).unwrap(),
+			max_gas_limit
+		);
+		let capped = execution_gas_limit(None, max_gas_limit, 10, Some(30_000)).unwrap();
+		assert_eq!(capped, U256::from(30_000));
+		assert_eq!(
+			execution_gas_limit(Some(U256::from(50_000)), max_gas_limit, 10, Some(30_000)).unwrap(),
+			capped
+		);
+		assert_eq!(
+			execution_gas_limit(Some(U256::from(25_000)), max_gas_limit, 10, Some(30_000)).unwrap(),
+			U256::from(25_000)
+		);
+		assert!(
+			execution_gas_limit(Some(max_gas_limit + 1), max_gas_limit, 10, Some(30_000)).is_err()
+		);
+
+		// The block gas limit allows the store, the cap does not.
+		assert!(matches!(
+			transact_store(max_gas_limit),
+			ExitReason::Succeed(_)
+		));
+		assert_eq!(
+			transact_store(capped),
+			ExitReason::Error(ExitError::OutOfGas)
+		);
+	}
+
+	#[test]
+	fn oversized_revert_reason_is_truncated() {
+		let reason = "x".repeat(1024 * 1024);
+		let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+		data.extend_from_slice(&H256::from_low_u64_be(32).0);
+		data.extend_from_slice(&H256::from_low_u64_be(reason.len() as u64).0);
+		data.extend_from_slice(reason.as_bytes());
+
+		let err = match error_on_execution_failure(
+			&ExitReason::Revert(evm::ExitRevert::Reverted),
+			&data,
+			16,
+		) {
+			Err(jsonrpsee::core::Error::Call(CallError::Custom(err))) => err,
+			_ => panic!("revert is an error with data"),
+		};
+		assert_eq!(
+			err.message(),
+			"VM Exception while processing transaction: revert xxxxxxxxxxxxxxxx..."
+		);
+		assert_eq!(
+			err.data().map(|data| data.get().to_string()),
+			Some(format!("\"{}\"", format_hex(&data)))
+		);
+
+		assert_eq!(truncate_revert_reason("short", 16), "short");
+		// Cuts happen on character boundaries.
+		assert_eq!(truncate_revert_reason("aé", 2), "a...");
+	}
+
+	#[test]
+	fn invalid_opcode_is_reported() {
+		// PUSH1 1 followed by the undefined 0x0c opcode.
+		let exit_reason = transact(vec![0x60, 0x01, 0x0c], U256::from(100_000u64));
+		assert_eq!(
+			exit_reason,
+			ExitReason::Error(ExitError::InvalidCode(evm::Opcode(0x0c)))
+		);
+
+		let err = match error_on_execution_failure(&exit_reason, &[], 16) {
+			Err(jsonrpsee::core::Error::Call(CallError::Custom(err))) => err,
+			_ => panic!("evm error is an error with data"),
+		};
+		assert_eq!(err.message(), "evm error: invalid opcode 0x0c");
+		assert_eq!(
+			err.data().map(|data| data.get().to_string()),
+			Some("\"0x0c\"".to_string())
+		);
+	}
+
+	#[test]
+	fn calling_an_address_without_code_is_reported() {
+		let to = H160::repeat_byte(0xc0);
+		let precompiles = standard_precompiles();
+		let err = ensure_callee_has_code(&precompiles, to, || Ok(Vec::new())).unwrap_err();
+		assert!(err.to_string().contains("called address has no code"));
+
+		assert!(ensure_callee_has_code(&precompiles, to, || Ok(vec![0x00])).is_ok());
+	}
+
+	#[test]
+	fn calling_a_precompile_with_data_is_not_reported() {
+		// Sha256, its code is not read.
+		let sha256 = H160::from_low_u64_be(2);
+		assert!(ensure_callee_has_code(&standard_precompiles(), sha256, || unreachable!()).is_ok());
+
+		let addresses: BTreeSet<H160> = [H160::from_low_u64_be(0x400)].into_iter().collect();
+		assert!(
+			ensure_callee_has_code(&addresses, H160::from_low_u64_be(0x400), || {
+				unreachable!()
+			})
+			.is_ok()
+		);
+		// Not one of the configured precompiles.
+		let err = ensure_callee_has_code(&addresses, sha256, || Ok(Vec::new())).unwrap_err();
+		assert!(err.to_string().contains("called address has no code"));
+	}
+
+	#[test]
+	fn create_collision_is_reported() {
+		let caller = H160::repeat_byte(0xca);
+		let mut stream = rlp::RlpStream::new_list(2);
+		stream.append(&caller);
+		stream.append(&U256::zero());
+		let address = H160::from_slice(&sp_core::hashing::keccak_256(&stream.out())[12..]);
+
+		let vicinity = vicinity();
+		// The address the caller deploys to already has code.
+		let mut state = BTreeMap::new();
+		state.insert(
+			address,
+			MemoryAccount {
+				nonce: U256::zero(),
+				balance: U256::zero(),
+				storage: BTreeMap::new(),
+				code: vec![0x00],
+			},
+		);
+		let backend = MemoryBackend::new(&vicinity, state);
+		let config = Config::london();
+		let metadata = StackSubstateMetadata::new(1_000_000, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+		let (exit_reason, _) =
+			executor.transact_create(caller, U256::zero(), vec![0x00], 1_000_000, Vec::new());
+		assert_eq!(exit_reason, ExitReason::Error(ExitError::CreateCollision));
+
+		let err = error_on_execution_failure(&exit_reason, &[], 0).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("evm error: contract creation collision"));
+	}
+
+	#[test]
+	fn create_gas_includes_the_code_deposit() {
+		// PUSH1 0x0a PUSH1 0x00 RETURN: deploys 10 zero bytes.
+		let init_code = vec![0x60, 0x0a, 0x60, 0x00, 0xf3];
+		let code_len = 10;
+
+		let create = |gas_limit: u64| {
+			let vicinity = vicinity();
+			let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+			let config = Config::london();
+			let metadata = StackSubstateMetadata::new(gas_limit, &config);
+			let state = MemoryStackState::new(metadata, &backend);
+			let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+			let (exit_reason, _) = executor.transact_create(
+				H160::repeat_byte(0xca),
+				U256::zero(),
+				init_code.clone(),
+				gas_limit,
+				Vec::new(),
+			);
+			(exit_reason, executor.used_gas())
+		};
+
+		let (exit_reason, used_gas) = create(1_000_000);
+		assert!(exit_reason.is_succeed());
+		// 21000 base and 32000 creation, 4 non-zero and 1 zero byte of init code,
+		// two PUSH1 and a one word memory expansion, then 200 per deployed byte.
+		let intrinsic = 21_000 + 32_000 + 4 * 16 + 4;
+		let execution = 3 + 3 + 3;
+		assert_eq!(used_gas, intrinsic + execution + 200 * code_len);
+
+		// An estimate leaving out the deposit would fail the deployment.
+		let (exit_reason, _) = create(used_gas - 1);
+		assert!(!exit_reason.is_succeed());
+	}
+
+	#[test]
+	fn pending_receipts_report_the_execution_status() {
+		let transaction = EthereumTransaction::Legacy(ethereum::LegacyTransaction {
+			nonce: U256::zero(),
+			gas_price: U256::from(1_000_000_000u64),
+			gas_limit: U256::from(100_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x11)),
+			value: U256::zero(),
+			input: Vec::new(),
+			signature: ethereum::TransactionSignature::new(
+				37,
+				H256::repeat_byte(0x01),
+				H256::repeat_byte(0x01),
+			)
+			.unwrap(),
+		});
+		let log = ethereum::Log {
+			address: H160::repeat_byte(0x11),
+			topics: vec![H256::repeat_byte(0x22)],
+			data: vec![0x33],
+		};
+		let build = |exit_reason: ExitReason| {
+			pending_receipt_build(
+				&transaction,
+				None,
+				H160::repeat_byte(0xaa),
+				Some(H160::repeat_byte(0x11)),
+				None,
+				&exit_reason,
+				U256::from(21_000u64),
+				&[log.clone()],
+			)
+		};
+
+		let succeeded = build(ExitReason::Succeed(evm::ExitSucceed::Stopped));
+		assert!(succeeded.speculative);
+		assert_eq!(succeeded.receipt.status_code, Some(U64::one()));
+		assert_eq!(succeeded.receipt.gas_used, Some(U256::from(21_000u64)));
+		assert_eq!(succeeded.receipt.block_hash, None);
+		assert_eq!(succeeded.receipt.logs.len(), 1);
+		assert_eq!(
+			succeeded.receipt.logs[0].transaction_hash,
+			Some(transaction.hash())
+		);
+		assert_eq!(succeeded.receipt.logs[0].log_index, None);
+		assert_eq!(
+			succeeded.receipt.logs[0].transaction_log_index,
+			Some(U256::zero())
+		);
+		assert!(succeeded
+			.receipt
+			.logs_bloom
+			.contains_input(BloomInput::Raw(&log.address[..])));
+
+		let reverted = build(ExitReason::Revert(evm::ExitRevert::Reverted));
+		assert_eq!(reverted.receipt.status_code, Some(U64::zero()));
+	}
+
+	#[test]
+	fn pending_receipts_price_eip1559_transactions_at_the_projected_base_fee() {
+		let transaction = EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
+			chain_id: 42,
+			nonce: U256::zero(),
+			max_priority_fee_per_gas: U256::from(2_000_000_000u64),
+			max_fee_per_gas: U256::from(10_000_000_000u64),
+			gas_limit: U256::from(100_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x11)),
+			value: U256::zero(),
+			input: Vec::new(),
+			access_list: Vec::new(),
+			odd_y_parity: false,
+			r: H256::repeat_byte(0x01),
+			s: H256::repeat_byte(0x01),
+		});
+		let effective_gas_price = |base_fee: Option<u64>| {
+			pending_receipt_build(
+				&transaction,
+				base_fee.map(U256::from),
+				H160::repeat_byte(0xaa),
+				Some(H160::repeat_byte(0x11)),
+				None,
+				&ExitReason::Succeed(evm::ExitSucceed::Stopped),
+				U256::from(21_000u64),
+				&[],
+			)
+			.receipt
+			.effective_gas_price
+		};
+
+		// Base fee plus tip.
+		assert_eq!(
+			effective_gas_price(Some(1_000_000_000)),
+			U256::from(3_000_000_000u64)
+		);
+		// Capped by the max fee.
+		assert_eq!(
+			effective_gas_price(Some(9_000_000_000)),
+			U256::from(10_000_000_000u64)
+		);
+		// Without a projection, the max fee.
+		assert_eq!(effective_gas_price(None), U256::from(10_000_000_000u64));
+	}
+}
@@ -16,6 +16,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
 use ethereum_types::{H256, U256};
 use jsonrpsee::core::RpcResult as Result;
 // Substrate
@@ -32,7 +34,7 @@ use sp_runtime::{
 use fc_rpc_core::types::*;
 use fp_rpc::EthereumRuntimeRPCApi;
 
-use crate::{eth::Eth, frontier_backend_client, internal_err};
+use crate::{eth::Eth, frontier_backend_client, gas_price_oracle::GasPriceContext, internal_err};
 
 impl<B, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A>
 where
@@ -44,12 +46,7 @@ where
 	BE::State: StateBackend<BlakeTwo256>,
 {
 	pub fn gas_price(&self) -> Result<U256> {
-		let block = BlockId::Hash(self.client.info().best_hash);
-
-		self.client
-			.runtime_api()
-			.gas_price(&block)
-			.map_err(|err| internal_err(format!("fetch runtime chain id failed: {:?}", err)))
+		self.with_gas_price_context(|context| self.gas_price_oracle.gas_price(context))
 	}
 
 	pub fn fee_history(
@@ -65,6 +62,14 @@ where
 		} else {
 			block_count.as_u64()
 		};
+		if block_count == 0 {
+			return Ok(FeeHistory {
+				oldest_block: U256::zero(),
+				base_fee_per_gas: Vec::new(),
+				gas_used_ratio: Vec::new(),
+				reward: None,
+			});
+		}
 
 		if let Ok(Some(id)) = frontier_backend_client::native_block_id::<B, C>(
 			self.client.as_ref(),
@@ -86,99 +91,37 @@ where
 					)));
 				}
 			};
-			// Highest and lowest block number within the requested range.
-			let highest = UniqueSaturatedInto::<u64>::unique_saturated_into(number);
-			let lowest = highest.saturating_sub(block_count.saturating_sub(1));
+			let (lowest, highest) = fee_history_range(
+				UniqueSaturatedInto::<u64>::unique_saturated_into(number),
+				block_count,
+			);
 			// Tip of the chain.
 			let best_number =
 				UniqueSaturatedInto::<u64>::unique_saturated_into(self.client.info().best_number);
-			// Only support in-cache queries.
-			if lowest < best_number.saturating_sub(self.fee_history_cache_limit) {
+			// Only support in-cache queries, the blocks older than the cache being left out.
+			let lowest = lowest.max(best_number.saturating_sub(self.fee_history_cache_limit));
+			if lowest > highest {
 				return Err(internal_err("Block range out of bounds."));
 			}
 			if let Ok(fee_history_cache) = &self.fee_history_cache.lock() {
-				let mut response = FeeHistory {
-					oldest_block: U256::from(lowest),
-					base_fee_per_gas: Vec::new(),
-					gas_used_ratio: Vec::new(),
-					reward: None,
-				};
-				let mut rewards = Vec::new();
-				// Iterate over the requested block range.
-				for n in lowest..highest + 1 {
-					if let Some(block) = fee_history_cache.get(&n) {
-						response.base_fee_per_gas.push(U256::from(block.base_fee));
-						response.gas_used_ratio.push(block.gas_used_ratio);
-						// If the request includes reward percentiles, get them from the cache.
-						if let Some(ref requested_percentiles) = reward_percentiles {
-							let mut block_rewards = Vec::new();
-							// Resolution is half a point. I.e. 1.0,1.5
-							let resolution_per_percentile: f64 = 2.0;
-							// Get cached reward for each provided percentile.
-							for p in requested_percentiles {
-								// Find the cache index from the user percentile.
-								let p = p.clamp(0.0, 100.0);
-								let index = ((p.round() / 2f64) * 2f64) * resolution_per_percentile;
-								// Get and push the reward.
-								let reward = if let Some(r) = block.rewards.get(index as usize) {
-									U256::from(*r)
-								} else {
-									U256::zero()
-								};
-								block_rewards.push(reward);
-							}
-							// Push block rewards.
-							if !block_rewards.is_empty() {
-								// Push block rewards.
-								rewards.push(block_rewards);
-							}
-						}
-					}
-				}
-				if rewards.len() > 0 {
-					response.reward = Some(rewards);
-				}
-				// Calculate next base fee.
-				if let (Some(last_gas_used), Some(last_fee_per_gas)) = (
-					response.gas_used_ratio.last(),
-					response.base_fee_per_gas.last(),
-				) {
-					let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
-						self.client.as_ref(),
-						id,
-					);
-					let handler = self
-						.overrides
-						.schemas
-						.get(&schema)
-						.unwrap_or(&self.overrides.fallback);
-					let default_elasticity = sp_runtime::Permill::from_parts(125_000);
-					let elasticity = handler
-						.elasticity(&id)
-						.unwrap_or(default_elasticity)
-						.deconstruct();
-					let elasticity = elasticity as f64 / 1_000_000f64;
-					let last_fee_per_gas = last_fee_per_gas.as_u64() as f64;
-					if last_gas_used > &0.5 {
-						// Increase base gas
-						let increase = ((last_gas_used - 0.5) * 2f64) * elasticity;
-						let new_base_fee =
-							(last_fee_per_gas + (last_fee_per_gas * increase)) as u64;
-						response.base_fee_per_gas.push(U256::from(new_base_fee));
-					} else if last_gas_used < &0.5 {
-						// Decrease base gas
-						let increase = ((0.5 - last_gas_used) * 2f64) * elasticity;
-						let new_base_fee =
-							(last_fee_per_gas - (last_fee_per_gas * increase)) as u64;
-						response.base_fee_per_gas.push(U256::from(new_base_fee));
-					} else {
-						// Same base gas
-						response
-							.base_fee_per_gas
-							.push(U256::from(last_fee_per_gas as u64));
-					}
-				}
-				return Ok(response);
+				let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
+					self.client.as_ref(),
+					id,
+				);
+				let handler = self
+					.overrides
+					.schemas
+					.get(&schema)
+					.unwrap_or(&self.overrides.fallback);
+				let default_elasticity = sp_runtime::Permill::from_parts(125_000);
+				let elasticity = handler.elasticity(&id).unwrap_or(default_elasticity);
+				return collect_fee_history(
+					fee_history_cache,
+					lowest,
+					highest,
+					reward_percentiles.as_deref(),
+					elasticity,
+				);
 			} else {
 				return Err(internal_err("Failed to read fee history cache."));
 			}
@@ -189,32 +132,361 @@ where
 		)))
 	}
 
+	/// Base fee projected for the pending block, from the fullness of the best block.
+	pub(crate) fn pending_base_fee(&self) -> Option<U256> {
+		let id = BlockId::Hash(self.client.info().best_hash);
+		let schema =
+			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
+		let handler = self
+			.overrides
+			.schemas
+			.get(&schema)
+			.unwrap_or(&self.overrides.fallback);
+
+		let base_fee = handler.base_fee(&id)?;
+		let block = handler.current_block(&id)?;
+		let gas_used_ratio = gas_used_ratio(block.header.gas_used, block.header.gas_limit);
+		let default_elasticity = sp_runtime::Permill::from_parts(125_000);
+		let elasticity = handler.elasticity(&id).unwrap_or(default_elasticity);
+		Some(next_base_fee(base_fee, gas_used_ratio, elasticity))
+	}
+
 	pub fn max_priority_fee_per_gas(&self) -> Result<U256> {
-		// https://github.com/ethereum/go-ethereum/blob/master/eth/ethconfig/config.go#L44-L51
-		let at_percentile = 60;
-		let block_count = 20;
-		let index = (at_percentile * 2) as usize;
+		self.with_gas_price_context(|context| {
+			self.gas_price_oracle.max_priority_fee_per_gas(context)
+		})
+	}
 
-		let highest =
+	/// Run `price` with the chain state the gas price oracle prices from.
+	fn with_gas_price_context(&self, price: impl FnOnce(&GasPriceContext) -> U256) -> Result<U256> {
+		let block = BlockId::Hash(self.client.info().best_hash);
+		let runtime_gas_price = self
+			.client
+			.runtime_api()
+			.gas_price(&block)
+			.map_err(|err| internal_err(format!("fetch runtime chain id failed: {:?}", err)))?;
+		let pending_base_fee = self.pending_base_fee();
+		let best_number =
 			UniqueSaturatedInto::<u64>::unique_saturated_into(self.client.info().best_number);
-		let lowest = highest.saturating_sub(block_count - 1);
-
-		// https://github.com/ethereum/go-ethereum/blob/master/eth/gasprice/gasprice.go#L149
-		let mut rewards = Vec::new();
-		if let Ok(fee_history_cache) = &self.fee_history_cache.lock() {
-			for n in lowest..highest + 1 {
-				if let Some(block) = fee_history_cache.get(&n) {
-					let reward = if let Some(r) = block.rewards.get(index as usize) {
-						U256::from(*r)
-					} else {
-						U256::zero()
-					};
-					rewards.push(reward);
-				}
-			}
-		} else {
-			return Err(internal_err("Failed to read fee oracle cache."));
+
+		let fee_history_cache = self
+			.fee_history_cache
+			.lock()
+			.map_err(|_| internal_err("Failed to read fee oracle cache."))?;
+		Ok(price(&GasPriceContext {
+			runtime_gas_price,
+			pending_base_fee,
+			best_number,
+			fee_history_cache: &fee_history_cache,
+		}))
+	}
+}
+
+/// Lowest and highest block of a fee history of `block_count > 0` blocks up to `newest`,
+/// clamped at genesis.
+fn fee_history_range(newest: u64, block_count: u64) -> (u64, u64) {
+	(newest.saturating_sub(block_count.saturating_sub(1)), newest)
+}
+
+/// Fee history of the blocks `lowest..=highest` from the fee history cache.
+///
+/// As geth, it reports the longest run of cached blocks ending at `highest`, whose first
+/// block is `oldest_block`, and only fails when `highest` is not cached. The block arrays
+/// have an entry per reported block, `base_fee_per_gas` one more projecting the base fee
+/// of the block following `highest`.
+fn collect_fee_history(
+	fee_history_cache: &BTreeMap<u64, FeeHistoryCacheItem>,
+	lowest: u64,
+	highest: u64,
+	reward_percentiles: Option<&[f64]>,
+	elasticity: sp_runtime::Permill,
+) -> Result<FeeHistory> {
+	if !fee_history_cache.contains_key(&highest) {
+		return Err(internal_err(format!(
+			"Fee history of block {} is not cached.",
+			highest
+		)));
+	}
+	let oldest = (lowest..highest)
+		.rev()
+		.find(|n| !fee_history_cache.contains_key(n))
+		.map_or(lowest, |missing| missing + 1);
+	let mut response = FeeHistory {
+		oldest_block: U256::from(oldest),
+		base_fee_per_gas: Vec::new(),
+		gas_used_ratio: Vec::new(),
+		reward: None,
+	};
+	let reward_percentiles = reward_percentiles.filter(|p| !p.is_empty());
+	let mut rewards = Vec::new();
+	// Iterate over the reported block range.
+	for block in fee_history_cache
+		.range(oldest..=highest)
+		.map(|(_, block)| block)
+	{
+		response.base_fee_per_gas.push(U256::from(block.base_fee));
+		response.gas_used_ratio.push(block.gas_used_ratio);
+		// If the request includes reward percentiles, get them from the cache.
+		if let Some(requested_percentiles) = reward_percentiles {
+			rewards.push(percentile_rewards(&block.rewards, requested_percentiles));
 		}
-		Ok(*rewards.iter().min().unwrap_or(&U256::zero()))
+	}
+	if reward_percentiles.is_some() {
+		response.reward = Some(rewards);
+	}
+	// Calculate next base fee.
+	if let (Some(last_gas_used), Some(last_fee_per_gas)) = (
+		response.gas_used_ratio.last(),
+		response.base_fee_per_gas.last(),
+	) {
+		response.base_fee_per_gas.push(next_base_fee(
+			*last_fee_per_gas,
+			*last_gas_used,
+			elasticity,
+		));
+	}
+	Ok(response)
+}
+
+/// Fraction of `gas_limit` used by `gas_used`, zero for a zero limit, at a parts per
+/// million resolution so that gas amounts beyond 64 bits do not overflow.
+fn gas_used_ratio(gas_used: U256, gas_limit: U256) -> f64 {
+	if gas_limit.is_zero() {
+		return 0f64;
+	}
+	let parts_per_million = gas_used.saturating_mul(U256::from(1_000_000u64)) / gas_limit;
+	parts_per_million.min(U256::from(u64::MAX)).as_u64() as f64 / 1_000_000f64
+}
+
+/// Picks the requested percentiles from the rewards cached for a block, at
+/// half a point resolution (i.e. 1.0, 1.5).
+///
+/// Each requested percentile gets an entry, zero if the block has none cached
+/// (e.g. it had no transactions), so reward arrays stay aligned with the blocks.
+fn percentile_rewards(cached_rewards: &[u64], requested_percentiles: &[f64]) -> Vec<U256> {
+	// Resolution is half a point. I.e. 1.0,1.5
+	let resolution_per_percentile: f64 = 2.0;
+	requested_percentiles
+		.iter()
+		.map(|p| {
+			// Find the cache index from the user percentile.
+			let index = (p.clamp(0.0, 100.0) * resolution_per_percentile).round() as usize;
+			cached_rewards
+				.get(index)
+				.map(|r| U256::from(*r))
+				.unwrap_or_else(U256::zero)
+		})
+		.collect()
+}
+
+/// Base fee of the block following one with `base_fee`, filled at `gas_used_ratio`.
+///
+/// As in EIP-1559, the base fee moves in proportion to the distance between the block
+/// fullness and the half full target, by `elasticity` (12.5% by default) of it for a
+/// full or an empty block. A block above the target raises it by at least one, and it
+/// never falls below zero.
+pub(crate) fn next_base_fee(
+	base_fee: U256,
+	gas_used_ratio: f64,
+	elasticity: sp_runtime::Permill,
+) -> U256 {
+	// Distance from the target in parts per million, 1_000_000 for a full or empty block.
+	let distance = ((gas_used_ratio.clamp(0.0, 1.0) - 0.5).abs() * 2_000_000f64).round() as u64;
+	let change = base_fee
+		.saturating_mul(U256::from(distance))
+		.saturating_mul(U256::from(elasticity.deconstruct()))
+		/ U256::from(1_000_000_000_000u64);
+	if gas_used_ratio > 0.5 {
+		base_fee.saturating_add(change.max(U256::one()))
+	} else if gas_used_ratio < 0.5 {
+		base_fee.saturating_sub(change)
+	} else {
+		base_fee
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Cached rewards as the fee history task stores them, 201 points.
+	fn cached_rewards(min: u64, max: u64) -> Vec<u64> {
+		(0..201u64).map(|i| min + (max - min) * i / 200).collect()
+	}
+
+	#[test]
+	fn percentile_rewards_at_boundaries() {
+		let cached = cached_rewards(10, 210);
+
+		assert_eq!(
+			percentile_rewards(&cached, &[0.0, 100.0]),
+			vec![U256::from(10), U256::from(210)]
+		);
+		// Out of range percentiles are clamped.
+		assert_eq!(
+			percentile_rewards(&cached, &[-5.0, 150.0]),
+			vec![U256::from(10), U256::from(210)]
+		);
+		// Half point resolution.
+		assert_eq!(percentile_rewards(&cached, &[12.5]), vec![U256::from(35)]);
+	}
+
+	#[test]
+	fn empty_block_in_range_keeps_rewards_aligned() {
+		let requested = [0.0, 50.0, 100.0];
+		let blocks = vec![cached_rewards(1, 3), Vec::new(), cached_rewards(4, 6)];
+
+		let rewards: Vec<Vec<U256>> = blocks
+			.iter()
+			.map(|cached| percentile_rewards(cached, &requested))
+			.collect();
+
+		assert_eq!(rewards.len(), 3);
+		assert_eq!(rewards[1], vec![U256::zero(); 3]);
+		assert_eq!(
+			rewards[2],
+			vec![U256::from(4), U256::from(5), U256::from(6)]
+		);
+	}
+
+	fn fee_history_cache(
+		blocks: std::ops::RangeInclusive<u64>,
+	) -> BTreeMap<u64, FeeHistoryCacheItem> {
+		blocks
+			.map(|n| {
+				let item = FeeHistoryCacheItem {
+					base_fee: 1_000 + n,
+					gas_used_ratio: 1.0,
+					rewards: cached_rewards(n, n + 200),
+				};
+				(n, item)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn fee_history_is_aligned_on_the_requested_range() {
+		let elasticity = sp_runtime::Permill::from_parts(125_000);
+		let cache = fee_history_cache(0..=10);
+
+		let (lowest, highest) = fee_history_range(10, 4);
+		assert_eq!((lowest, highest), (7, 10));
+		let history =
+			collect_fee_history(&cache, lowest, highest, Some(&[50.0][..]), elasticity).unwrap();
+		assert_eq!(history.oldest_block, U256::from(7));
+		assert_eq!(history.gas_used_ratio.len(), 4);
+		assert_eq!(history.reward.as_ref().map(Vec::len), Some(4));
+		// One more base fee, projected for the block following the newest.
+		assert_eq!(history.base_fee_per_gas.len(), 5);
+		assert_eq!(history.base_fee_per_gas[0], U256::from(1_007));
+		assert_eq!(history.base_fee_per_gas[3], U256::from(1_010));
+		assert_eq!(
+			history.base_fee_per_gas[4],
+			next_base_fee(U256::from(1_010), 1.0, elasticity)
+		);
+	}
+
+	#[test]
+	fn fee_history_range_is_clamped_at_genesis() {
+		let elasticity = sp_runtime::Permill::from_parts(125_000);
+		let cache = fee_history_cache(0..=10);
+
+		let (lowest, highest) = fee_history_range(2, 5);
+		assert_eq!((lowest, highest), (0, 2));
+		let history = collect_fee_history(&cache, lowest, highest, None, elasticity).unwrap();
+		assert_eq!(history.oldest_block, U256::zero());
+		assert_eq!(history.gas_used_ratio.len(), 3);
+		assert_eq!(history.base_fee_per_gas.len(), 4);
+		assert!(history.reward.is_none());
+
+		assert_eq!(fee_history_range(0, 1), (0, 0));
+		assert_eq!(fee_history_range(0, 1024), (0, 0));
+	}
+
+	#[test]
+	fn fee_history_reports_the_cached_suffix_of_the_range() {
+		let elasticity = sp_runtime::Permill::from_parts(125_000);
+		let mut cache = fee_history_cache(0..=10);
+		cache.remove(&8);
+
+		let history = collect_fee_history(&cache, 5, 10, Some(&[50.0][..]), elasticity).unwrap();
+		assert_eq!(history.oldest_block, U256::from(9));
+		assert_eq!(history.gas_used_ratio.len(), 2);
+		assert_eq!(history.reward.as_ref().map(Vec::len), Some(2));
+		assert_eq!(history.base_fee_per_gas.len(), 3);
+		assert_eq!(history.base_fee_per_gas[0], U256::from(1_009));
+
+		// Nothing is reported when the newest block itself is not cached.
+		let err = collect_fee_history(&cache, 5, 8, None, elasticity).unwrap_err();
+		assert!(err.to_string().contains("block 8 is not cached"));
+	}
+
+	#[test]
+	fn gas_used_ratio_does_not_overflow() {
+		assert_eq!(
+			gas_used_ratio(U256::from(15_000_000u64), U256::zero()),
+			0f64
+		);
+		assert_eq!(
+			gas_used_ratio(U256::from(7_500_000u64), U256::from(15_000_000u64)),
+			0.5
+		);
+		let gas_limit = U256::from(u64::MAX) * 4;
+		assert_eq!(gas_used_ratio(gas_limit, gas_limit), 1.0);
+		assert_eq!(gas_used_ratio(gas_limit * 3 / 4, gas_limit), 0.75);
+		assert_eq!(
+			gas_used_ratio(U256::MAX, U256::one()),
+			u64::MAX as f64 / 1_000_000f64
+		);
+	}
+
+	#[test]
+	fn next_base_fee_tracks_block_fullness() {
+		let elasticity = sp_runtime::Permill::from_parts(125_000);
+		let base_fee = U256::from(1_000_000_000u64);
+
+		assert_eq!(
+			next_base_fee(base_fee, 1.0, elasticity),
+			U256::from(1_125_000_000u64)
+		);
+		assert_eq!(next_base_fee(base_fee, 0.5, elasticity), base_fee);
+		assert_eq!(
+			next_base_fee(base_fee, 0.0, elasticity),
+			U256::from(875_000_000u64)
+		);
+	}
+
+	#[test]
+	fn next_base_fee_is_bounded() {
+		let elasticity = sp_runtime::Permill::from_parts(125_000);
+
+		// Halfway between the target and a full block, half the maximum change.
+		assert_eq!(
+			next_base_fee(U256::from(1_000_000u64), 0.75, elasticity),
+			U256::from(1_062_500u64)
+		);
+		// Fullness out of range changes it no more than a full or an empty block.
+		assert_eq!(
+			next_base_fee(U256::from(1_000_000u64), 2.0, elasticity),
+			U256::from(1_125_000u64)
+		);
+		assert_eq!(
+			next_base_fee(U256::from(1_000_000u64), -1.0, elasticity),
+			U256::from(875_000u64)
+		);
+		// Above the target the base fee rises by at least one.
+		assert_eq!(
+			next_base_fee(U256::from(5u64), 0.51, elasticity),
+			U256::from(6u64)
+		);
+		assert_eq!(next_base_fee(U256::zero(), 1.0, elasticity), U256::one());
+		// And does not fall below zero.
+		assert_eq!(next_base_fee(U256::zero(), 0.0, elasticity), U256::zero());
+		// Base fees beyond 64 bits are projected as well.
+		let base_fee = U256::from(u64::MAX) * 8;
+		assert_eq!(
+			next_base_fee(base_fee, 1.0, elasticity),
+			U256::from(u64::MAX) * 9
+		);
 	}
 }
@@ -16,62 +16,107 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{marker::PhantomData, sync::Arc, time};
+use std::{future::Future, marker::PhantomData, sync::Arc, time};
 
-use ethereum::BlockV2 as EthereumBlock;
+use ethereum::{BlockV2 as EthereumBlock, TransactionV2 as EthereumTransaction};
 use ethereum_types::{H256, U256};
+use futures::{stream, StreamExt};
 use jsonrpsee::core::{async_trait, RpcResult as Result};
 // Substrate
 use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
-use sp_api::ProvideRuntimeApi;
+use sc_rpc::DenyUnsafe;
+use sc_transaction_pool::{ChainApi, Pool};
+use sc_transaction_pool_api::InPoolTransaction;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_core::hashing::keccak_256;
 use sp_runtime::{
 	generic::BlockId,
-	traits::{BlakeTwo256, Block as BlockT, NumberFor, One, Saturating, UniqueSaturatedInto},
+	traits::{BlakeTwo256, Block as BlockT, NumberFor, UniqueSaturatedInto},
 };
 // Frontier
 use fc_rpc_core::{types::*, EthFilterApiServer};
 use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus};
 
-use crate::{eth::cache::EthBlockDataCacheTask, frontier_backend_client, internal_err};
+use crate::{
+	crypto::public_key_address, eth::cache::EthBlockDataCacheTask, frontier_backend_client,
+	internal_err, public_key, transaction_logs, LogsCache,
+};
+
+/// Number of blocks of an eth_getLogs range read at the same time.
+const LOGS_SCAN_CONCURRENCY: usize = 8;
+
+/// Max number of ready pool transactions executed for the logs of a pending query,
+/// or for a pending receipt.
+pub(super) const MAX_PENDING_TRANSACTIONS: usize = 256;
 
-pub struct EthFilter<B: BlockT, C, BE> {
+pub struct EthFilter<B: BlockT, C, BE, A: ChainApi> {
 	client: Arc<C>,
 	backend: Arc<fc_db::Backend<B>>,
+	graph: Arc<Pool<A>>,
 	filter_pool: FilterPool,
 	max_stored_filters: usize,
 	max_past_logs: u32,
 	block_data_cache: Arc<EthBlockDataCacheTask<B>>,
+	/// Maximum number of logs a scanned block may hold, queries reaching a block
+	/// with more fail.
+	max_block_logs: Option<u32>,
+	/// Cache of the `eth_getLogs` results of finalized block ranges.
+	logs_cache: Option<Arc<LogsCache>>,
+	/// Whether eth_getLogs only serves the logs of finalized blocks, ranges ending
+	/// past the finalized block being clamped to it.
+	finalized_logs_only: bool,
+	/// Whether the unsafe debug_getFilters is denied, as on public endpoints.
+	deny_unsafe: DenyUnsafe,
 	_marker: PhantomData<BE>,
 }
 
-impl<B: BlockT, C, BE> EthFilter<B, C, BE> {
+impl<B: BlockT, C, BE, A: ChainApi> EthFilter<B, C, BE, A> {
 	pub fn new(
 		client: Arc<C>,
 		backend: Arc<fc_db::Backend<B>>,
+		graph: Arc<Pool<A>>,
 		filter_pool: FilterPool,
 		max_stored_filters: usize,
 		max_past_logs: u32,
 		block_data_cache: Arc<EthBlockDataCacheTask<B>>,
+		max_block_logs: Option<u32>,
+		logs_cache: Option<Arc<LogsCache>>,
+		finalized_logs_only: bool,
+		deny_unsafe: DenyUnsafe,
 	) -> Self {
 		Self {
 			client,
 			backend,
+			graph,
 			filter_pool,
 			max_stored_filters,
 			max_past_logs,
 			block_data_cache,
+			max_block_logs,
+			logs_cache,
+			finalized_logs_only,
+			deny_unsafe,
 			_marker: PhantomData,
 		}
 	}
 }
 
-impl<B, C, BE> EthFilter<B, C, BE>
+impl<B, C, BE, A: ChainApi> EthFilter<B, C, BE, A>
 where
 	B: BlockT<Hash = H256> + Send + Sync + 'static,
 	C: HeaderBackend<B> + Send + Sync + 'static,
 {
+	/// Last block eth_getLogs and eth_getLogsPage serve the logs of.
+	fn logs_head(&self) -> NumberFor<B> {
+		let info = self.client.info();
+		if self.finalized_logs_only {
+			info.finalized_number
+		} else {
+			info.best_number
+		}
+	}
+
 	fn create_filter(&self, filter_type: FilterType) -> Result<U256> {
 		let block_number =
 			UniqueSaturatedInto::<u64>::unique_saturated_into(self.client.info().best_number);
@@ -95,6 +140,7 @@ where
 					last_poll: BlockNumber::Num(block_number),
 					filter_type,
 					at_block: block_number,
+					last_poll_at: time::Instant::now(),
 				},
 			);
 			Ok(key)
@@ -106,7 +152,7 @@ where
 }
 
 #[async_trait]
-impl<B, C, BE> EthFilterApiServer for EthFilter<B, C, BE>
+impl<B, C, BE, A> EthFilterApiServer for EthFilter<B, C, BE, A>
 where
 	B: BlockT<Hash = H256> + Send + Sync + 'static,
 	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
@@ -114,6 +160,7 @@ where
 	C::Api: EthereumRuntimeRPCApi<B>,
 	BE: Backend<B> + 'static,
 	BE::State: StateBackend<BlakeTwo256>,
+	A: ChainApi<Block = B> + 'static,
 {
 	fn new_filter(&self, filter: Filter) -> Result<U256> {
 		self.create_filter(FilterType::Log(filter))
@@ -169,6 +216,7 @@ where
 								last_poll: BlockNumber::Num(next),
 								filter_type: pool_item.filter_type.clone(),
 								at_block: pool_item.at_block,
+								last_poll_at: time::Instant::now(),
 							},
 						);
 
@@ -183,6 +231,7 @@ where
 								last_poll: BlockNumber::Num(block_number + 1),
 								filter_type: pool_item.filter_type.clone(),
 								at_block: pool_item.at_block,
+								last_poll_at: time::Instant::now(),
 							},
 						);
 
@@ -233,6 +282,7 @@ where
 		let client = Arc::clone(&self.client);
 		let block_data_cache = Arc::clone(&self.block_data_cache);
 		let max_past_logs = self.max_past_logs;
+		let max_block_logs = self.max_block_logs;
 
 		match path {
 			FuturePath::Error(err) => Err(err),
@@ -267,11 +317,13 @@ where
 					&block_data_cache,
 					&mut ret,
 					max_past_logs,
+					max_block_logs,
 					&filter,
 					from_number,
 					current_number,
 				)
 				.await?;
+				sort_logs(&mut ret);
 
 				Ok(FilterChanges::Logs(ret))
 			}
@@ -305,6 +357,7 @@ where
 		let client = Arc::clone(&self.client);
 		let block_data_cache = Arc::clone(&self.block_data_cache);
 		let max_past_logs = self.max_past_logs;
+		let max_block_logs = self.max_block_logs;
 
 		let filter = filter_result?;
 
@@ -331,11 +384,13 @@ where
 			&block_data_cache,
 			&mut ret,
 			max_past_logs,
+			max_block_logs,
 			&filter,
 			from_number,
 			current_number,
 		)
 		.await?;
+		sort_logs(&mut ret);
 		Ok(ret)
 	}
 
@@ -360,9 +415,25 @@ where
 		let block_data_cache = Arc::clone(&self.block_data_cache);
 		let backend = Arc::clone(&self.backend);
 		let max_past_logs = self.max_past_logs;
+		let max_block_logs = self.max_block_logs;
 
 		let mut ret: Vec<Log> = Vec::new();
-		if let Some(hash) = filter.block_hash {
+		// Logs of a single transaction are all in the block including it.
+		let block_hash = match (filter.block_hash, filter.transaction_hash) {
+			(None, Some(transaction_hash)) => {
+				match frontier_backend_client::load_transactions::<B, C>(
+					client.as_ref(),
+					backend.as_ref(),
+					transaction_hash,
+					true,
+				)? {
+					Some((hash, _)) => Some(hash),
+					None => return Ok(Vec::new()),
+				}
+			}
+			(block_hash, _) => block_hash,
+		};
+		if let Some(hash) = block_hash {
 			let id = match frontier_backend_client::load_hash::<B, C>(
 				client.as_ref(),
 				backend.as_ref(),
@@ -376,6 +447,9 @@ where
 			let substrate_hash = client
 				.expect_block_hash_from_id(&id)
 				.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+			if self.finalized_logs_only && !is_finalized::<B, C>(client.as_ref(), substrate_hash)? {
+				return Ok(Vec::new());
+			}
 
 			let schema =
 				frontier_backend_client::onchain_storage_schema::<B, C, BE>(client.as_ref(), id);
@@ -385,10 +459,25 @@ where
 				.current_transaction_statuses(schema, substrate_hash)
 				.await;
 			if let (Some(block), Some(statuses)) = (block, statuses) {
+				ensure_block_logs_within(&block, &statuses, max_block_logs)?;
 				filter_block_logs(&mut ret, &filter, block, statuses);
 			}
 		} else {
-			let best_number = client.info().best_number;
+			let best_number = self.logs_head();
+			let mut filter = filter;
+			let timestamp_of = |number: u64| {
+				block_timestamp::<B, C, BE>(client.as_ref(), &block_data_cache, number)
+			};
+			if !resolve_block_timestamps(
+				&mut filter,
+				UniqueSaturatedInto::<u64>::unique_saturated_into(best_number),
+				timestamp_of,
+			)
+			.await?
+			{
+				return Ok(Vec::new());
+			}
+
 			let mut current_number = filter
 				.to_block
 				.and_then(|v| v.to_min_block_num())
@@ -405,30 +494,230 @@ where
 				.map(|s| s.unique_saturated_into())
 				.unwrap_or(best_number);
 
-			let _ = filter_range_logs(
-				client.as_ref(),
-				&block_data_cache,
-				&mut ret,
-				max_past_logs,
-				&filter,
-				from_number,
-				current_number,
-			)
-			.await?;
+			if filter.from_block != Some(BlockNumber::Pending) {
+				// Only finalized ranges are cached, later blocks may still be reorged.
+				let cache = match &self.logs_cache {
+					Some(cache) if current_number <= client.info().finalized_number => client
+						.hash(current_number)
+						.map_err(|err| internal_err(format!("{:?}", err)))?
+						.map(|to_hash| (cache, to_hash)),
+					_ => None,
+				};
+				let (from, to) = (
+					UniqueSaturatedInto::<u64>::unique_saturated_into(from_number),
+					UniqueSaturatedInto::<u64>::unique_saturated_into(current_number),
+				);
+				match cache.and_then(|(cache, to_hash)| cache.get(&filter, from, to, to_hash)) {
+					Some(mut logs) => ret.append(&mut logs),
+					None => {
+						filter_range_logs(
+							client.as_ref(),
+							&block_data_cache,
+							&mut ret,
+							max_past_logs,
+							max_block_logs,
+							&filter,
+							from_number,
+							current_number,
+						)
+						.await?;
+						if let Some((cache, to_hash)) = cache {
+							cache.insert(&filter, from, to, to_hash, ret.clone());
+						}
+					}
+				}
+			}
+			if filter.to_block == Some(BlockNumber::Pending) && !self.finalized_logs_only {
+				pending_logs::<B, C, BE, A>(
+					client.as_ref(),
+					self.graph.as_ref(),
+					&mut ret,
+					max_past_logs,
+					&filter,
+				)?;
+			}
 		}
+		sort_logs(&mut ret);
 		Ok(ret)
 	}
+
+	async fn logs_page(
+		&self,
+		filter: Filter,
+		cursor: Option<LogsCursor>,
+		limit: Option<Index>,
+	) -> Result<LogsPage> {
+		let client = Arc::clone(&self.client);
+		let block_data_cache = Arc::clone(&self.block_data_cache);
+		let limit = limit
+			.map(|limit| limit.value())
+			.unwrap_or(self.max_past_logs as usize)
+			.clamp(1, self.max_past_logs as usize);
+
+		if filter.block_hash.is_some() || filter.transaction_hash.is_some() {
+			return Err(internal_err(
+				"eth_getLogsPage does not support blockHash or transactionHash filters, use eth_getLogs",
+			));
+		}
+
+		let best_number = self.logs_head();
+		let mut to_number = filter
+			.to_block
+			.and_then(|v| v.to_min_block_num())
+			.map(|s| s.unique_saturated_into())
+			.unwrap_or(best_number);
+
+		if to_number > best_number {
+			to_number = best_number;
+		}
+
+		let (from_number, skip) = match cursor {
+			Some(cursor) if !cursor.matches(&filter) => {
+				return Err(internal_err(
+					"logs cursor was issued for a different filter",
+				));
+			}
+			Some(cursor) => (
+				cursor.block_number.unique_saturated_into(),
+				cursor.log_index,
+			),
+			None => (
+				filter
+					.from_block
+					.and_then(|v| v.to_min_block_num())
+					.map(|s| s.unique_saturated_into())
+					.unwrap_or(best_number),
+				0,
+			),
+		};
+
+		let mut logs = Vec::new();
+		let next = filter_range_logs_page(
+			client.as_ref(),
+			&block_data_cache,
+			&mut logs,
+			limit,
+			self.max_block_logs,
+			&filter,
+			from_number,
+			to_number,
+			skip,
+		)
+		.await?;
+
+		Ok(LogsPage {
+			logs,
+			next_cursor: next
+				.map(|(number, log_index)| LogsCursor::new(&filter, number, log_index)),
+		})
+	}
+
+	fn filters(&self) -> Result<Vec<FilterInfo>> {
+		// The filters of every client are listed, with their criteria.
+		self.deny_unsafe.check_if_safe()?;
+		let pool = self
+			.filter_pool
+			.lock()
+			.map_err(|_| internal_err("Filter pool is not available."))?;
+		Ok(FilterInfo::list(&pool, time::Instant::now()))
+	}
 }
 
-async fn filter_range_logs<B: BlockT, C, BE>(
+/// Timestamp in seconds of the block `number`.
+async fn block_timestamp<B: BlockT, C, BE>(
+	client: &C,
+	block_data_cache: &EthBlockDataCacheTask<B>,
+	number: u64,
+) -> Result<u64>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+{
+	let id = BlockId::Number(number.unique_saturated_into());
+	let substrate_hash = client
+		.expect_block_hash_from_id(&id)
+		.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+	let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(client, id);
+	let block = block_data_cache
+		.current_block(schema, substrate_hash)
+		.await
+		.ok_or_else(|| internal_err(format!("Ethereum block {} is not found", number)))?;
+	Ok(block.header.timestamp / 1000)
+}
+
+/// Replace the timestamp `fromBlock` and `toBlock` of `filter` by the blocks of
+/// `0..=best` they resolve to, returning false when no block is in the range.
+async fn resolve_block_timestamps<F, Fut>(
+	filter: &mut Filter,
+	best: u64,
+	block_timestamp: F,
+) -> Result<bool>
+where
+	F: Fn(u64) -> Fut,
+	Fut: std::future::Future<Output = Result<u64>>,
+{
+	if let Some(BlockNumber::Timestamp(timestamp)) = filter.from_block {
+		match first_block_at_or_after(best, timestamp, &block_timestamp).await? {
+			Some(number) => filter.from_block = Some(BlockNumber::Num(number)),
+			None => return Ok(false),
+		}
+	}
+	if let Some(BlockNumber::Timestamp(timestamp)) = filter.to_block {
+		// The last block produced at or before the timestamp precedes the first one
+		// produced after it.
+		match first_block_at_or_after(best, timestamp.saturating_add(1), &block_timestamp).await? {
+			Some(0) => return Ok(false),
+			Some(number) => filter.to_block = Some(BlockNumber::Num(number - 1)),
+			None => filter.to_block = Some(BlockNumber::Num(best)),
+		}
+	}
+	Ok(true)
+}
+
+/// First block of `0..=best` produced at or after `timestamp`, binary searching the
+/// block timestamps, which never decrease.
+async fn first_block_at_or_after<F, Fut>(
+	best: u64,
+	timestamp: u64,
+	block_timestamp: &F,
+) -> Result<Option<u64>>
+where
+	F: Fn(u64) -> Fut,
+	Fut: std::future::Future<Output = Result<u64>>,
+{
+	let (mut lowest, mut highest) = (0u64, best.saturating_add(1));
+	while lowest < highest {
+		let mid = lowest + (highest - lowest) / 2;
+		if block_timestamp(mid).await? >= timestamp {
+			highest = mid;
+		} else {
+			lowest = mid + 1;
+		}
+	}
+	Ok(if lowest > best { None } else { Some(lowest) })
+}
+
+/// Scans `from..=to` like `filter_range_logs`, but stops once `limit` logs are
+/// collected instead of failing. Logs of the first block with a log index below
+/// `skip` were returned by a previous page and are left out.
+///
+/// Returns the block and log index to resume from, if the range was not
+/// exhausted.
+async fn filter_range_logs_page<B: BlockT, C, BE>(
 	client: &C,
 	block_data_cache: &EthBlockDataCacheTask<B>,
 	ret: &mut Vec<Log>,
-	max_past_logs: u32,
+	limit: usize,
+	max_block_logs: Option<u32>,
 	filter: &Filter,
 	from: NumberFor<B>,
 	to: NumberFor<B>,
-) -> Result<()>
+	skip: u32,
+) -> Result<Option<(u64, u32)>>
 where
 	B: BlockT<Hash = H256> + Send + Sync + 'static,
 	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
@@ -437,12 +726,134 @@ where
 	BE: Backend<B> + 'static,
 	BE::State: StateBackend<BlakeTwo256>,
 {
-	// Max request duration of 10 seconds.
+	let topics_input = if filter.topics.is_some() {
+		let filtered_params = FilteredParams::new(Some(filter.clone()));
+		Some(filtered_params.flat_topics)
+	} else {
+		None
+	};
+	let address_bloom_filter = FilteredParams::adresses_bloom_filter(&filter.address);
+	let topics_bloom_filter = FilteredParams::topics_bloom_filter(&topics_input);
+	let (address_bloom_filter, topics_bloom_filter) = (&address_bloom_filter, &topics_bloom_filter);
+
+	let block_logs = move |number: u64| async move {
+		let id = BlockId::Number(number.unique_saturated_into());
+		let substrate_hash = client
+			.expect_block_hash_from_id(&id)
+			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+
+		let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(client, id);
+
+		let block = block_data_cache.current_block(schema, substrate_hash).await;
+
+		let mut logs = Vec::new();
+		if let Some(block) = block {
+			if FilteredParams::address_in_bloom(block.header.logs_bloom, address_bloom_filter)
+				&& FilteredParams::topics_in_bloom(block.header.logs_bloom, topics_bloom_filter)
+			{
+				let statuses = block_data_cache
+					.current_transaction_statuses(schema, substrate_hash)
+					.await;
+				if let Some(statuses) = statuses {
+					ensure_block_logs_within(&block, &statuses, max_block_logs)?;
+					filter_block_logs(&mut logs, filter, block, statuses);
+					// Cursors resume at a log index, so logs must come in index order.
+					sort_logs(&mut logs);
+				}
+			}
+		}
+		Result::<Vec<Log>>::Ok(logs)
+	};
+
+	page_blocks_logs(
+		ret,
+		from.unique_saturated_into(),
+		to.unique_saturated_into(),
+		skip,
+		limit,
+		block_logs,
+	)
+	.await
+}
+
+/// Appends to `ret` the logs of the blocks `from..=to` in block order, the logs
+/// of block `from` starting at log index `skip`, until `limit` logs are paged.
+/// Returns the block and log index the next page starts at, if any is left.
+async fn page_blocks_logs<F, Fut>(
+	ret: &mut Vec<Log>,
+	from: u64,
+	to: u64,
+	mut skip: u32,
+	limit: usize,
+	block_logs: F,
+) -> Result<Option<(u64, u32)>>
+where
+	F: Fn(u64) -> Fut,
+	Fut: Future<Output = Result<Vec<Log>>>,
+{
+	// Max request duration of 10 seconds, a partial page is returned past it.
 	let max_duration = time::Duration::from_secs(10);
 	let begin_request = time::Instant::now();
 
 	let mut current_number = from;
+	while current_number <= to {
+		let logs = block_logs(current_number).await?;
+		if let Some(log_index) = append_logs_page(ret, logs, skip, limit) {
+			return Ok(Some((current_number, log_index)));
+		}
+		skip = 0;
+
+		if current_number == to {
+			break;
+		}
+		current_number = current_number.saturating_add(1);
+		if ret.len() >= limit || begin_request.elapsed() > max_duration {
+			return Ok(Some((current_number, 0)));
+		}
+	}
+	Ok(None)
+}
+
+/// Appends the logs of one block to a page, leaving out logs with a log index
+/// below `skip`. Returns the log index to resume from if the page filled up
+/// before the block's logs were exhausted.
+fn append_logs_page(
+	page: &mut Vec<Log>,
+	block_logs: Vec<Log>,
+	skip: u32,
+	limit: usize,
+) -> Option<u32> {
+	for log in block_logs {
+		let log_index = log.log_index.map(|i| i.low_u32()).unwrap_or_default();
+		if log_index < skip {
+			continue;
+		}
+		if page.len() >= limit {
+			return Some(log_index);
+		}
+		page.push(log);
+	}
+	None
+}
 
+async fn filter_range_logs<B: BlockT, C, BE>(
+	client: &C,
+	block_data_cache: &EthBlockDataCacheTask<B>,
+	ret: &mut Vec<Log>,
+	max_past_logs: u32,
+	max_block_logs: Option<u32>,
+	filter: &Filter,
+	from: NumberFor<B>,
+	to: NumberFor<B>,
+) -> Result<()>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+{
 	// Pre-calculate BloomInput for reuse.
 	let topics_input = if filter.topics.is_some() {
 		let filtered_params = FilteredParams::new(Some(filter.clone()));
@@ -452,9 +863,10 @@ where
 	};
 	let address_bloom_filter = FilteredParams::adresses_bloom_filter(&filter.address);
 	let topics_bloom_filter = FilteredParams::topics_bloom_filter(&topics_input);
+	let (address_bloom_filter, topics_bloom_filter) = (&address_bloom_filter, &topics_bloom_filter);
 
-	while current_number <= to {
-		let id = BlockId::Number(current_number);
+	let block_logs = move |number: u64| async move {
+		let id = BlockId::Number(number.unique_saturated_into());
 		let substrate_hash = client
 			.expect_block_hash_from_id(&id)
 			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
@@ -463,18 +875,58 @@ where
 
 		let block = block_data_cache.current_block(schema, substrate_hash).await;
 
+		let mut logs = Vec::new();
 		if let Some(block) = block {
-			if FilteredParams::address_in_bloom(block.header.logs_bloom, &address_bloom_filter)
-				&& FilteredParams::topics_in_bloom(block.header.logs_bloom, &topics_bloom_filter)
+			if FilteredParams::address_in_bloom(block.header.logs_bloom, address_bloom_filter)
+				&& FilteredParams::topics_in_bloom(block.header.logs_bloom, topics_bloom_filter)
 			{
 				let statuses = block_data_cache
 					.current_transaction_statuses(schema, substrate_hash)
 					.await;
 				if let Some(statuses) = statuses {
-					filter_block_logs(ret, filter, block, statuses);
+					ensure_block_logs_within(&block, &statuses, max_block_logs)?;
+					filter_block_logs(&mut logs, filter, block, statuses);
 				}
 			}
 		}
+		Result::<Vec<Log>>::Ok(logs)
+	};
+
+	scan_blocks_logs(
+		ret,
+		from.unique_saturated_into(),
+		to.unique_saturated_into(),
+		LOGS_SCAN_CONCURRENCY,
+		max_past_logs,
+		block_logs,
+	)
+	.await
+}
+
+/// Appends the logs of the blocks `from..=to` to `ret` in block order, fetching up
+/// to `concurrency` blocks at a time as the data of each is read by a worker of
+/// the block data cache.
+async fn scan_blocks_logs<F, Fut>(
+	ret: &mut Vec<Log>,
+	from: u64,
+	to: u64,
+	concurrency: usize,
+	max_past_logs: u32,
+	block_logs: F,
+) -> Result<()>
+where
+	F: Fn(u64) -> Fut,
+	Fut: Future<Output = Result<Vec<Log>>>,
+{
+	// Max request duration of 10 seconds.
+	let max_duration = time::Duration::from_secs(10);
+	let begin_request = time::Instant::now();
+
+	let mut blocks = stream::iter(from..=to)
+		.map(block_logs)
+		.buffered(concurrency.max(1));
+	while let Some(logs) = blocks.next().await {
+		ret.append(&mut logs?);
 		// Check for restrictions
 		if ret.len() as u32 > max_past_logs {
 			return Err(internal_err(format!(
@@ -488,15 +940,233 @@ where
 				max_duration.as_secs()
 			)));
 		}
-		if current_number == to {
-			break;
-		} else {
-			current_number = current_number.saturating_add(One::one());
+	}
+	Ok(())
+}
+
+/// Appends to `ret` the logs of the ready transactions of the pool, executed one
+/// after the other on top of the best block. As they are not part of a block yet,
+/// their block hash and number are unset. The query is bounded like a range scan,
+/// and by the number of transactions executed.
+fn pending_logs<B, C, BE, A>(
+	client: &C,
+	graph: &Pool<A>,
+	ret: &mut Vec<Log>,
+	max_past_logs: u32,
+	filter: &Filter,
+) -> Result<()>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + StorageProvider<B, BE>,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	BE: Backend<B> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	A: ChainApi<Block = B> + 'static,
+{
+	let best = BlockId::Hash(client.info().best_hash);
+	// The runtime api keeps the changes of each call, so every transaction runs on
+	// top of the previous ones.
+	let api = client.runtime_api();
+	match api.api_version::<dyn EthereumRuntimeRPCApi<B>>(&best) {
+		Ok(Some(api_version)) if api_version >= 4 => (),
+		_ => return Ok(()),
+	}
+
+	let xts: Vec<<B as BlockT>::Extrinsic> = graph
+		.validated_pool()
+		.ready()
+		.map(|in_pool_tx| in_pool_tx.data().clone())
+		.collect();
+	let transactions = api
+		.extrinsic_filter(&best, xts)
+		.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?;
+
+	// Max request duration of 10 seconds.
+	let max_duration = time::Duration::from_secs(10);
+	let begin_request = time::Instant::now();
+
+	let params = FilteredParams::new(Some(filter.clone()));
+	let mut log_index: u32 = 0;
+	for (index, transaction) in transactions.into_iter().enumerate() {
+		// Check for restrictions before executing the transaction
+		if index >= MAX_PENDING_TRANSACTIONS {
+			return Err(internal_err(format!(
+				"query executes more than {} pending transactions",
+				MAX_PENDING_TRANSACTIONS
+			)));
+		}
+		if ret.len() as u32 > max_past_logs {
+			return Err(internal_err(format!(
+				"query returned more than {} results",
+				max_past_logs
+			)));
+		}
+		if begin_request.elapsed() > max_duration {
+			return Err(internal_err(format!(
+				"query timeout of {} seconds exceeded",
+				max_duration.as_secs()
+			)));
+		}
+		let from = match public_key(&transaction) {
+			Ok(public) => public_key_address(&public),
+			Err(_) => continue,
+		};
+		let (
+			action,
+			input,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			access_list,
+		) = match &transaction {
+			EthereumTransaction::Legacy(t) => (
+				&t.action,
+				t.input.clone(),
+				t.value,
+				t.gas_limit,
+				t.gas_price,
+				t.gas_price,
+				Vec::new(),
+			),
+			EthereumTransaction::EIP2930(t) => (
+				&t.action,
+				t.input.clone(),
+				t.value,
+				t.gas_limit,
+				t.gas_price,
+				t.gas_price,
+				t.access_list.clone(),
+			),
+			EthereumTransaction::EIP1559(t) => (
+				&t.action,
+				t.input.clone(),
+				t.value,
+				t.gas_limit,
+				t.max_fee_per_gas,
+				t.max_priority_fee_per_gas,
+				t.access_list.clone(),
+			),
+		};
+		let access_list = Some(
+			access_list
+				.into_iter()
+				.map(|item| (item.address, item.storage_keys))
+				.collect(),
+		);
+		let logs = match action {
+			ethereum::TransactionAction::Call(to) => api
+				.call(
+					&best,
+					from,
+					*to,
+					input,
+					value,
+					gas_limit,
+					Some(max_fee_per_gas),
+					Some(max_priority_fee_per_gas),
+					None,
+					false,
+					access_list,
+				)
+				.map(|info| info.map(|info| info.logs)),
+			ethereum::TransactionAction::Create => api
+				.create(
+					&best,
+					from,
+					input,
+					value,
+					gas_limit,
+					Some(max_fee_per_gas),
+					Some(max_priority_fee_per_gas),
+					None,
+					false,
+					access_list,
+				)
+				.map(|info| info.map(|info| info.logs)),
+		}
+		.map_err(|err| internal_err(format!("runtime error: {:?}", err)))?
+		.unwrap_or_default();
+
+		let formatted = transaction_logs(
+			H256::zero(),
+			U256::zero(),
+			transaction.hash(),
+			index as u32,
+			log_index,
+			&logs,
+		);
+		log_index += logs.len() as u32;
+		for mut log in formatted {
+			if (filter.address.is_none() || params.filter_address(&log))
+				&& (filter.topics.is_none() || params.filter_topics(&log))
+			{
+				log.block_hash = None;
+				log.block_number = None;
+				ret.push(log);
+			}
 		}
 	}
+	if ret.len() as u32 > max_past_logs {
+		return Err(internal_err(format!(
+			"query returned more than {} results",
+			max_past_logs
+		)));
+	}
 	Ok(())
 }
 
+/// Whether the block `hash` is a finalized block of the canonical chain.
+fn is_finalized<B: BlockT, C>(client: &C, hash: B::Hash) -> Result<bool>
+where
+	C: HeaderBackend<B>,
+{
+	let number = match client
+		.number(hash)
+		.map_err(|err| internal_err(format!("{:?}", err)))?
+	{
+		Some(number) => number,
+		None => return Ok(false),
+	};
+	Ok(number <= client.info().finalized_number
+		&& client
+			.hash(number)
+			.map_err(|err| internal_err(format!("{:?}", err)))?
+			== Some(hash))
+}
+
+/// Fails on a block holding more than `max_block_logs` logs, before any of them
+/// is formatted.
+fn ensure_block_logs_within(
+	block: &EthereumBlock,
+	statuses: &[TransactionStatus],
+	max_block_logs: Option<u32>,
+) -> Result<()> {
+	let max_block_logs = match max_block_logs {
+		Some(max_block_logs) => max_block_logs as usize,
+		None => return Ok(()),
+	};
+	let block_logs = statuses
+		.iter()
+		.map(|status| status.logs.len())
+		.sum::<usize>();
+	if block_logs > max_block_logs {
+		return Err(internal_err(format!(
+			"block {} has {} logs, more than the limit of {}",
+			block.header.number, block_logs, max_block_logs
+		)));
+	}
+	Ok(())
+}
+
+/// Orders logs by block number then log index, whatever order blocks and
+/// transaction statuses were scanned in. Pending logs, without a block number,
+/// come last in their original order.
+fn sort_logs(logs: &mut [Log]) {
+	logs.sort_by_key(|log| (log.block_number.is_none(), log.block_number, log.log_index));
+}
+
 fn filter_block_logs<'a>(
 	ret: &'a mut Vec<Log>,
 	filter: &'a Filter,
@@ -507,53 +1177,370 @@ fn filter_block_logs<'a>(
 	let mut block_log_index: u32 = 0;
 	let block_hash = H256::from(keccak_256(&rlp::encode(&block.header)));
 	for status in transaction_statuses.iter() {
-		let logs = status.logs.clone();
-		let mut transaction_log_index: u32 = 0;
-		let transaction_hash = status.transaction_hash;
-		for ethereum_log in logs {
-			let mut log = Log {
-				address: ethereum_log.address,
-				topics: ethereum_log.topics.clone(),
-				data: Bytes(ethereum_log.data.clone()),
-				block_hash: None,
-				block_number: None,
+		let logs = transaction_logs(
+			block_hash,
+			block.header.number,
+			status.transaction_hash,
+			status.transaction_index,
+			block_log_index,
+			&status.logs,
+		);
+		// Skipped transactions keep counting, so the indexes of the kept logs stay right.
+		block_log_index += status.logs.len() as u32;
+		if matches!(filter.transaction_hash, Some(hash) if hash != status.transaction_hash) {
+			continue;
+		}
+		for log in logs {
+			let add = (filter.address.is_none() || params.filter_address(&log))
+				&& (filter.topics.is_none() || params.filter_topics(&log));
+			if add {
+				ret.push(log);
+			}
+		}
+	}
+	ret
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::empty_block;
+
+	fn synthetic_block_logs(block_number: u64, count: u32) -> Vec<Log> {
+		(0..count)
+			.map(|log_index| Log {
+				address: Default::default(),
+				topics: Vec::new(),
+				data: Bytes(Vec::new()),
+				block_hash: Some(H256::from_low_u64_be(block_number)),
+				block_number: Some(U256::from(block_number)),
 				transaction_hash: None,
 				transaction_index: None,
-				log_index: None,
+				log_index: Some(U256::from(log_index)),
 				transaction_log_index: None,
 				removed: false,
-			};
-			let mut add: bool = true;
-			match (filter.address.clone(), filter.topics.clone()) {
-				(Some(_), Some(_)) => {
-					if !params.filter_address(&log) || !params.filter_topics(&log) {
-						add = false;
-					}
-				}
-				(Some(_), _) => {
-					if !params.filter_address(&log) {
-						add = false;
-					}
-				}
-				(_, Some(_)) => {
-					if !params.filter_topics(&log) {
-						add = false;
+			})
+			.collect()
+	}
+
+	#[test]
+	fn concurrent_scans_match_the_sequential_scan() {
+		use std::{cell::Cell, task::Poll};
+
+		let in_flight = Cell::new(0usize);
+		let max_in_flight = Cell::new(0usize);
+		let scan = |concurrency: usize| {
+			let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+			let block_logs = move |number: u64| async move {
+				in_flight.set(in_flight.get() + 1);
+				max_in_flight.set(max_in_flight.get().max(in_flight.get()));
+				// Blocks take different times to read, completing out of order.
+				let mut yields = number * 7 % 5;
+				futures::future::poll_fn(|cx| {
+					if yields == 0 {
+						return Poll::Ready(());
 					}
-				}
-				_ => {}
-			}
-			if add {
-				log.block_hash = Some(block_hash);
-				log.block_number = Some(block.header.number);
-				log.transaction_hash = Some(transaction_hash);
-				log.transaction_index = Some(U256::from(status.transaction_index));
-				log.log_index = Some(U256::from(block_log_index));
-				log.transaction_log_index = Some(U256::from(transaction_log_index));
-				ret.push(log);
+					yields -= 1;
+					cx.waker().wake_by_ref();
+					Poll::Pending
+				})
+				.await;
+				in_flight.set(in_flight.get() - 1);
+				Ok(synthetic_block_logs(number, (number % 3) as u32))
+			};
+			let mut ret = Vec::new();
+			futures::executor::block_on(scan_blocks_logs(
+				&mut ret,
+				1,
+				2_000,
+				concurrency,
+				u32::MAX,
+				block_logs,
+			))
+			.unwrap();
+			ret
+		};
+
+		let sequential = scan(1);
+		assert_eq!(max_in_flight.get(), 1);
+		assert_eq!(
+			sequential,
+			(1..=2_000)
+				.flat_map(|number| synthetic_block_logs(number, (number % 3) as u32))
+				.collect::<Vec<_>>()
+		);
+
+		max_in_flight.set(0);
+		assert_eq!(scan(LOGS_SCAN_CONCURRENCY), sequential);
+		assert_eq!(max_in_flight.get(), LOGS_SCAN_CONCURRENCY);
+	}
+
+	#[test]
+	fn concurrent_scans_stop_past_the_logs_limit() {
+		let block_logs = |number: u64| async move { Ok(synthetic_block_logs(number, 2)) };
+		let mut ret = Vec::new();
+		let err = futures::executor::block_on(scan_blocks_logs(
+			&mut ret,
+			1,
+			100,
+			LOGS_SCAN_CONCURRENCY,
+			10,
+			block_logs,
+		))
+		.unwrap_err();
+		assert!(err
+			.message()
+			.contains("query returned more than 10 results"));
+		assert_eq!(ret.len(), 12);
+	}
+
+	/// Pages the logs of the in-memory `blocks`, numbered from 0.
+	fn page(
+		blocks: &[Vec<Log>],
+		from: u64,
+		skip: u32,
+		limit: usize,
+	) -> (Vec<Log>, Option<(u64, u32)>) {
+		let block_logs = |number: u64| {
+			let logs = blocks[number as usize].clone();
+			async move { Ok(logs) }
+		};
+		let mut ret = Vec::new();
+		let next = futures::executor::block_on(page_blocks_logs(
+			&mut ret,
+			from,
+			blocks.len() as u64 - 1,
+			skip,
+			limit,
+			block_logs,
+		))
+		.unwrap();
+		(ret, next)
+	}
+
+	#[test]
+	fn logs_pages_reconstruct_the_full_set() {
+		let blocks: Vec<Vec<Log>> = (0..30).map(|n| synthetic_block_logs(n, 1_000)).collect();
+		let all: Vec<Log> = blocks.iter().flatten().cloned().collect();
+		let limit = all.len() / 2 + 500;
+
+		let (first, cursor) = page(&blocks, 0, 0, limit);
+		assert_eq!(first.len(), limit);
+		let (from, skip) = cursor.expect("range is not exhausted; qed");
+		// The page filled up in the middle of a block.
+		assert_eq!((from, skip), (15, 500));
+
+		let (second, cursor) = page(&blocks, from, skip, limit);
+		assert!(cursor.is_none());
+
+		let reconstructed: Vec<Log> = first.into_iter().chain(second).collect();
+		assert_eq!(reconstructed, all);
+	}
+
+	#[test]
+	fn logs_are_ordered_by_block_then_log_index() {
+		let mut logs: Vec<Log> = [(3, 1), (1, 2), (3, 0), (1, 0), (2, 5), (1, 1)]
+			.iter()
+			.map(|&(block_number, log_index)| {
+				let mut log = synthetic_block_logs(block_number, 1).remove(0);
+				log.log_index = Some(U256::from(log_index));
+				log
+			})
+			.collect();
+		let mut pending = synthetic_block_logs(0, 1).remove(0);
+		pending.block_hash = None;
+		pending.block_number = None;
+		logs.insert(0, pending);
+
+		sort_logs(&mut logs);
+		let order: Vec<(Option<u64>, u64)> = logs
+			.iter()
+			.map(|log| {
+				(
+					log.block_number.map(|n| n.low_u64()),
+					log.log_index.unwrap().low_u64(),
+				)
+			})
+			.collect();
+		assert_eq!(
+			order,
+			vec![
+				(Some(1), 0),
+				(Some(1), 1),
+				(Some(1), 2),
+				(Some(2), 5),
+				(Some(3), 0),
+				(Some(3), 1),
+				(None, 0),
+			]
+		);
+	}
+
+	#[test]
+	fn blocks_with_too_many_logs_are_rejected() {
+		let block = empty_block(7);
+		let ethereum_log = ethereum::Log {
+			address: Default::default(),
+			topics: Vec::new(),
+			data: Vec::new(),
+		};
+		// 100 transactions of 100 logs each.
+		let statuses: Vec<TransactionStatus> = (0..100u32)
+			.map(|transaction_index| TransactionStatus {
+				transaction_index,
+				logs: vec![ethereum_log.clone(); 100],
+				..Default::default()
+			})
+			.collect();
+
+		let err = ensure_block_logs_within(&block, &statuses, Some(9_999)).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("block 7 has 10000 logs, more than the limit of 9999"));
+
+		assert!(ensure_block_logs_within(&block, &statuses, Some(10_000)).is_ok());
+		assert!(ensure_block_logs_within(&block, &statuses, None).is_ok());
+	}
+
+	#[test]
+	fn log_indexes_run_across_the_block() {
+		let block = empty_block(7);
+		let ethereum_log = ethereum::Log {
+			address: Default::default(),
+			topics: Vec::new(),
+			data: Vec::new(),
+		};
+		let statuses: Vec<TransactionStatus> = (0..2u32)
+			.map(|transaction_index| TransactionStatus {
+				transaction_hash: H256::from_low_u64_be(transaction_index as u64 + 1),
+				transaction_index,
+				logs: vec![ethereum_log.clone(), ethereum_log.clone()],
+				..Default::default()
+			})
+			.collect();
+		let indexes = |logs: &[Log]| -> Vec<(u64, u64, u64)> {
+			logs.iter()
+				.map(|log| {
+					(
+						log.transaction_index.unwrap().low_u64(),
+						log.log_index.unwrap().low_u64(),
+						log.transaction_log_index.unwrap().low_u64(),
+					)
+				})
+				.collect()
+		};
+
+		let mut filter = Filter {
+			from_block: None,
+			to_block: None,
+			block_hash: None,
+			transaction_hash: None,
+			address: None,
+			topics: None,
+		};
+		let mut ret = Vec::new();
+		filter_block_logs(&mut ret, &filter, block.clone(), statuses.clone());
+		assert_eq!(
+			indexes(&ret),
+			vec![(0, 0, 0), (0, 1, 1), (1, 2, 0), (1, 3, 1)]
+		);
+
+		// Logs of the second transaction only keep their block-level index.
+		filter.transaction_hash = Some(H256::from_low_u64_be(2));
+		let mut ret = Vec::new();
+		filter_block_logs(&mut ret, &filter, block, statuses);
+		assert_eq!(indexes(&ret), vec![(1, 2, 0), (1, 3, 1)]);
+	}
+	#[test]
+	fn block_timestamps_resolve_to_the_boundary_blocks() {
+		// Blocks 0 to 4, produced every 12 seconds.
+		let times = [1_000u64, 1_012, 1_024, 1_036, 1_048];
+		let timestamp_of = |number: u64| async move { Ok(times[number as usize]) };
+		let resolve = |from_block, to_block| {
+			let mut filter = Filter {
+				from_block: Some(from_block),
+				to_block: Some(to_block),
+				block_hash: None,
+				transaction_hash: None,
+				address: None,
+				topics: None,
+			};
+			futures::executor::block_on(resolve_block_timestamps(&mut filter, 4, timestamp_of))
+				.unwrap()
+				.then(|| (filter.from_block.unwrap(), filter.to_block.unwrap()))
+		};
+
+		// 1_030 falls between blocks 2 and 3.
+		assert_eq!(
+			resolve(BlockNumber::Timestamp(1_030), BlockNumber::Latest),
+			Some((BlockNumber::Num(3), BlockNumber::Latest))
+		);
+		assert_eq!(
+			resolve(BlockNumber::Earliest, BlockNumber::Timestamp(1_030)),
+			Some((BlockNumber::Earliest, BlockNumber::Num(2)))
+		);
+		// A block time is its own boundary on both ends.
+		assert_eq!(
+			resolve(BlockNumber::Timestamp(1_024), BlockNumber::Timestamp(1_024)),
+			Some((BlockNumber::Num(2), BlockNumber::Num(2)))
+		);
+		// Past the best block or before genesis, no block is in the range.
+		assert_eq!(
+			resolve(BlockNumber::Timestamp(2_000), BlockNumber::Latest),
+			None
+		);
+		assert_eq!(
+			resolve(BlockNumber::Earliest, BlockNumber::Timestamp(999)),
+			None
+		);
+		assert_eq!(
+			resolve(BlockNumber::Earliest, BlockNumber::Timestamp(2_000)),
+			Some((BlockNumber::Earliest, BlockNumber::Num(4)))
+		);
+	}
+
+	#[test]
+	fn only_canonical_finalized_blocks_are_finalized() {
+		use sc_block_builder::BlockBuilderProvider;
+		use sp_consensus::BlockOrigin;
+		use substrate_test_runtime_client::{
+			prelude::*, runtime::Block as TestBlock, DefaultTestClientBuilderExt, TestClientBuilder,
+		};
+
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let mut client = Arc::new(client);
+		let genesis = client.info().genesis_hash;
+		let mut import = |parent: H256, change: u8, finalize: bool| {
+			let mut builder = client
+				.new_block_at(&BlockId::Hash(parent), Default::default(), false)
+				.unwrap();
+			builder.push_storage_change(vec![change], None).unwrap();
+			let block = builder.build().unwrap().block;
+			let hash = block.header.hash();
+			if finalize {
+				futures::executor::block_on(client.import_as_final(BlockOrigin::Own, block))
+					.unwrap();
+			} else {
+				futures::executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
 			}
-			transaction_log_index += 1;
-			block_log_index += 1;
-		}
+			hash
+		};
+
+		let a1 = import(genesis, 1, true);
+		// b2 is retracted once its sibling b1 gets finalized, c1 is not finalized yet.
+		let b2 = import(a1, 2, false);
+		let b1 = import(a1, 1, true);
+		let c1 = import(b1, 1, false);
+
+		let finalized = |hash: H256| is_finalized::<TestBlock, _>(client.as_ref(), hash).unwrap();
+		assert!(finalized(genesis));
+		assert!(finalized(a1));
+		assert!(finalized(b1));
+		assert!(!finalized(b2));
+		assert!(!finalized(c1));
+		assert!(!finalized(H256::repeat_byte(0xff)));
 	}
-	ret
 }
@@ -25,7 +25,7 @@ use sp_runtime::traits::Block as BlockT;
 // Frontier
 use fc_rpc_core::types::*;
 
-use crate::eth::Eth;
+use crate::{eth::Eth, METHOD_NOT_SUPPORTED};
 
 impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A> {
 	pub fn is_mining(&self) -> Result<bool> {
@@ -37,14 +37,42 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A
 	}
 
 	pub fn work(&self) -> Result<Work> {
-		Ok(Work::default())
+		Err(proof_of_work_unsupported())
 	}
 
 	pub fn submit_hashrate(&self, _: U256, _: H256) -> Result<bool> {
-		Ok(false)
+		Err(proof_of_work_unsupported())
 	}
 
 	pub fn submit_work(&self, _: H64, _: H256, _: H256) -> Result<bool> {
-		Ok(false)
+		Err(proof_of_work_unsupported())
+	}
+}
+
+/// Blocks are not mined, so there is no work to hand out or take solutions for.
+fn proof_of_work_unsupported() -> jsonrpsee::core::Error {
+	crate::err(
+		METHOD_NOT_SUPPORTED,
+		"proof-of-work is not supported on this chain",
+		None,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn proof_of_work_is_unsupported() {
+		match proof_of_work_unsupported() {
+			jsonrpsee::core::Error::Call(jsonrpsee::types::error::CallError::Custom(err)) => {
+				assert_eq!(err.code(), METHOD_NOT_SUPPORTED);
+				assert_eq!(
+					err.message(),
+					"proof-of-work is not supported on this chain"
+				);
+			}
+			err => panic!("unexpected error {:?}", err),
+		}
 	}
 }
@@ -28,7 +28,12 @@ mod state;
 mod submit;
 mod transaction;
 
-use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	marker::PhantomData,
+	sync::Arc,
+	time::Duration,
+};
 
 use ethereum::{BlockV2 as EthereumBlock, TransactionV2 as EthereumTransaction};
 use ethereum_types::{H160, H256, H512, H64, U256, U64};
@@ -50,7 +55,11 @@ use sp_runtime::{
 use fc_rpc_core::{types::*, EthApiServer};
 use fp_rpc::{ConvertTransactionRuntimeApi, EthereumRuntimeRPCApi, TransactionStatus};
 
-use crate::{internal_err, overrides::OverrideHandle, public_key, signer::EthSigner};
+use self::block::{RichBlockCache, RICH_BLOCK_CACHE_SIZE};
+use crate::{
+	crypto::public_key_address, gas_price_oracle::GasPriceOracle, internal_err,
+	overrides::OverrideHandle, public_key, signer::AsyncEthSigner,
+};
 
 pub use self::{
 	cache::{EthBlockDataCacheTask, EthTask},
@@ -58,6 +67,75 @@ pub use self::{
 	filter::EthFilter,
 };
 
+/// Settings of the Eth API. The defaults bound eth_call like the template node does
+/// and leave the optional behaviours off.
+#[derive(Clone, Debug)]
+pub struct EthConfig {
+	/// Maximum size in bytes of the data returned by eth_call.
+	pub max_call_return_data_size: usize,
+	/// Gas ceiling of eth_call/eth_estimateGas executions, below the one allowed by
+	/// the block gas limit. Unbounded by anything else when `None`.
+	pub rpc_gas_cap: Option<u64>,
+	/// Maximum length in bytes of the revert reason embedded in error messages.
+	pub max_revert_reason_len: usize,
+	/// First block reporting the (empty) withdrawals of post-Shanghai blocks.
+	pub withdrawals_block: Option<u64>,
+	/// When set, eth_sendRawTransaction rejects transactions whose nonce is more
+	/// than this many nonces ahead of the sender's next one.
+	pub max_nonce_gap: Option<u64>,
+	/// Whether eth_call fails when sending data to an address without code, rather
+	/// than returning empty data.
+	pub require_callee_code: bool,
+	/// Address eth_coinbase returns when the block author maps to no address.
+	pub default_coinbase: Option<H160>,
+	/// Whether the eth_getPendingReceipt extension is enabled.
+	pub pending_receipts: bool,
+	/// Wall time after which the eth_estimateGas binary search gives up.
+	pub estimate_gas_timeout: Option<Duration>,
+	/// Addresses of the runtime's precompiles, which run without code at their
+	/// address. No address is taken for a precompile when empty.
+	pub precompile_addresses: BTreeSet<H160>,
+	/// Whether eth_sendTransaction and eth_sendRawTransaction reject transactions
+	/// from accounts with code, as EIP-3607 specifies.
+	pub reject_senders_with_code: bool,
+	/// Maximum number of calls of an eth_multicall request.
+	pub max_multicall_calls: usize,
+	/// EVM configuration of the runtime, pricing the intrinsic gas of calls.
+	pub evm_config: evm::Config,
+	/// Costs of the runtime's precompiles whose cost only depends on the length
+	/// of their input, which eth_estimateGas returns without executing the call.
+	pub precompile_linear_costs: BTreeMap<H160, LinearPrecompileCost>,
+}
+
+/// Gas cost of a precompile linear in the number of 32 bytes words of its input,
+/// the `BASE` and `WORD` of a `LinearCostPrecompile`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinearPrecompileCost {
+	pub base: u64,
+	pub word: u64,
+}
+
+impl Default for EthConfig {
+	fn default() -> Self {
+		Self {
+			max_call_return_data_size: 10 * 1024 * 1024,
+			rpc_gas_cap: None,
+			max_revert_reason_len: 4096,
+			withdrawals_block: None,
+			max_nonce_gap: None,
+			require_callee_code: false,
+			default_coinbase: None,
+			pending_receipts: false,
+			estimate_gas_timeout: None,
+			precompile_addresses: BTreeSet::new(),
+			reject_senders_with_code: false,
+			max_multicall_calls: 100,
+			evm_config: evm::Config::london(),
+			precompile_linear_costs: BTreeMap::new(),
+		}
+	}
+}
+
 /// Eth API implementation.
 pub struct Eth<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi, EGA = ()> {
 	pool: Arc<P>,
@@ -66,7 +144,7 @@ pub struct Eth<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi, EGA = ()> {
 	convert_transaction: Option<CT>,
 	network: Arc<NetworkService<B, H>>,
 	is_authority: bool,
-	signers: Vec<Box<dyn EthSigner>>,
+	signers: Vec<Box<dyn AsyncEthSigner>>,
 	overrides: Arc<OverrideHandle<B>>,
 	backend: Arc<fc_db::Backend<B>>,
 	block_data_cache: Arc<EthBlockDataCacheTask<B>>,
@@ -75,6 +153,12 @@ pub struct Eth<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi, EGA = ()> {
 	/// When using eth_call/eth_estimateGas, the maximum allowed gas limit will be
 	/// block.gas_limit * execute_gas_limit_multiplier
 	execute_gas_limit_multiplier: u64,
+	/// Prices suggested by eth_gasPrice and eth_maxPriorityFeePerGas.
+	gas_price_oracle: Box<dyn GasPriceOracle>,
+	/// Settings of the API.
+	config: EthConfig,
+	/// Blocks recently returned by eth_getBlockByHash and eth_getBlockByNumber.
+	rich_block_cache: RichBlockCache,
 	_marker: PhantomData<(B, BE, EGA)>,
 }
 
@@ -85,7 +169,7 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A
 		graph: Arc<Pool<A>>,
 		convert_transaction: Option<CT>,
 		network: Arc<NetworkService<B, H>>,
-		signers: Vec<Box<dyn EthSigner>>,
+		signers: Vec<Box<dyn AsyncEthSigner>>,
 		overrides: Arc<OverrideHandle<B>>,
 		backend: Arc<fc_db::Backend<B>>,
 		is_authority: bool,
@@ -93,6 +177,8 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A
 		fee_history_cache: FeeHistoryCache,
 		fee_history_cache_limit: FeeHistoryCacheLimit,
 		execute_gas_limit_multiplier: u64,
+		gas_price_oracle: Box<dyn GasPriceOracle>,
+		config: EthConfig,
 	) -> Self {
 		Self {
 			client,
@@ -108,6 +194,9 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A
 			fee_history_cache,
 			fee_history_cache_limit,
 			execute_gas_limit_multiplier,
+			gas_price_oracle,
+			config,
+			rich_block_cache: RichBlockCache::new(RICH_BLOCK_CACHE_SIZE),
 			_marker: PhantomData,
 		}
 	}
@@ -223,6 +312,18 @@ where
 		self.transaction_receipt(hash).await
 	}
 
+	async fn transaction_receipts(&self, hashes: Vec<H256>) -> Result<Vec<Option<Receipt>>> {
+		self.transaction_receipts(hashes).await
+	}
+
+	async fn block_receipts(&self, number: BlockNumber) -> Result<Option<Vec<Receipt>>> {
+		self.block_receipts(number).await
+	}
+
+	fn pending_receipt(&self, hash: H256) -> Result<Option<PendingReceipt>> {
+		self.pending_receipt(hash)
+	}
+
 	// ########################################################################
 	// State
 	// ########################################################################
@@ -247,8 +348,30 @@ where
 	// Execute
 	// ########################################################################
 
-	fn call(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<Bytes> {
-		self.call(request, number)
+	fn call(
+		&self,
+		request: CallRequest,
+		number: Option<BlockNumber>,
+		state_overrides: Option<BTreeMap<H160, CallStateOverride>>,
+	) -> Result<Bytes> {
+		self.call(request, number, state_overrides)
+	}
+
+	fn call_decoded(
+		&self,
+		request: CallRequest,
+		output_types: Vec<AbiType>,
+		number: Option<BlockNumber>,
+	) -> Result<Vec<AbiValue>> {
+		self.call_decoded(request, output_types, number)
+	}
+
+	fn multicall(
+		&self,
+		requests: Vec<CallRequest>,
+		number: Option<BlockNumber>,
+	) -> Result<Vec<CallResult>> {
+		self.multicall(requests, number)
 	}
 
 	async fn estimate_gas(
@@ -315,6 +438,10 @@ where
 	async fn send_raw_transaction(&self, bytes: Bytes) -> Result<H256> {
 		self.send_raw_transaction(bytes).await
 	}
+
+	fn decode_raw_transaction(&self, bytes: Bytes) -> Result<Transaction> {
+		self.decode_raw_transaction(bytes)
+	}
 }
 
 fn rich_block_build(
@@ -323,6 +450,7 @@ fn rich_block_build(
 	hash: Option<H256>,
 	full_transactions: bool,
 	base_fee: Option<U256>,
+	with_withdrawals: bool,
 ) -> RichBlock {
 	Rich {
 		inner: Block {
@@ -344,8 +472,15 @@ fn rich_block_build(
 				logs_bloom: block.header.logs_bloom,
 				timestamp: U256::from(block.header.timestamp / 1000),
 				difficulty: block.header.difficulty,
+				mix_hash: block.header.mix_hash,
 				nonce: Some(block.header.nonce),
 				size: Some(U256::from(rlp::encode(&block.header).len() as u32)),
+				base_fee_per_gas: base_fee,
+				withdrawals_root: if with_withdrawals {
+					Some(empty_trie_root())
+				} else {
+					None
+				},
 			},
 			total_difficulty: U256::zero(),
 			uncles: vec![],
@@ -377,12 +512,21 @@ fn rich_block_build(
 				}
 			},
 			size: Some(U256::from(rlp::encode(&block).len() as u32)),
-			base_fee_per_gas: base_fee,
+			withdrawals: if with_withdrawals {
+				Some(Vec::new())
+			} else {
+				None
+			},
 		},
 		extra_info: BTreeMap::new(),
 	}
 }
 
+/// Root of an empty Merkle Patricia trie.
+fn empty_trie_root() -> H256 {
+	H256::from(keccak_256(&rlp::NULL_RLP))
+}
+
 fn transaction_build(
 	ethereum_transaction: EthereumTransaction,
 	block: Option<EthereumBlock>,
@@ -392,14 +536,16 @@ fn transaction_build(
 	let mut transaction: Transaction = ethereum_transaction.clone().into();
 
 	if let EthereumTransaction::EIP1559(_) = ethereum_transaction {
-		if block.is_none() && status.is_none() {
-			// If transaction is not mined yet, gas price is considered just max fee per gas.
+		if block.is_none() && status.is_none() && base_fee.is_none() {
+			// If transaction is not mined yet and the pending base fee is unknown, gas price
+			// is considered just max fee per gas.
 			transaction.gas_price = transaction.max_fee_per_gas;
 		} else {
 			let base_fee = base_fee.unwrap_or_default();
 			let max_priority_fee_per_gas = transaction.max_priority_fee_per_gas.unwrap_or_default();
 			let max_fee_per_gas = transaction.max_fee_per_gas.unwrap_or_default();
-			// If transaction is already mined, gas price is the effective gas price.
+			// Gas price is the effective gas price at the base fee of the including block,
+			// or the projected one of the pending block.
 			transaction.gas_price = Some(
 				base_fee
 					.checked_add(max_priority_fee_per_gas)
@@ -430,7 +576,7 @@ fn transaction_build(
 	transaction.from = status.as_ref().map_or(
 		{
 			match pubkey {
-				Some(pk) => H160::from(H256::from(keccak_256(&pk))),
+				Some(pk) => public_key_address(&pk),
 				_ => H160::default(),
 			}
 		},
@@ -473,20 +619,33 @@ where
 	A: ChainApi<Block = B> + 'static,
 {
 	// In case of Pending, we need an overlayed state to query over.
-	let api = client.runtime_api();
-	let best = BlockId::Hash(client.info().best_hash);
 	// Get all transactions in the ready queue.
 	let xts: Vec<<B as BlockT>::Extrinsic> = graph
 		.validated_pool()
 		.ready()
 		.map(|in_pool_tx| in_pool_tx.data().clone())
 		.collect::<Vec<<B as BlockT>::Extrinsic>>();
+	pending_runtime_api_with(client, xts)
+}
+
+/// Runtime api over the best block's state with `xts` applied on top of it.
+fn pending_runtime_api_with<B: BlockT, C>(
+	client: &C,
+	xts: Vec<<B as BlockT>::Extrinsic>,
+) -> Result<sp_api::ApiRef<'_, C::Api>>
+where
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: BlockBuilderApi<B>,
+{
+	let api = client.runtime_api();
+	let best = BlockId::Hash(client.info().best_hash);
 	// Manually initialize the overlay.
 	if let Ok(Some(header)) = client.header(best) {
 		let parent_hash = BlockId::Hash(*header.parent_hash());
 		api.initialize_block(&parent_hash, &header)
 			.map_err(|e| internal_err(format!("Runtime api access error: {:?}", e)))?;
-		// Apply the ready queue to the best block's state.
+		// Apply the transactions to the best block's state.
 		for xt in xts {
 			let _ = api.apply_extrinsic(&best, xt);
 		}
@@ -498,3 +657,103 @@ where
 		)))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::empty_block;
+
+	#[test]
+	fn withdrawals_are_empty() {
+		let block = empty_block(7);
+		let empty_root = H256::from_slice(
+			&hex::decode("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421")
+				.unwrap(),
+		);
+
+		let rich_block = rich_block_build(block.clone(), Vec::new(), None, false, None, true);
+		assert_eq!(rich_block.inner.withdrawals, Some(Vec::new()));
+		assert_eq!(rich_block.inner.header.withdrawals_root, Some(empty_root));
+
+		let rich_block = rich_block_build(block, Vec::new(), None, false, None, false);
+		assert_eq!(rich_block.inner.withdrawals, None);
+		assert_eq!(rich_block.inner.header.withdrawals_root, None);
+	}
+
+	#[test]
+	fn proof_of_work_fields_are_constant() {
+		let rich_block = rich_block_build(empty_block(7), Vec::new(), None, false, None, false);
+		let header = rich_block.inner.header;
+
+		// Keccak of the RLP encoded empty uncle list.
+		let empty_uncles_hash = H256::from_slice(
+			&hex::decode("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347")
+				.unwrap(),
+		);
+		assert_eq!(header.uncles_hash, empty_uncles_hash);
+		assert_eq!(header.mix_hash, H256::zero());
+		assert_eq!(header.nonce, Some(H64::zero()));
+		assert_eq!(header.receipts_root, H256::zero());
+	}
+
+	#[test]
+	fn pending_dynamic_fee_transaction_uses_projected_base_fee() {
+		let gwei = |amount: u64| U256::from(amount) * U256::from(1_000_000_000u64);
+		let transaction = EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
+			chain_id: 42,
+			nonce: U256::zero(),
+			max_priority_fee_per_gas: gwei(2),
+			max_fee_per_gas: gwei(100),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x11)),
+			value: U256::zero(),
+			input: Vec::new(),
+			access_list: Vec::new(),
+			odd_y_parity: false,
+			r: H256::repeat_byte(0x01),
+			s: H256::repeat_byte(0x01),
+		});
+		let gas_price =
+			|base_fee| transaction_build(transaction.clone(), None, None, base_fee).gas_price;
+
+		assert_eq!(gas_price(Some(gwei(10))), Some(gwei(12)));
+		assert_eq!(gas_price(Some(gwei(20))), Some(gwei(22)));
+		// Capped by the max fee.
+		assert_eq!(gas_price(Some(gwei(99))), Some(gwei(100)));
+		// Unknown pending base fee.
+		assert_eq!(gas_price(None), Some(gwei(100)));
+	}
+
+	#[test]
+	fn timestamp_is_in_seconds() {
+		use evm::{backend::MemoryBackend, Config, ExitReason, ExitSucceed};
+
+		use crate::overlay::{OverlayBackend, OverlayEnvironment};
+
+		let mut block = empty_block(7);
+		// The runtime stores milliseconds.
+		block.header.timestamp = 1_700_000_123_456;
+		let seconds = U256::from(1_700_000_123u64);
+
+		let rich_block = rich_block_build(block.clone(), Vec::new(), None, false, None, false);
+		assert_eq!(rich_block.inner.header.timestamp, seconds);
+
+		// TIMESTAMP PUSH1 0x00 MSTORE PUSH1 0x20 PUSH1 0x00 RETURN
+		let contract = H160::repeat_byte(0xc0);
+		let vicinity = crate::test_utils::vicinity();
+		let mut overlay = OverlayBackend::new(MemoryBackend::new(&vicinity, BTreeMap::new()));
+		overlay.set_code(
+			contract,
+			vec![0x42, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3],
+		);
+		overlay.set_environment(OverlayEnvironment::default().with_header(&block.header));
+
+		let request = CallRequest {
+			to: Some(contract),
+			..Default::default()
+		};
+		let (exit_reason, data, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(U256::from_big_endian(&data), seconds);
+	}
+}
@@ -26,7 +26,7 @@ use sc_transaction_pool::ChainApi;
 use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder as BlockBuilderApi;
-use sp_blockchain::HeaderBackend;
+use sp_blockchain::{BlockStatus, HeaderBackend};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{BlakeTwo256, Block as BlockT},
@@ -36,7 +36,7 @@ use fc_rpc_core::types::*;
 use fp_rpc::EthereumRuntimeRPCApi;
 
 use crate::{
-	eth::{pending_runtime_api, Eth},
+	eth::{execute::JSON_RPC_ERROR_DEFAULT, pending_runtime_api, Eth},
 	frontier_backend_client, internal_err,
 };
 
@@ -51,6 +51,32 @@ where
 	P: TransactionPool<Block = B> + Send + Sync + 'static,
 	A: ChainApi<Block = B> + 'static,
 {
+	/// Block a state query at `number` reads, other than the pending one.
+	///
+	/// Lookups of an unknown block, such as eth_getBlockByHash, return `null`, but state
+	/// queries at an unknown block fail: reading its missing state as empty would answer
+	/// with balances and code the chain does not have.
+	fn state_block_id(&self, number: BlockNumber) -> Result<BlockId<B>> {
+		let id = frontier_backend_client::native_block_id::<B, C>(
+			self.client.as_ref(),
+			self.backend.as_ref(),
+			Some(number),
+		)?;
+		// Resolved to a hash once, so that a reorg cannot have the reads made at a
+		// number span two blocks.
+		let hash = id.and_then(|id| self.client.block_hash_from_id(&id).ok().flatten());
+		match hash.map(BlockId::Hash) {
+			Some(id) if matches!(self.client.status(id), Ok(BlockStatus::InChain)) => {
+				frontier_backend_client::ensure_state_available::<B, C, BE>(
+					self.client.as_ref(),
+					id,
+				)?;
+				Ok(id)
+			}
+			_ => Err(crate::err(JSON_RPC_ERROR_DEFAULT, "header not found", None)),
+		}
+	}
+
 	pub fn balance(&self, address: H160, number: Option<BlockNumber>) -> Result<U256> {
 		let number = number.unwrap_or(BlockNumber::Latest);
 		if number == BlockNumber::Pending {
@@ -59,19 +85,14 @@ where
 				.account_basic(&BlockId::Hash(self.client.info().best_hash), address)
 				.map_err(|err| internal_err(format!("fetch runtime chain id failed: {:?}", err)))?
 				.balance)
-		} else if let Ok(Some(id)) = frontier_backend_client::native_block_id::<B, C>(
-			self.client.as_ref(),
-			self.backend.as_ref(),
-			Some(number),
-		) {
+		} else {
+			let id = self.state_block_id(number)?;
 			Ok(self
 				.client
 				.runtime_api()
 				.account_basic(&id, address)
 				.map_err(|err| internal_err(format!("fetch runtime chain id failed: {:?}", err)))?
 				.balance)
-		} else {
-			Ok(U256::zero())
 		}
 	}
 
@@ -87,11 +108,8 @@ where
 			Ok(api
 				.storage_at(&BlockId::Hash(self.client.info().best_hash), address, index)
 				.unwrap_or_default())
-		} else if let Ok(Some(id)) = frontier_backend_client::native_block_id::<B, C>(
-			self.client.as_ref(),
-			self.backend.as_ref(),
-			Some(number),
-		) {
+		} else {
+			let id = self.state_block_id(number)?;
 			let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
 				self.client.as_ref(),
 				id,
@@ -103,8 +121,6 @@ where
 				.unwrap_or(&self.overrides.fallback)
 				.storage_at(&id, address, index)
 				.unwrap_or_default())
-		} else {
-			Ok(H256::default())
 		}
 	}
 
@@ -135,14 +151,7 @@ where
 			return Ok(current_nonce);
 		}
 
-		let id = match frontier_backend_client::native_block_id::<B, C>(
-			self.client.as_ref(),
-			self.backend.as_ref(),
-			number,
-		)? {
-			Some(id) => id,
-			None => return Ok(U256::zero()),
-		};
+		let id = self.state_block_id(number.unwrap_or(BlockNumber::Latest))?;
 
 		Ok(self
 			.client
@@ -160,11 +169,8 @@ where
 				.account_code_at(&BlockId::Hash(self.client.info().best_hash), address)
 				.unwrap_or_default()
 				.into())
-		} else if let Ok(Some(id)) = frontier_backend_client::native_block_id::<B, C>(
-			self.client.as_ref(),
-			self.backend.as_ref(),
-			Some(number),
-		) {
+		} else {
+			let id = self.state_block_id(number)?;
 			let schema = frontier_backend_client::onchain_storage_schema::<B, C, BE>(
 				self.client.as_ref(),
 				id,
@@ -178,8 +184,6 @@ where
 				.account_code_at(&id, address)
 				.unwrap_or_default()
 				.into())
-		} else {
-			Ok(Bytes(vec![]))
 		}
 	}
 }
@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use ethereum_types::H256;
+use ethereum_types::{H160, H256, U256};
 use futures::future::TryFutureExt;
 use jsonrpsee::core::RpcResult as Result;
 // Substrate
@@ -37,10 +37,15 @@ use fc_rpc_core::types::*;
 use fp_rpc::{ConvertTransaction, ConvertTransactionRuntimeApi, EthereumRuntimeRPCApi};
 
 use crate::{
-	eth::{format, Eth},
-	internal_err,
+	crypto::public_key_address,
+	eth::{format, transaction_build, Eth, EthConfig},
+	internal_err, public_key,
+	signer::sign_with,
 };
 
+/// Percentage added to the estimated gas of transactions sent without a gas limit.
+const GAS_ESTIMATE_BUFFER_PERCENT: u64 = 10;
+
 impl<B, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A>
 where
 	B: BlockT<Hash = H256> + Send + Sync + 'static,
@@ -69,6 +74,10 @@ where
 			}
 		};
 
+		if self.config.reject_senders_with_code {
+			check_sender_code(from, &self.code_at(from, None)?)?;
+		}
+
 		let nonce = match request.nonce {
 			Some(nonce) => nonce,
 			None => match self.transaction_count(from, None) {
@@ -93,22 +102,29 @@ where
 					.client
 					.runtime_api()
 					.current_block(&BlockId::Hash(hash));
-				if let Ok(Some(block)) = block {
+				let block_gas_limit = if let Ok(Some(block)) = block {
 					block.header.gas_limit
 				} else {
 					return Err(internal_err("block unavailable, cannot query gas limit"));
-				}
+				};
+				// Estimation fails on reverts, sparing the submission of a doomed transaction.
+				let mut call_request = CallRequest::from(request.clone());
+				call_request.from = Some(from);
+				let estimate = self.estimate_gas(call_request, None).await?;
+				buffered_gas_limit(estimate, block_gas_limit)
 			}
 		};
 		let max_fee_per_gas = request.max_fee_per_gas;
+		let max_priority_fee_per_gas = request.max_priority_fee_per_gas;
 		let message: Option<TransactionMessage> = request.into();
+		// Fees left out are filled from the suggestions, a zero fee would never be included.
 		let message = match message {
 			Some(TransactionMessage::Legacy(mut m)) => {
 				m.nonce = nonce;
 				m.chain_id = Some(chain_id);
 				m.gas_limit = gas_limit;
 				if gas_price.is_none() {
-					m.gas_price = self.gas_price().unwrap_or_default();
+					m.gas_price = self.gas_price()?;
 				}
 				TransactionMessage::Legacy(m)
 			}
@@ -117,7 +133,7 @@ where
 				m.chain_id = chain_id;
 				m.gas_limit = gas_limit;
 				if gas_price.is_none() {
-					m.gas_price = self.gas_price().unwrap_or_default();
+					m.gas_price = self.gas_price()?;
 				}
 				TransactionMessage::EIP2930(m)
 			}
@@ -125,30 +141,28 @@ where
 				m.nonce = nonce;
 				m.chain_id = chain_id;
 				m.gas_limit = gas_limit;
-				if max_fee_per_gas.is_none() {
-					m.max_fee_per_gas = self.gas_price().unwrap_or_default();
-				}
+				let suggested_priority_fee = match max_priority_fee_per_gas {
+					Some(_) => U256::zero(),
+					None => self.max_priority_fee_per_gas()?,
+				};
+				let suggested_gas_price = match max_fee_per_gas {
+					Some(_) => U256::zero(),
+					None => self.gas_price()?,
+				};
+				let (max_fee, max_priority_fee) = dynamic_fees(
+					max_fee_per_gas,
+					max_priority_fee_per_gas,
+					suggested_gas_price,
+					suggested_priority_fee,
+				);
+				m.max_fee_per_gas = max_fee;
+				m.max_priority_fee_per_gas = max_priority_fee;
 				TransactionMessage::EIP1559(m)
 			}
 			_ => return Err(internal_err("invalid transaction parameters")),
 		};
 
-		let mut transaction = None;
-
-		for signer in &self.signers {
-			if signer.accounts().contains(&from) {
-				match signer.sign(message, &from) {
-					Ok(t) => transaction = Some(t),
-					Err(e) => return Err(e),
-				}
-				break;
-			}
-		}
-
-		let transaction = match transaction {
-			Some(transaction) => transaction,
-			None => return Err(internal_err("no signer available")),
-		};
+		let transaction = sign_with(&self.signers, message, &from).await?;
 		let transaction_hash = transaction.hash();
 
 		let block_hash = BlockId::hash(self.client.info().best_hash);
@@ -210,32 +224,18 @@ where
 	}
 
 	pub async fn send_raw_transaction(&self, bytes: Bytes) -> Result<H256> {
-		let slice = &bytes.0[..];
-		if slice.is_empty() {
-			return Err(internal_err("transaction data is empty"));
-		}
-		let first = slice.get(0).unwrap();
-		let transaction = if first > &0x7f {
-			// Legacy transaction. Decode and wrap in envelope.
-			match rlp::decode::<ethereum::TransactionV0>(slice) {
-				Ok(transaction) => ethereum::TransactionV2::Legacy(transaction),
-				Err(_) => return Err(internal_err("decode transaction failed")),
-			}
-		} else {
-			// Typed Transaction.
-			// `ethereum` crate decode implementation for `TransactionV2` expects a valid rlp input,
-			// and EIP-1559 breaks that assumption by prepending a version byte.
-			// We re-encode the payload input to get a valid rlp, and the decode implementation will strip
-			// them to check the transaction version byte.
-			let extend = rlp::encode(&slice);
-			match rlp::decode::<ethereum::TransactionV2>(&extend[..]) {
-				Ok(transaction) => transaction,
-				Err(_) => return Err(internal_err("decode transaction failed")),
-			}
-		};
-
+		let transaction = decode_transaction(&bytes.0[..])?;
+		// Known before submission, the hash of the raw bytes including the type byte of
+		// typed transactions, which is the hash of the transaction once mined.
 		let transaction_hash = transaction.hash();
 
+		check_raw_sender(
+			&self.config,
+			&transaction,
+			|sender| Ok(self.code_at(sender, None)?.0),
+			|sender| self.transaction_count(sender, Some(BlockNumber::Pending)),
+		)?;
+
 		let block_hash = BlockId::hash(self.client.info().best_hash);
 		let api_version = match self
 			.client
@@ -295,4 +295,419 @@ where
 			.map_err(|err| internal_err(format::Geth::pool_error(err)))
 			.await
 	}
+
+	pub fn decode_raw_transaction(&self, bytes: Bytes) -> Result<Transaction> {
+		let transaction = decode_transaction(&bytes.0[..])?;
+		if public_key(&transaction).is_err() {
+			return Err(internal_err("invalid transaction signature"));
+		}
+		// Unmined, the sender is recovered from the signature.
+		Ok(transaction_build(transaction, None, None, None))
+	}
+}
+
+/// Gas limit of a transaction sent without one, leaving the estimate some room for
+/// state changes between estimation and inclusion.
+fn buffered_gas_limit(estimate: U256, block_gas_limit: U256) -> U256 {
+	let buffer = estimate.saturating_mul(U256::from(GAS_ESTIMATE_BUFFER_PERCENT)) / 100;
+	std::cmp::min(estimate.saturating_add(buffer), block_gas_limit)
+}
+
+/// Rejects a nonce leaving more than `max_nonce_gap` nonces unused before it, which
+/// the pool would queue until the gap is filled.
+fn check_nonce_gap(nonce: U256, next_nonce: U256, max_nonce_gap: u64) -> Result<()> {
+	if nonce > next_nonce.saturating_add(U256::from(max_nonce_gap)) {
+		return Err(internal_err(format!(
+			"nonce too high: next nonce {}, transaction nonce {}",
+			next_nonce, nonce
+		)));
+	}
+	Ok(())
+}
+
+/// Checks of `send_raw_transaction` on the sender of `transaction`, with the code and
+/// the next nonce of an account read through `code_at` and `next_nonce`, per `config`.
+fn check_raw_sender(
+	config: &EthConfig,
+	transaction: &ethereum::TransactionV2,
+	code_at: impl FnOnce(H160) -> Result<Vec<u8>>,
+	next_nonce: impl FnOnce(H160) -> Result<U256>,
+) -> Result<()> {
+	if config.max_nonce_gap.is_none() && !config.reject_senders_with_code {
+		return Ok(());
+	}
+	let sender = match public_key(transaction) {
+		Ok(public_key) => public_key_address(&public_key),
+		Err(_) => return Err(internal_err("invalid transaction signature")),
+	};
+	if config.reject_senders_with_code {
+		check_sender_code(sender, &code_at(sender)?)?;
+	}
+	if let Some(max_nonce_gap) = config.max_nonce_gap {
+		check_nonce_gap(
+			transaction_nonce(transaction),
+			next_nonce(sender)?,
+			max_nonce_gap,
+		)?;
+	}
+	Ok(())
+}
+
+/// Rejects a transaction from a `sender` with `code`, which no key should control
+/// per EIP-3607.
+fn check_sender_code(sender: H160, code: &[u8]) -> Result<()> {
+	if !code.is_empty() {
+		return Err(internal_err(format!(
+			"sender not an eoa: address {:?} has code",
+			sender
+		)));
+	}
+	Ok(())
+}
+
+fn transaction_nonce(transaction: &ethereum::TransactionV2) -> U256 {
+	match transaction {
+		ethereum::TransactionV2::Legacy(t) => t.nonce,
+		ethereum::TransactionV2::EIP2930(t) => t.nonce,
+		ethereum::TransactionV2::EIP1559(t) => t.nonce,
+	}
+}
+
+/// Decodes a signed transaction, legacy or EIP-2718 typed `type || rlp(payload)`.
+///
+/// Shared by the submission and decoding endpoints so that they accept the same
+/// transactions, of type 1 (EIP-2930) or 2 (EIP-1559) for typed ones.
+fn decode_transaction(slice: &[u8]) -> Result<ethereum::TransactionV2> {
+	if slice.is_empty() {
+		return Err(internal_err("transaction data is empty"));
+	}
+	let first = slice.get(0).unwrap();
+	if first > &0x7f {
+		// Legacy transaction. Decode and wrap in envelope.
+		match rlp::decode::<ethereum::TransactionV0>(slice) {
+			Ok(transaction) => Ok(ethereum::TransactionV2::Legacy(transaction)),
+			Err(_) => Err(internal_err("decode transaction failed")),
+		}
+	} else if first != &0x01 && first != &0x02 {
+		Err(internal_err(format!(
+			"transaction type {:#04x} is not supported",
+			first
+		)))
+	} else {
+		// Typed Transaction.
+		// `ethereum` crate decode implementation for `TransactionV2` expects a valid rlp input,
+		// and EIP-1559 breaks that assumption by prepending a version byte.
+		// We re-encode the payload input to get a valid rlp, and the decode implementation will strip
+		// them to check the transaction version byte.
+		let extend = rlp::encode(&slice);
+		match rlp::decode::<ethereum::TransactionV2>(&extend[..]) {
+			Ok(transaction) => Ok(transaction),
+			Err(_) => Err(internal_err("decode transaction failed")),
+		}
+	}
+}
+
+/// Fees of a dynamic fee transaction, filling those left out of the request from the
+/// suggested gas price and priority fee.
+fn dynamic_fees(
+	max_fee_per_gas: Option<U256>,
+	max_priority_fee_per_gas: Option<U256>,
+	suggested_gas_price: U256,
+	suggested_priority_fee: U256,
+) -> (U256, U256) {
+	let max_fee = max_fee_per_gas
+		.unwrap_or_else(|| suggested_gas_price.saturating_add(suggested_priority_fee));
+	let max_priority_fee = match max_priority_fee_per_gas {
+		Some(max_priority_fee) => max_priority_fee,
+		// A suggestion never exceeds the max fee given.
+		None => suggested_priority_fee.min(max_fee),
+	};
+	(max_fee, max_priority_fee)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{EthDevSigner, EthSigner};
+
+	#[test]
+	fn gas_estimate_is_buffered() {
+		let block_gas_limit = U256::from(15_000_000u64);
+		assert_eq!(
+			buffered_gas_limit(U256::from(50_000u64), block_gas_limit),
+			U256::from(55_000u64)
+		);
+		// The buffer never exceeds the block gas limit.
+		assert_eq!(
+			buffered_gas_limit(U256::from(14_000_000u64), block_gas_limit),
+			block_gas_limit
+		);
+	}
+
+	#[test]
+	fn dynamic_fees_default_to_suggestions() {
+		let gas_price = U256::from(1_000u64);
+		let priority_fee = U256::from(10u64);
+
+		// Nothing given.
+		assert_eq!(
+			dynamic_fees(None, None, gas_price, priority_fee),
+			(U256::from(1_010u64), priority_fee)
+		);
+		// Only the max fee given, the suggested priority fee is capped by it.
+		assert_eq!(
+			dynamic_fees(Some(U256::from(5u64)), None, gas_price, priority_fee),
+			(U256::from(5u64), U256::from(5u64))
+		);
+		// Both given.
+		assert_eq!(
+			dynamic_fees(
+				Some(U256::from(2_000u64)),
+				Some(U256::from(3u64)),
+				gas_price,
+				priority_fee
+			),
+			(U256::from(2_000u64), U256::from(3u64))
+		);
+	}
+
+	#[test]
+	fn nonces_beyond_the_gap_are_rejected() {
+		let next_nonce = U256::from(5u64);
+
+		// Two nonces ahead, leaving 5 and 6 unused.
+		let err = check_nonce_gap(U256::from(7u64), next_nonce, 1).unwrap_err();
+		assert!(err.to_string().contains("nonce too high"));
+
+		// Within the allowed gap.
+		assert!(check_nonce_gap(U256::from(6u64), next_nonce, 1).is_ok());
+		assert!(check_nonce_gap(U256::from(7u64), next_nonce, 2).is_ok());
+		// Current and replaced nonces are left to the pool.
+		assert!(check_nonce_gap(next_nonce, next_nonce, 0).is_ok());
+		assert!(check_nonce_gap(U256::from(3u64), next_nonce, 0).is_ok());
+	}
+
+	#[test]
+	fn decodes_and_recovers_the_sender() {
+		// The EIP-155 example transaction, signed with the 0x4646..46 key.
+		let raw = hex::decode(
+			"f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+			 8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d899\
+			 7f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+		)
+		.unwrap();
+		let transaction = decode_transaction(&raw).unwrap();
+		let sender =
+			H160::from_slice(&hex::decode("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap());
+
+		let decoded = transaction_build(transaction, None, None, None);
+		assert_eq!(decoded.from, sender);
+		assert_eq!(decoded.nonce, U256::from(9u64));
+		assert!(decoded.block_hash.is_none());
+
+		assert!(decode_transaction(&[]).is_err());
+		assert!(decode_transaction(&raw[..raw.len() - 1]).is_err());
+	}
+
+	#[test]
+	fn decodes_each_transaction_type() {
+		let action = || ethereum::TransactionAction::Call(H160::repeat_byte(0x11));
+		let typed = |transaction_type: u8, payload: &[u8]| {
+			let mut raw = vec![transaction_type];
+			raw.extend_from_slice(payload);
+			raw
+		};
+
+		let legacy = rlp::encode(&ethereum::LegacyTransaction {
+			nonce: U256::from(1u64),
+			gas_price: U256::from(1u64),
+			gas_limit: U256::from(21_000u64),
+			action: action(),
+			value: U256::zero(),
+			input: Vec::new(),
+			signature: ethereum::TransactionSignature::new(
+				37,
+				H256::repeat_byte(0x01),
+				H256::repeat_byte(0x01),
+			)
+			.unwrap(),
+		});
+		assert!(matches!(
+			decode_transaction(&legacy),
+			Ok(ethereum::TransactionV2::Legacy(t)) if t.nonce == U256::from(1u64)
+		));
+
+		let eip2930 = typed(
+			0x01,
+			&rlp::encode(&ethereum::EIP2930Transaction {
+				chain_id: 42,
+				nonce: U256::from(2u64),
+				gas_price: U256::from(1u64),
+				gas_limit: U256::from(21_000u64),
+				action: action(),
+				value: U256::zero(),
+				input: Vec::new(),
+				access_list: Vec::new(),
+				odd_y_parity: false,
+				r: H256::repeat_byte(0x01),
+				s: H256::repeat_byte(0x01),
+			}),
+		);
+		assert!(matches!(
+			decode_transaction(&eip2930),
+			Ok(ethereum::TransactionV2::EIP2930(t)) if t.nonce == U256::from(2u64)
+		));
+
+		let eip1559_payload = rlp::encode(&ethereum::EIP1559Transaction {
+			chain_id: 42,
+			nonce: U256::from(3u64),
+			max_priority_fee_per_gas: U256::from(1u64),
+			max_fee_per_gas: U256::from(2u64),
+			gas_limit: U256::from(21_000u64),
+			action: action(),
+			value: U256::zero(),
+			input: Vec::new(),
+			access_list: Vec::new(),
+			odd_y_parity: false,
+			r: H256::repeat_byte(0x01),
+			s: H256::repeat_byte(0x01),
+		});
+		assert!(matches!(
+			decode_transaction(&typed(0x02, &eip1559_payload)),
+			Ok(ethereum::TransactionV2::EIP1559(t)) if t.nonce == U256::from(3u64)
+		));
+
+		let err = decode_transaction(&typed(0x03, &eip1559_payload)).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("transaction type 0x03 is not supported"));
+		// A payload of another type than the one announced.
+		assert!(decode_transaction(&typed(0x01, &eip1559_payload)).is_err());
+	}
+
+	#[test]
+	fn transaction_hash_is_the_hash_of_the_raw_bytes() {
+		// The EIP-155 example transaction.
+		let legacy = hex::decode(
+			"f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+			 8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d899\
+			 7f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+		)
+		.unwrap();
+		assert_eq!(
+			decode_transaction(&legacy).unwrap().hash(),
+			crate::crypto::keccak256(&legacy)
+		);
+
+		// A typed transaction hashes the type byte along with its payload.
+		let payload = rlp::encode(&ethereum::EIP1559Transaction {
+			chain_id: 42,
+			nonce: U256::zero(),
+			max_priority_fee_per_gas: U256::from(1u64),
+			max_fee_per_gas: U256::from(2u64),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x11)),
+			value: U256::zero(),
+			input: Vec::new(),
+			access_list: Vec::new(),
+			odd_y_parity: false,
+			r: H256::repeat_byte(0x01),
+			s: H256::repeat_byte(0x01),
+		});
+		let mut typed = vec![0x02];
+		typed.extend_from_slice(&payload);
+		let hash = decode_transaction(&typed).unwrap().hash();
+		assert_eq!(hash, crate::crypto::keccak256(&typed));
+		assert_ne!(hash, crate::crypto::keccak256(&payload));
+	}
+
+	#[test]
+	fn senders_with_code_are_rejected() {
+		let signer = EthDevSigner::new();
+		let account = signer.accounts()[0];
+		let message = TransactionMessage::Legacy(ethereum::LegacyTransactionMessage {
+			nonce: U256::zero(),
+			gas_price: U256::one(),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x11)),
+			value: U256::zero(),
+			input: Vec::new(),
+			chain_id: Some(42),
+		});
+		let transaction = signer.sign(message, &account).unwrap();
+		let sender = public_key_address(&public_key(&transaction).unwrap());
+		assert_eq!(sender, account);
+
+		// The sender being a contract, such as one deployed at the address of the key.
+		let err = check_sender_code(sender, &[0x60, 0x00]).unwrap_err();
+		assert!(err.to_string().contains(&format!(
+			"sender not an eoa: address {:?} has code",
+			account
+		)));
+		assert!(check_sender_code(sender, &[]).is_ok());
+	}
+
+	#[test]
+	fn raw_transactions_from_senders_with_code_are_rejected() {
+		let signer = EthDevSigner::new();
+		let account = signer.accounts()[0];
+		let message = TransactionMessage::Legacy(ethereum::LegacyTransactionMessage {
+			nonce: U256::from(3u64),
+			gas_price: U256::one(),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x11)),
+			value: U256::zero(),
+			input: Vec::new(),
+			chain_id: Some(42),
+		});
+		let raw = match signer.sign(message, &account).unwrap() {
+			ethereum::TransactionV2::Legacy(t) => rlp::encode(&t).to_vec(),
+			_ => panic!("legacy message signs a legacy transaction"),
+		};
+		// Decoded as `send_raw_transaction` does.
+		let transaction = decode_transaction(&raw).unwrap();
+		let config = EthConfig {
+			reject_senders_with_code: true,
+			..Default::default()
+		};
+		let no_nonce = |_: H160| -> Result<U256> { panic!("no nonce gap is configured") };
+
+		let err = check_raw_sender(
+			&config,
+			&transaction,
+			|sender| {
+				assert_eq!(sender, account);
+				Ok(vec![0x60, 0x00])
+			},
+			no_nonce,
+		)
+		.unwrap_err();
+		assert!(err.to_string().contains(&format!(
+			"sender not an eoa: address {:?} has code",
+			account
+		)));
+		assert!(check_raw_sender(&config, &transaction, |_| Ok(Vec::new()), no_nonce).is_ok());
+
+		// Left to the runtime unless enabled, the code of the sender not being read.
+		assert!(check_raw_sender(
+			&EthConfig::default(),
+			&transaction,
+			|_| panic!("senders with code are not rejected"),
+			no_nonce,
+		)
+		.is_ok());
+
+		// The nonce gap is checked against the next nonce of the recovered sender too.
+		let config = EthConfig {
+			max_nonce_gap: Some(1),
+			..config
+		};
+		let next_nonce = |sender: H160| -> Result<U256> {
+			assert_eq!(sender, account);
+			Ok(U256::one())
+		};
+		let err =
+			check_raw_sender(&config, &transaction, |_| Ok(Vec::new()), next_nonce).unwrap_err();
+		assert!(err.to_string().contains("nonce too high"));
+	}
 }
@@ -16,9 +16,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
-use ethereum::TransactionV2 as EthereumTransaction;
+use ethereum::{BlockV2 as EthereumBlock, TransactionV2 as EthereumTransaction};
 use ethereum_types::{H256, U256, U64};
 use jsonrpsee::core::RpcResult as Result;
 // Substrate
@@ -35,13 +35,16 @@ use sp_runtime::{
 };
 // Frontier
 use fc_rpc_core::types::*;
-use fp_rpc::EthereumRuntimeRPCApi;
+use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus};
 
 use crate::{
 	eth::{transaction_build, Eth},
-	frontier_backend_client, internal_err,
+	frontier_backend_client, internal_err, transaction_logs,
 };
 
+/// Max number of transaction hashes of an `eth_getTransactionReceipts` request.
+const MAX_TRANSACTION_RECEIPTS: usize = 1000;
+
 impl<B, C, P, CT, BE, H: ExHashT, A: ChainApi> Eth<B, C, P, CT, BE, H, A>
 where
 	B: BlockT<Hash = H256> + Send + Sync + 'static,
@@ -119,7 +122,12 @@ where
 				for txn in ethereum_transactions {
 					let inner_hash = txn.hash();
 					if hash == inner_hash {
-						return Ok(Some(transaction_build(txn, None, None, None)));
+						return Ok(Some(transaction_build(
+							txn,
+							None,
+							None,
+							self.pending_base_fee(),
+						)));
 					}
 				}
 				// Unknown transaction.
@@ -282,186 +290,448 @@ where
 	}
 
 	pub async fn transaction_receipt(&self, hash: H256) -> Result<Option<Receipt>> {
+		Ok(self.transaction_receipts(vec![hash]).await?.pop().flatten())
+	}
+
+	pub async fn transaction_receipts(&self, hashes: Vec<H256>) -> Result<Vec<Option<Receipt>>> {
+		ensure_receipts_batch_size(hashes.len())?;
 		let client = Arc::clone(&self.client);
-		let overrides = Arc::clone(&self.overrides);
-		let block_data_cache = Arc::clone(&self.block_data_cache);
 		let backend = Arc::clone(&self.backend);
 
-		let (hash, index) = match frontier_backend_client::load_transactions::<B, C>(
-			client.as_ref(),
-			backend.as_ref(),
-			hash,
-			true,
-		)
-		.map_err(|err| internal_err(format!("{:?}", err)))?
-		{
-			Some((hash, index)) => (hash, index as usize),
-			None => return Ok(None),
-		};
+		// Block data is loaded once for all the requested transactions it includes.
+		let mut blocks: BTreeMap<H256, Option<BlockReceiptsData>> = BTreeMap::new();
+		let mut result = Vec::with_capacity(hashes.len());
+		for hash in hashes {
+			let transaction = frontier_backend_client::load_transactions::<B, C>(
+				client.as_ref(),
+				backend.as_ref(),
+				hash,
+				true,
+			)
+			.map_err(|err| internal_err(format!("{:?}", err)))?;
+			let (ethereum_block_hash, index) = match transaction {
+				Some((hash, index)) => (hash, index as usize),
+				None => {
+					result.push(None);
+					continue;
+				}
+			};
+
+			let id = match frontier_backend_client::load_hash::<B, C>(
+				client.as_ref(),
+				backend.as_ref(),
+				ethereum_block_hash,
+			)
+			.map_err(|err| internal_err(format!("{:?}", err)))?
+			{
+				Some(hash) => hash,
+				_ => {
+					result.push(None);
+					continue;
+				}
+			};
+			let substrate_hash = client
+				.expect_block_hash_from_id(&id)
+				.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
+
+			if !blocks.contains_key(&substrate_hash) {
+				let data = self.block_receipts_data(id, substrate_hash).await;
+				blocks.insert(substrate_hash, data);
+			}
 
-		let id = match frontier_backend_client::load_hash::<B, C>(
+			result.push(match blocks.get(&substrate_hash) {
+				Some(Some(data)) => Some(receipt_build(hash, index, data)?),
+				_ => None,
+			});
+		}
+		Ok(result)
+	}
+
+	pub async fn block_receipts(&self, number: BlockNumber) -> Result<Option<Vec<Receipt>>> {
+		let client = Arc::clone(&self.client);
+		let backend = Arc::clone(&self.backend);
+
+		let id = match frontier_backend_client::native_block_id::<B, C>(
 			client.as_ref(),
 			backend.as_ref(),
-			hash,
-		)
-		.map_err(|err| internal_err(format!("{:?}", err)))?
-		{
-			Some(hash) => hash,
-			_ => return Ok(None),
+			Some(number),
+		)? {
+			Some(id) => id,
+			None => return Ok(None),
 		};
 		let substrate_hash = client
 			.expect_block_hash_from_id(&id)
 			.map_err(|_| internal_err(format!("Expect block number from id: {}", id)))?;
 
+		match self.block_receipts_data(id, substrate_hash).await {
+			Some(data) => Ok(Some(block_receipts_build(&data)?)),
+			None => Ok(None),
+		}
+	}
+
+	async fn block_receipts_data(
+		&self,
+		id: BlockId<B>,
+		substrate_hash: B::Hash,
+	) -> Option<BlockReceiptsData> {
 		let schema =
-			frontier_backend_client::onchain_storage_schema::<B, C, BE>(client.as_ref(), id);
-		let handler = overrides
+			frontier_backend_client::onchain_storage_schema::<B, C, BE>(self.client.as_ref(), id);
+		let handler = self
+			.overrides
 			.schemas
 			.get(&schema)
-			.unwrap_or(&overrides.fallback);
+			.unwrap_or(&self.overrides.fallback);
 
-		let block = block_data_cache.current_block(schema, substrate_hash).await;
-		let statuses = block_data_cache
+		let block = self
+			.block_data_cache
+			.current_block(schema, substrate_hash)
+			.await;
+		let statuses = self
+			.block_data_cache
 			.current_transaction_statuses(schema, substrate_hash)
 			.await;
 		let receipts = handler.current_receipts(&id);
-		let is_eip1559 = handler.is_eip1559(&id);
 
 		match (block, statuses, receipts) {
-			(Some(block), Some(statuses), Some(receipts)) => {
-				let block_hash = H256::from(keccak_256(&rlp::encode(&block.header)));
-				let receipt = receipts[index].clone();
+			(Some(block), Some(statuses), Some(receipts)) => Some(BlockReceiptsData {
+				block,
+				statuses,
+				receipts,
+				is_eip1559: handler.is_eip1559(&id),
+				base_fee: handler.base_fee(&id),
+			}),
+			_ => None,
+		}
+	}
+}
 
-				let (logs, logs_bloom, status_code, cumulative_gas_used, gas_used) = if !is_eip1559
-				{
-					// Pre-london frontier update stored receipts require cumulative gas calculation.
-					match receipt {
-						ethereum::ReceiptV3::Legacy(ref d) => {
-							let index = core::cmp::min(receipts.len(), index + 1);
-							let cumulative_gas: u32 = receipts[..index]
-								.iter()
-								.map(|r| match r {
-									ethereum::ReceiptV3::Legacy(d) => Ok(d.used_gas.as_u32()),
-									_ => Err(internal_err(format!(
-										"Unknown receipt for request {}",
-										hash
-									))),
-								})
-								.sum::<Result<u32>>()?;
-							(
-								d.logs.clone(),
-								d.logs_bloom,
-								d.status_code,
-								U256::from(cumulative_gas),
-								d.used_gas,
-							)
-						}
-						_ => {
-							return Err(internal_err(format!(
-								"Unknown receipt for request {}",
-								hash
-							)))
-						}
-					}
+/// Block data needed to build the receipts of its transactions.
+struct BlockReceiptsData {
+	block: EthereumBlock,
+	statuses: Vec<TransactionStatus>,
+	receipts: Vec<ethereum::ReceiptV3>,
+	is_eip1559: bool,
+	base_fee: Option<U256>,
+}
+
+fn receipt_build(hash: H256, index: usize, data: &BlockReceiptsData) -> Result<Receipt> {
+	let BlockReceiptsData {
+		block,
+		statuses,
+		receipts,
+		is_eip1559,
+		base_fee,
+	} = data;
+	let unknown_receipt = || internal_err(format!("Unknown receipt for request {}", hash));
+
+	let block_hash = H256::from(keccak_256(&rlp::encode(&block.header)));
+	let receipt = receipts.get(index).ok_or_else(unknown_receipt)?;
+
+	let (logs, logs_bloom, status_code, cumulative_gas_used, gas_used) = if !is_eip1559 {
+		// Pre-london frontier update stored receipts require cumulative gas calculation.
+		match receipt {
+			ethereum::ReceiptV3::Legacy(d) => {
+				let index = core::cmp::min(receipts.len(), index + 1);
+				let cumulative_gas: u32 = receipts[..index]
+					.iter()
+					.map(|r| match r {
+						ethereum::ReceiptV3::Legacy(d) => Ok(d.used_gas.as_u32()),
+						_ => Err(unknown_receipt()),
+					})
+					.sum::<Result<u32>>()?;
+				(
+					&d.logs,
+					d.logs_bloom,
+					d.status_code,
+					U256::from(cumulative_gas),
+					d.used_gas,
+				)
+			}
+			_ => return Err(unknown_receipt()),
+		}
+	} else {
+		match receipt {
+			ethereum::ReceiptV3::Legacy(d)
+			| ethereum::ReceiptV3::EIP2930(d)
+			| ethereum::ReceiptV3::EIP1559(d) => {
+				let cumulative_gas = d.used_gas;
+				let gas_used = if index > 0 {
+					let previous_gas_used = match &receipts[index - 1] {
+						ethereum::ReceiptV3::Legacy(d)
+						| ethereum::ReceiptV3::EIP2930(d)
+						| ethereum::ReceiptV3::EIP1559(d) => d.used_gas,
+					};
+					cumulative_gas.saturating_sub(previous_gas_used)
 				} else {
-					match receipt {
-						ethereum::ReceiptV3::Legacy(ref d)
-						| ethereum::ReceiptV3::EIP2930(ref d)
-						| ethereum::ReceiptV3::EIP1559(ref d) => {
-							let cumulative_gas = d.used_gas;
-							let gas_used = if index > 0 {
-								let previous_receipt = receipts[index - 1].clone();
-								let previous_gas_used = match previous_receipt {
-									ethereum::ReceiptV3::Legacy(d)
-									| ethereum::ReceiptV3::EIP2930(d)
-									| ethereum::ReceiptV3::EIP1559(d) => d.used_gas,
-								};
-								cumulative_gas.saturating_sub(previous_gas_used)
-							} else {
-								cumulative_gas
-							};
-							(
-								d.logs.clone(),
-								d.logs_bloom,
-								d.status_code,
-								cumulative_gas,
-								gas_used,
-							)
-						}
-					}
+					cumulative_gas
 				};
+				(
+					&d.logs,
+					d.logs_bloom,
+					d.status_code,
+					cumulative_gas,
+					gas_used,
+				)
+			}
+		}
+	};
+
+	let status = statuses.get(index).ok_or_else(unknown_receipt)?;
+	let transaction = block.transactions.get(index).ok_or_else(unknown_receipt)?;
+	let effective_gas_price = match transaction {
+		EthereumTransaction::Legacy(t) => t.gas_price,
+		EthereumTransaction::EIP2930(t) => t.gas_price,
+		EthereumTransaction::EIP1559(t) => base_fee
+			.unwrap_or_default()
+			.checked_add(t.max_priority_fee_per_gas)
+			.unwrap_or_else(U256::max_value)
+			.min(t.max_fee_per_gas),
+	};
+
+	// Logs of the preceding transactions come first in the block.
+	let previous_receipts = core::cmp::min(receipts.len(), status.transaction_index as usize);
+	let pre_receipts_log_index = receipts[..previous_receipts]
+		.iter()
+		.map(|r| match r {
+			ethereum::ReceiptV3::Legacy(d)
+			| ethereum::ReceiptV3::EIP2930(d)
+			| ethereum::ReceiptV3::EIP1559(d) => d.logs.len() as u32,
+		})
+		.sum::<u32>();
+
+	Ok(Receipt {
+		transaction_hash: Some(status.transaction_hash),
+		transaction_index: Some(status.transaction_index.into()),
+		block_hash: Some(block_hash),
+		from: Some(status.from),
+		to: status.to,
+		block_number: Some(block.header.number),
+		cumulative_gas_used,
+		gas_used: Some(gas_used),
+		contract_address: status.contract_address,
+		logs: transaction_logs(
+			block_hash,
+			block.header.number,
+			status.transaction_hash,
+			status.transaction_index,
+			pre_receipts_log_index,
+			logs,
+		),
+		status_code: Some(U64::from(status_code)),
+		logs_bloom,
+		state_root: None,
+		effective_gas_price,
+		// Taken from the envelope, older storage schemas keep typed transaction
+		// receipts as legacy ones.
+		transaction_type: match transaction {
+			EthereumTransaction::Legacy(_) => U256::from(0),
+			EthereumTransaction::EIP2930(_) => U256::from(1),
+			EthereumTransaction::EIP1559(_) => U256::from(2),
+		},
+	})
+}
 
-				let status = statuses[index].clone();
-				let mut cumulative_receipts = receipts;
-				cumulative_receipts.truncate((status.transaction_index + 1) as usize);
-
-				let transaction = block.transactions[index].clone();
-				let effective_gas_price = match transaction {
-					EthereumTransaction::Legacy(t) => t.gas_price,
-					EthereumTransaction::EIP2930(t) => t.gas_price,
-					EthereumTransaction::EIP1559(t) => handler
-						.base_fee(&id)
-						.unwrap_or_default()
-						.checked_add(t.max_priority_fee_per_gas)
-						.unwrap_or_else(U256::max_value)
-						.min(t.max_fee_per_gas),
-				};
+/// Receipts of all the transactions of a block, checking that the gas used adds up:
+/// the cumulative gas used of each receipt is the one of the previous receipt plus its
+/// own gas used, totalling the gas used of the block.
+fn block_receipts_build(data: &BlockReceiptsData) -> Result<Vec<Receipt>> {
+	let receipts = data
+		.statuses
+		.iter()
+		.enumerate()
+		.map(|(index, status)| receipt_build(status.transaction_hash, index, data))
+		.collect::<Result<Vec<_>>>()?;
+
+	let mut cumulative_gas_used = U256::zero();
+	for receipt in &receipts {
+		cumulative_gas_used =
+			cumulative_gas_used.saturating_add(receipt.gas_used.unwrap_or_default());
+		if receipt.cumulative_gas_used != cumulative_gas_used {
+			return Err(internal_err(format!(
+				"inconsistent receipt of transaction {:?}: cumulative gas used {}, expected {}",
+				receipt.transaction_hash.unwrap_or_default(),
+				receipt.cumulative_gas_used,
+				cumulative_gas_used
+			)));
+		}
+	}
+	if cumulative_gas_used != data.block.header.gas_used {
+		return Err(internal_err(format!(
+			"inconsistent receipts: cumulative gas used {}, block gas used {}",
+			cumulative_gas_used, data.block.header.gas_used
+		)));
+	}
+	Ok(receipts)
+}
 
-				return Ok(Some(Receipt {
-					transaction_hash: Some(status.transaction_hash),
-					transaction_index: Some(status.transaction_index.into()),
-					block_hash: Some(block_hash),
-					from: Some(status.from),
-					to: status.to,
-					block_number: Some(block.header.number),
-					cumulative_gas_used,
-					gas_used: Some(gas_used),
-					contract_address: status.contract_address,
-					logs: {
-						let mut pre_receipts_log_index = None;
-						if cumulative_receipts.len() > 0 {
-							cumulative_receipts.truncate(cumulative_receipts.len() - 1);
-							pre_receipts_log_index = Some(
-								cumulative_receipts
-									.iter()
-									.map(|r| match r {
-										ethereum::ReceiptV3::Legacy(d)
-										| ethereum::ReceiptV3::EIP2930(d)
-										| ethereum::ReceiptV3::EIP1559(d) => d.logs.len() as u32,
-									})
-									.sum::<u32>(),
-							);
-						}
-						logs.iter()
-							.enumerate()
-							.map(|(i, log)| Log {
-								address: log.address,
-								topics: log.topics.clone(),
-								data: Bytes(log.data.clone()),
-								block_hash: Some(block_hash),
-								block_number: Some(block.header.number),
-								transaction_hash: Some(status.transaction_hash),
-								transaction_index: Some(status.transaction_index.into()),
-								log_index: Some(U256::from(
-									(pre_receipts_log_index.unwrap_or(0)) + i as u32,
-								)),
-								transaction_log_index: Some(U256::from(i)),
-								removed: false,
-							})
-							.collect()
-					},
-					status_code: Some(U64::from(status_code)),
-					logs_bloom,
-					state_root: None,
-					effective_gas_price,
-					transaction_type: match receipt {
-						ethereum::ReceiptV3::Legacy(_) => U256::from(0),
-						ethereum::ReceiptV3::EIP2930(_) => U256::from(1),
-						ethereum::ReceiptV3::EIP1559(_) => U256::from(2),
-					},
-				}));
+fn ensure_receipts_batch_size(hashes: usize) -> Result<()> {
+	if hashes > MAX_TRANSACTION_RECEIPTS {
+		return Err(internal_err(format!(
+			"request of {} receipts exceeds the limit of {} receipts",
+			hashes, MAX_TRANSACTION_RECEIPTS
+		)));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::partial_header;
+	use ethereum_types::H160;
+
+	fn block_receipts_data() -> BlockReceiptsData {
+		let to = || ethereum::TransactionAction::Call(H160::repeat_byte(0x11));
+		let transactions = vec![
+			EthereumTransaction::Legacy(ethereum::LegacyTransaction {
+				nonce: U256::zero(),
+				gas_price: U256::one(),
+				gas_limit: U256::from(21_000u64),
+				action: to(),
+				value: U256::zero(),
+				input: Vec::new(),
+				signature: ethereum::TransactionSignature::new(
+					27,
+					H256::repeat_byte(0x01),
+					H256::repeat_byte(0x01),
+				)
+				.unwrap(),
+			}),
+			EthereumTransaction::EIP2930(ethereum::EIP2930Transaction {
+				chain_id: 42,
+				nonce: U256::one(),
+				gas_price: U256::one(),
+				gas_limit: U256::from(21_000u64),
+				action: to(),
+				value: U256::zero(),
+				input: Vec::new(),
+				access_list: Vec::new(),
+				odd_y_parity: false,
+				r: H256::repeat_byte(0x01),
+				s: H256::repeat_byte(0x01),
+			}),
+			EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
+				chain_id: 42,
+				nonce: U256::from(2u64),
+				max_priority_fee_per_gas: U256::zero(),
+				max_fee_per_gas: U256::one(),
+				gas_limit: U256::from(21_000u64),
+				action: to(),
+				value: U256::zero(),
+				input: Vec::new(),
+				access_list: Vec::new(),
+				odd_y_parity: false,
+				r: H256::repeat_byte(0x01),
+				s: H256::repeat_byte(0x01),
+			}),
+		];
+		let statuses = transactions
+			.iter()
+			.enumerate()
+			.map(|(index, transaction)| TransactionStatus {
+				transaction_hash: transaction.hash(),
+				transaction_index: index as u32,
+				from: H160::repeat_byte(0x22),
+				to: Some(H160::repeat_byte(0x11)),
+				contract_address: None,
+				logs: Vec::new(),
+				logs_bloom: Default::default(),
+			})
+			.collect();
+		// Stored the way older schemas do, as legacy receipts whatever the type.
+		let receipts = transactions
+			.iter()
+			.map(|_| {
+				ethereum::ReceiptV3::Legacy(ethereum::EIP658ReceiptData {
+					status_code: 1,
+					used_gas: U256::from(21_000u64),
+					logs_bloom: Default::default(),
+					logs: Vec::new(),
+				})
+			})
+			.collect();
+		let block = EthereumBlock::new(
+			ethereum::PartialHeader {
+				gas_limit: U256::from(15_000_000u64),
+				gas_used: U256::from(63_000u64),
+				..partial_header(1)
+			},
+			transactions,
+			Vec::new(),
+		);
+
+		BlockReceiptsData {
+			block,
+			statuses,
+			receipts,
+			is_eip1559: false,
+			base_fee: None,
+		}
+	}
+
+	#[test]
+	fn receipt_type_is_the_transaction_type() {
+		let data = block_receipts_data();
+		let receipt_type = |index: usize| {
+			let hash = data.statuses[index].transaction_hash;
+			format_quantity(receipt_build(hash, index, &data).unwrap().transaction_type)
+		};
+
+		assert_eq!(receipt_type(0), "0x0");
+		assert_eq!(receipt_type(1), "0x1");
+		assert_eq!(receipt_type(2), "0x2");
+	}
+
+	#[test]
+	fn block_receipts_add_up_to_the_block_gas_used() {
+		// Stored as legacy receipts, the cumulative gas used is summed from their gas used.
+		let data = block_receipts_data();
+		let receipts = block_receipts_build(&data).unwrap();
+		let cumulative = receipts
+			.iter()
+			.map(|receipt| (receipt.cumulative_gas_used, receipt.gas_used))
+			.collect::<Vec<_>>();
+		let gas = |gas: u64| U256::from(gas);
+		assert_eq!(
+			cumulative,
+			vec![
+				(gas(21_000), Some(gas(21_000))),
+				(gas(42_000), Some(gas(21_000))),
+				(gas(63_000), Some(gas(21_000))),
+			]
+		);
+
+		// Post-london receipts store the cumulative gas used.
+		let mut data = block_receipts_data();
+		data.is_eip1559 = true;
+		for (index, receipt) in data.receipts.iter_mut().enumerate() {
+			if let ethereum::ReceiptV3::Legacy(d) = receipt {
+				d.used_gas = gas(21_000 * (index as u64 + 1));
 			}
-			_ => Ok(None),
 		}
+		let receipts = block_receipts_build(&data).unwrap();
+		assert_eq!(
+			receipts.last().map(|receipt| receipt.cumulative_gas_used),
+			Some(data.block.header.gas_used)
+		);
+
+		// Receipts not adding up to the gas used of the block are rejected.
+		let mut data = block_receipts_data();
+		data.block.header.gas_used = gas(60_000);
+		let err = block_receipts_build(&data).unwrap_err().to_string();
+		assert!(
+			err.contains("inconsistent receipts: cumulative gas used 63000, block gas used 60000")
+		);
+	}
+
+	#[test]
+	fn receipts_batches_are_capped() {
+		let err = ensure_receipts_batch_size(MAX_TRANSACTION_RECEIPTS + 1).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("request of 1001 receipts exceeds the limit of 1000 receipts"));
+
+		assert!(ensure_receipts_batch_size(MAX_TRANSACTION_RECEIPTS).is_ok());
+		assert!(ensure_receipts_batch_size(0).is_ok());
 	}
 }
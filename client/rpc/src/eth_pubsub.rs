@@ -16,12 +16,22 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+use std::{
+	collections::BTreeMap,
+	marker::PhantomData,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+};
 
 use ethereum::{BlockV2 as EthereumBlock, TransactionV2 as EthereumTransaction};
 use ethereum_types::{H256, U256};
 use futures::{FutureExt as _, StreamExt as _};
-use jsonrpsee::{types::SubscriptionResult, SubscriptionSink};
+use jsonrpsee::{
+	types::{ErrorObject, ErrorObjectOwned, SubscriptionResult},
+	SubscriptionSink,
+};
 // Substrate
 use sc_client_api::{
 	backend::{Backend, StateBackend, StorageProvider},
@@ -34,25 +44,44 @@ use sp_api::{ApiExt, BlockId, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_consensus::SyncOracle;
 use sp_core::hashing::keccak_256;
-use sp_runtime::traits::{BlakeTwo256, Block as BlockT, UniqueSaturatedInto};
+use sp_runtime::traits::{BlakeTwo256, Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
 // Frontier
 use fc_rpc_core::{
 	types::{
-		pubsub::{Kind, Params, PubSubSyncStatus, Result as PubSubResult, SyncStatusMetadata},
+		format_hex,
+		pubsub::{
+			Kind, Params, PubSubSyncStatus, Result as PubSubResult, Resync, SyncStatusMetadata,
+		},
 		Bytes, FilteredParams, Header, Log, Rich,
 	},
 	EthPubSubApiServer,
 };
 use fp_rpc::EthereumRuntimeRPCApi;
 
-use crate::{frontier_backend_client, overrides::OverrideHandle};
+use crate::{
+	frontier_backend_client, overrides::OverrideHandle, transaction_logs, METHOD_NOT_SUPPORTED,
+};
 
 #[derive(Debug)]
 pub struct EthereumSubIdProvider;
 
 impl jsonrpsee::core::traits::IdProvider for EthereumSubIdProvider {
 	fn next_id(&self) -> jsonrpsee::types::SubscriptionId<'static> {
-		format!("0x{}", hex::encode(rand::random::<u128>().to_le_bytes())).into()
+		format_hex(&rand::random::<u128>().to_le_bytes()).into()
+	}
+}
+
+/// Subscription id provider handing out increasing ids, `0x..01` first, instead of the
+/// random ones of [`EthereumSubIdProvider`], so that tests can expect given ids.
+#[derive(Debug, Default)]
+pub struct SequentialSubIdProvider {
+	last_id: AtomicU64,
+}
+
+impl jsonrpsee::core::traits::IdProvider for SequentialSubIdProvider {
+	fn next_id(&self) -> jsonrpsee::types::SubscriptionId<'static> {
+		let id = self.last_id.fetch_add(1, Ordering::SeqCst) + 1;
+		format_hex(&(id as u128).to_be_bytes()).into()
 	}
 }
 
@@ -64,6 +93,10 @@ pub struct EthPubSub<B: BlockT, P, C, BE, H: ExHashT> {
 	subscriptions: SubscriptionTaskExecutor,
 	overrides: Arc<OverrideHandle<B>>,
 	starting_block: u64,
+	/// Subscription kinds allowed, all of them if `None`.
+	allowed_kinds: Option<Vec<Kind>>,
+	/// Maximum number of retracted blocks the logs subscription sends removed logs for.
+	max_logs_reorg_depth: u32,
 	_marker: PhantomData<BE>,
 }
 
@@ -77,6 +110,8 @@ where
 		network: Arc<NetworkService<B, H>>,
 		subscriptions: SubscriptionTaskExecutor,
 		overrides: Arc<OverrideHandle<B>>,
+		allowed_kinds: Option<Vec<Kind>>,
+		max_logs_reorg_depth: u32,
 	) -> Self {
 		// Capture the best block as seen on initialization. Used for syncing subscriptions.
 		let starting_block =
@@ -88,6 +123,8 @@ where
 			subscriptions,
 			overrides,
 			starting_block,
+			allowed_kinds,
+			max_logs_reorg_depth,
 			_marker: PhantomData,
 		}
 	}
@@ -95,7 +132,7 @@ where
 
 struct EthSubscriptionResult;
 impl EthSubscriptionResult {
-	pub fn new_heads(block: EthereumBlock) -> PubSubResult {
+	pub fn new_heads(block: EthereumBlock, base_fee: Option<U256>) -> PubSubResult {
 		PubSubResult::Header(Box::new(Rich {
 			inner: Header {
 				hash: Some(H256::from(keccak_256(&rlp::encode(&block.header)))),
@@ -111,10 +148,13 @@ impl EthSubscriptionResult {
 				gas_limit: block.header.gas_limit,
 				extra_data: Bytes(block.header.extra_data.clone()),
 				logs_bloom: block.header.logs_bloom,
-				timestamp: U256::from(block.header.timestamp),
+				timestamp: U256::from(block.header.timestamp / 1000),
 				difficulty: block.header.difficulty,
+				mix_hash: block.header.mix_hash,
 				nonce: Some(block.header.nonce),
 				size: Some(U256::from(rlp::encode(&block.header).len() as u32)),
+				base_fee_per_gas: base_fee,
+				withdrawals_root: None,
 			},
 			extra_info: BTreeMap::new(),
 		}))
@@ -124,7 +164,7 @@ impl EthSubscriptionResult {
 		receipts: Vec<ethereum::ReceiptV3>,
 		params: &FilteredParams,
 	) -> Vec<Log> {
-		let block_hash = Some(H256::from(keccak_256(&rlp::encode(&block.header))));
+		let block_hash = H256::from(keccak_256(&rlp::encode(&block.header)));
 		let mut logs: Vec<Log> = vec![];
 		let mut log_index: u32 = 0;
 		for (receipt_index, receipt) in receipts.into_iter().enumerate() {
@@ -133,58 +173,39 @@ impl EthSubscriptionResult {
 				| ethereum::ReceiptV3::EIP2930(d)
 				| ethereum::ReceiptV3::EIP1559(d) => d.logs,
 			};
-			let mut transaction_log_index: u32 = 0;
-			let transaction_hash: Option<H256> = if receipt_logs.len() > 0 {
-				Some(block.transactions[receipt_index as usize].hash())
-			} else {
-				None
-			};
-			for log in receipt_logs {
-				if Self::add_log(block_hash.unwrap(), &log, &block, params) {
-					logs.push(Log {
-						address: log.address,
-						topics: log.topics,
-						data: Bytes(log.data),
-						block_hash,
-						block_number: Some(block.header.number),
-						transaction_hash,
-						transaction_index: Some(U256::from(receipt_index)),
-						log_index: Some(U256::from(log_index)),
-						transaction_log_index: Some(U256::from(transaction_log_index)),
-						removed: false,
-					});
-				}
-				log_index += 1;
-				transaction_log_index += 1;
+			if receipt_logs.is_empty() {
+				continue;
 			}
+			let formatted = transaction_logs(
+				block_hash,
+				block.header.number,
+				block.transactions[receipt_index].hash(),
+				receipt_index as u32,
+				log_index,
+				&receipt_logs,
+			);
+			log_index += receipt_logs.len() as u32;
+			logs.extend(
+				formatted
+					.into_iter()
+					.filter(|log| Self::add_log(block_hash, log, &block, params)),
+			);
 		}
 		logs
 	}
 	fn add_log(
 		block_hash: H256,
-		ethereum_log: &ethereum::Log,
+		log: &Log,
 		block: &EthereumBlock,
 		params: &FilteredParams,
 	) -> bool {
-		let log = Log {
-			address: ethereum_log.address,
-			topics: ethereum_log.topics.clone(),
-			data: Bytes(ethereum_log.data.clone()),
-			block_hash: None,
-			block_number: None,
-			transaction_hash: None,
-			transaction_index: None,
-			log_index: None,
-			transaction_log_index: None,
-			removed: false,
-		};
 		if params.filter.is_some() {
 			let block_number =
 				UniqueSaturatedInto::<u64>::unique_saturated_into(block.header.number);
 			if !params.filter_block_range(block_number)
 				|| !params.filter_block_hash(block_hash)
-				|| !params.filter_address(&log)
-				|| !params.filter_topics(&log)
+				|| !params.filter_address(log)
+				|| !params.filter_topics(log)
 			{
 				return false;
 			}
@@ -209,6 +230,10 @@ where
 		kind: Kind,
 		params: Option<Params>,
 	) -> SubscriptionResult {
+		if let Err(err) = ensure_kind_allowed(self.allowed_kinds.as_deref(), &kind) {
+			sink.reject(err)?;
+			return Ok(());
+		}
 		sink.accept()?;
 
 		let filtered_params = match params {
@@ -221,14 +246,15 @@ where
 		let network = self.network.clone();
 		let overrides = self.overrides.clone();
 		let starting_block = self.starting_block;
+		let max_logs_reorg_depth = self.max_logs_reorg_depth;
 		let fut = async move {
 			match kind {
 				Kind::Logs => {
 					let stream = client
 						.import_notification_stream()
 						.filter_map(move |notification| {
-							if notification.is_new_best {
-								let id = BlockId::Hash(notification.hash);
+							let block_data = |hash: B::Hash| {
+								let id = BlockId::Hash(hash);
 
 								let schema = frontier_backend_client::onchain_storage_schema::<
 									B,
@@ -242,25 +268,33 @@ where
 
 								let block = handler.current_block(&id);
 								let receipts = handler.current_receipts(&id);
-
-								match (receipts, block) {
-									(Some(receipts), Some(block)) => {
-										futures::future::ready(Some((block, receipts)))
-									}
-									_ => futures::future::ready(None),
-								}
+								block.zip(receipts)
+							};
+							if notification.is_new_best {
+								// Only set when the new best block is not a child of the previous one.
+								let (retracted, enacted) = match &notification.tree_route {
+									Some(route) => (
+										route.retracted().iter().map(|block| block.hash).collect(),
+										route.enacted().iter().map(|block| block.hash).collect(),
+									),
+									None => (Vec::new(), Vec::new()),
+								};
+								futures::future::ready(Some(new_best_logs(
+									&retracted,
+									&enacted,
+									notification.hash,
+									UniqueSaturatedInto::<u64>::unique_saturated_into(
+										*notification.header.number(),
+									),
+									max_logs_reorg_depth,
+									&filtered_params,
+									block_data,
+								)))
 							} else {
 								futures::future::ready(None)
 							}
 						})
-						.flat_map(move |(block, receipts)| {
-							futures::stream::iter(EthSubscriptionResult::logs(
-								block,
-								receipts,
-								&filtered_params,
-							))
-						})
-						.map(|x| PubSubResult::Log(Box::new(x)));
+						.flat_map(futures::stream::iter);
 					sink.pipe_from_stream(stream).await;
 				}
 				Kind::NewHeads => {
@@ -280,13 +314,15 @@ where
 									.get(&schema)
 									.unwrap_or(&overrides.fallback);
 
-								let block = handler.current_block(&id);
+								let block = handler
+									.current_block(&id)
+									.map(|block| (block, handler.base_fee(&id)));
 								futures::future::ready(block)
 							} else {
 								futures::future::ready(None)
 							}
 						})
-						.map(EthSubscriptionResult::new_heads);
+						.map(|(block, base_fee)| EthSubscriptionResult::new_heads(block, base_fee));
 					sink.pipe_from_stream(stream).await;
 				}
 				Kind::NewPendingTransactions => {
@@ -370,9 +406,9 @@ where
 
 							PubSubSyncStatus::Detailed(SyncStatusMetadata {
 								syncing: true,
-								starting_block,
-								current_block,
-								highest_block,
+								starting_block: U256::from(starting_block),
+								current_block: U256::from(current_block),
+								highest_block: highest_block.map(U256::from),
 							})
 						} else {
 							PubSubSyncStatus::Simple(false)
@@ -412,3 +448,242 @@ where
 		Ok(())
 	}
 }
+
+/// Notifications of the logs subscription for the new best block `best`, of number
+/// `best_number`, which retracted and enacted the given blocks when reorganizing the
+/// chain.
+///
+/// The logs of the retracted blocks are sent again as removed, then those of the
+/// enacted blocks and of the new best one. A reorg retracting more than
+/// `max_reorg_depth` blocks sends a single resync signal instead of scanning them.
+fn new_best_logs<H: Copy, F>(
+	retracted: &[H],
+	enacted: &[H],
+	best: H,
+	best_number: u64,
+	max_reorg_depth: u32,
+	params: &FilteredParams,
+	block_data: F,
+) -> Vec<PubSubResult>
+where
+	F: Fn(H) -> Option<(EthereumBlock, Vec<ethereum::ReceiptV3>)>,
+{
+	if retracted.len() > max_reorg_depth as usize {
+		return vec![PubSubResult::Resync(Resync {
+			retracted: U256::from(retracted.len()),
+			from_block: U256::from(best_number.saturating_sub(enacted.len() as u64)),
+		})];
+	}
+	let block_logs = |hash: H| match block_data(hash) {
+		Some((block, receipts)) => EthSubscriptionResult::logs(block, receipts, params),
+		None => Vec::new(),
+	};
+	let removed = retracted.iter().flat_map(|hash| {
+		block_logs(*hash).into_iter().map(|mut log| {
+			log.removed = true;
+			log
+		})
+	});
+	let added = enacted
+		.iter()
+		.copied()
+		.chain(Some(best))
+		.flat_map(block_logs);
+	removed
+		.chain(added)
+		.map(|log| PubSubResult::Log(Box::new(log)))
+		.collect()
+}
+
+/// Fails on a subscription kind left out of the allowed ones, `None` allowing all.
+fn ensure_kind_allowed(
+	allowed_kinds: Option<&[Kind]>,
+	kind: &Kind,
+) -> Result<(), ErrorObjectOwned> {
+	match allowed_kinds {
+		Some(allowed_kinds) if !allowed_kinds.contains(kind) => Err(ErrorObject::owned(
+			METHOD_NOT_SUPPORTED,
+			format!("subscription kind {:?} is not allowed", kind),
+			None::<()>,
+		)),
+		_ => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::partial_header;
+
+	#[test]
+	fn new_heads_have_the_block_header_fields() {
+		let block = EthereumBlock::new(
+			ethereum::PartialHeader {
+				parent_hash: H256::repeat_byte(0x01),
+				beneficiary: ethereum_types::H160::repeat_byte(0x02),
+				state_root: H256::repeat_byte(0x03),
+				receipts_root: H256::repeat_byte(0x04),
+				logs_bloom: ethereum_types::Bloom::repeat_byte(0x05),
+				difficulty: U256::from(6u64),
+				number: U256::from(7u64),
+				gas_limit: U256::from(8_000_000u64),
+				gas_used: U256::from(21_000u64),
+				timestamp: 10_000,
+				extra_data: vec![0x0b],
+				mix_hash: H256::repeat_byte(0x0c),
+				nonce: ethereum_types::H64::repeat_byte(0x0d),
+			},
+			Vec::new(),
+			Vec::new(),
+		);
+		let header = block.header.clone();
+
+		let head = match EthSubscriptionResult::new_heads(block, Some(U256::from(1_000u64))) {
+			PubSubResult::Header(head) => head,
+			_ => panic!("expected a header"),
+		};
+		assert_eq!(head.hash, Some(header.hash()));
+		assert_eq!(head.parent_hash, header.parent_hash);
+		assert_eq!(head.uncles_hash, header.ommers_hash);
+		assert_eq!(head.miner, header.beneficiary);
+		assert_eq!(head.state_root, header.state_root);
+		assert_eq!(head.transactions_root, header.transactions_root);
+		assert_eq!(head.receipts_root, header.receipts_root);
+		assert_eq!(head.logs_bloom, header.logs_bloom);
+		assert_eq!(head.difficulty, header.difficulty);
+		assert_eq!(head.number, Some(header.number));
+		assert_eq!(head.gas_limit, header.gas_limit);
+		assert_eq!(head.gas_used, header.gas_used);
+		assert_eq!(head.timestamp, U256::from(10u64));
+		assert_eq!(head.extra_data, Bytes(header.extra_data.clone()));
+		assert_eq!(head.mix_hash, header.mix_hash);
+		assert_eq!(head.nonce, Some(header.nonce));
+		assert_eq!(head.base_fee_per_gas, Some(U256::from(1_000u64)));
+		assert!(head.extra_info.is_empty());
+	}
+
+	fn block_with_log(number: u64) -> (EthereumBlock, Vec<ethereum::ReceiptV3>) {
+		let transaction = EthereumTransaction::Legacy(ethereum::LegacyTransaction {
+			nonce: U256::from(number),
+			gas_price: U256::zero(),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(ethereum_types::H160::zero()),
+			value: U256::zero(),
+			input: Vec::new(),
+			signature: ethereum::TransactionSignature::new(
+				27,
+				H256::repeat_byte(0x01),
+				H256::repeat_byte(0x01),
+			)
+			.unwrap(),
+		});
+		let block = EthereumBlock::new(
+			ethereum::PartialHeader {
+				gas_limit: U256::from(8_000_000u64),
+				gas_used: U256::from(21_000u64),
+				..partial_header(number)
+			},
+			vec![transaction],
+			Vec::new(),
+		);
+		let receipt = ethereum::ReceiptV3::Legacy(ethereum::EIP658ReceiptData {
+			status_code: 1,
+			used_gas: U256::from(21_000u64),
+			logs_bloom: Default::default(),
+			logs: vec![ethereum::Log {
+				address: ethereum_types::H160::repeat_byte(0xaa),
+				topics: Vec::new(),
+				data: Vec::new(),
+			}],
+		});
+		(block, vec![receipt])
+	}
+
+	#[test]
+	fn reorgs_send_the_removed_logs_of_retracted_blocks() {
+		// Blocks 2 and 3 replaced by 2' and 3', here numbered 12 and 13.
+		let results = new_best_logs(
+			&[3u64, 2],
+			&[12],
+			13,
+			3,
+			64,
+			&FilteredParams::default(),
+			|number| Some(block_with_log(number)),
+		);
+
+		let logs: Vec<(Option<U256>, bool)> = results
+			.into_iter()
+			.map(|result| match result {
+				PubSubResult::Log(log) => (log.block_number, log.removed),
+				_ => panic!("expected a log"),
+			})
+			.collect();
+		assert_eq!(
+			logs,
+			vec![
+				(Some(U256::from(3u64)), true),
+				(Some(U256::from(2u64)), true),
+				(Some(U256::from(12u64)), false),
+				(Some(U256::from(13u64)), false),
+			]
+		);
+	}
+
+	#[test]
+	fn deep_reorgs_send_a_resync_signal() {
+		let reads = std::cell::Cell::new(0);
+		let retracted: Vec<u64> = (1..=100).rev().collect();
+		let enacted: Vec<u64> = (101..200).collect();
+
+		let results = new_best_logs(
+			&retracted,
+			&enacted,
+			200,
+			100,
+			64,
+			&FilteredParams::default(),
+			|number| {
+				reads.set(reads.get() + 1);
+				Some(block_with_log(number))
+			},
+		);
+
+		assert_eq!(
+			results,
+			vec![PubSubResult::Resync(Resync {
+				retracted: U256::from(100u64),
+				from_block: U256::from(1u64),
+			})]
+		);
+		// None of the retracted blocks is read.
+		assert_eq!(reads.get(), 0);
+	}
+
+	#[test]
+	fn sequential_ids_are_deterministic() {
+		use jsonrpsee::{core::traits::IdProvider, types::SubscriptionId};
+
+		let provider = SequentialSubIdProvider::default();
+		assert_eq!(
+			provider.next_id(),
+			SubscriptionId::from("0x00000000000000000000000000000001".to_string())
+		);
+		assert_eq!(
+			provider.next_id(),
+			SubscriptionId::from("0x00000000000000000000000000000002".to_string())
+		);
+	}
+
+	#[test]
+	fn disallowed_kinds_are_rejected() {
+		let allowed = [Kind::NewHeads];
+
+		assert!(ensure_kind_allowed(Some(&allowed), &Kind::NewHeads).is_ok());
+		let err = ensure_kind_allowed(Some(&allowed), &Kind::Logs).unwrap_err();
+		assert_eq!(err.code(), METHOD_NOT_SUPPORTED);
+		assert_eq!(err.message(), "subscription kind Logs is not allowed");
+		// All kinds are allowed by default.
+		assert!(ensure_kind_allowed(None, &Kind::Logs).is_ok());
+	}
+}
@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ethereum_types::U256;
+// Frontier
+use fc_rpc_core::types::FeeHistoryCacheItem;
+
+/// Chain state a `GasPriceOracle` prices from.
+pub struct GasPriceContext<'a> {
+	/// Minimum gas price of the runtime at the best block.
+	pub runtime_gas_price: U256,
+	/// Base fee projected for the pending block, if the runtime has one.
+	pub pending_base_fee: Option<U256>,
+	/// Number of the best block.
+	pub best_number: u64,
+	/// Fee history of the recent blocks, by block number.
+	pub fee_history_cache: &'a BTreeMap<u64, FeeHistoryCacheItem>,
+}
+
+/// Prices suggested by eth_gasPrice and eth_maxPriorityFeePerGas.
+pub trait GasPriceOracle: Send + Sync {
+	/// Gas price suggested by eth_gasPrice.
+	fn gas_price(&self, context: &GasPriceContext) -> U256;
+	/// Priority fee per gas suggested by eth_maxPriorityFeePerGas.
+	fn max_priority_fee_per_gas(&self, context: &GasPriceContext) -> U256;
+}
+
+/// Suggests the lowest tip a `percentile` of the transactions paid in each of the last
+/// `block_count` blocks, at least `priority_fee_floor`, and a gas price enough for the
+/// pending block.
+pub struct PercentileGasPriceOracle {
+	/// Percentile of the tips of a block, in `0..=100`.
+	pub percentile: u8,
+	/// Number of recent blocks whose tips are considered.
+	pub block_count: u64,
+	/// Lowest suggested tip, for chains whose recent blocks paid none.
+	pub priority_fee_floor: U256,
+}
+
+impl Default for PercentileGasPriceOracle {
+	// https://github.com/ethereum/go-ethereum/blob/master/eth/ethconfig/config.go#L44-L51
+	fn default() -> Self {
+		Self {
+			percentile: 60,
+			block_count: 20,
+			priority_fee_floor: U256::zero(),
+		}
+	}
+}
+
+impl GasPriceOracle for PercentileGasPriceOracle {
+	fn gas_price(&self, context: &GasPriceContext) -> U256 {
+		// Enough for the next block, whose base fee may be higher than the current one.
+		match context.pending_base_fee {
+			Some(pending_base_fee) => context.runtime_gas_price.max(pending_base_fee),
+			None => context.runtime_gas_price,
+		}
+	}
+
+	fn max_priority_fee_per_gas(&self, context: &GasPriceContext) -> U256 {
+		// The cache keeps the rewards at every half percentile.
+		let index = self.percentile as usize * 2;
+		let highest = context.best_number;
+		let lowest = highest.saturating_sub(self.block_count.saturating_sub(1));
+
+		// https://github.com/ethereum/go-ethereum/blob/master/eth/gasprice/gasprice.go#L149
+		(lowest..=highest)
+			.filter_map(|n| context.fee_history_cache.get(&n))
+			.map(|block| U256::from(block.rewards.get(index).copied().unwrap_or(0)))
+			.min()
+			.unwrap_or_default()
+			.max(self.priority_fee_floor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn cached_block(reward: u64) -> FeeHistoryCacheItem {
+		FeeHistoryCacheItem {
+			base_fee: 1_000,
+			gas_used_ratio: 0.5,
+			rewards: vec![reward; 201],
+		}
+	}
+
+	#[test]
+	fn prices_come_from_the_oracle() {
+		struct FlatGasPriceOracle(U256);
+
+		impl GasPriceOracle for FlatGasPriceOracle {
+			fn gas_price(&self, _: &GasPriceContext) -> U256 {
+				self.0
+			}
+
+			fn max_priority_fee_per_gas(&self, _: &GasPriceContext) -> U256 {
+				U256::zero()
+			}
+		}
+
+		let mut fee_history_cache = BTreeMap::new();
+		fee_history_cache.insert(9, cached_block(30));
+		fee_history_cache.insert(10, cached_block(20));
+		let context = GasPriceContext {
+			runtime_gas_price: U256::from(500u64),
+			pending_base_fee: Some(U256::from(1_100u64)),
+			best_number: 10,
+			fee_history_cache: &fee_history_cache,
+		};
+
+		let default: Box<dyn GasPriceOracle> = Box::new(PercentileGasPriceOracle::default());
+		assert_eq!(default.gas_price(&context), U256::from(1_100u64));
+		assert_eq!(
+			default.max_priority_fee_per_gas(&context),
+			U256::from(20u64)
+		);
+
+		let flat: Box<dyn GasPriceOracle> = Box::new(FlatGasPriceOracle(U256::from(7u64)));
+		assert_eq!(flat.gas_price(&context), U256::from(7u64));
+		assert_eq!(flat.max_priority_fee_per_gas(&context), U256::zero());
+	}
+
+	#[test]
+	fn priority_fee_has_a_floor() {
+		let mut fee_history_cache = BTreeMap::new();
+		fee_history_cache.insert(9, cached_block(0));
+		fee_history_cache.insert(10, cached_block(0));
+		let context = GasPriceContext {
+			runtime_gas_price: U256::from(500u64),
+			pending_base_fee: None,
+			best_number: 10,
+			fee_history_cache: &fee_history_cache,
+		};
+
+		let oracle = PercentileGasPriceOracle::default();
+		assert_eq!(oracle.max_priority_fee_per_gas(&context), U256::zero());
+		let oracle = PercentileGasPriceOracle {
+			priority_fee_floor: U256::from(1_000_000_000u64),
+			..Default::default()
+		};
+		assert_eq!(
+			oracle.max_priority_fee_per_gas(&context),
+			U256::from(1_000_000_000u64)
+		);
+
+		// Tips above the floor are suggested as they are.
+		fee_history_cache.insert(10, cached_block(2_000_000_000));
+		fee_history_cache.insert(9, cached_block(3_000_000_000));
+		let context = GasPriceContext {
+			runtime_gas_price: U256::from(500u64),
+			pending_base_fee: None,
+			best_number: 10,
+			fee_history_cache: &fee_history_cache,
+		};
+		assert_eq!(
+			oracle.max_priority_fee_per_gas(&context),
+			U256::from(2_000_000_000u64)
+		);
+	}
+}
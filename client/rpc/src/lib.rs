@@ -25,22 +25,34 @@
 	clippy::new_without_default
 )]
 
+mod crypto;
 mod eth;
 mod eth_pubsub;
+mod gas_price_oracle;
+mod logs_cache;
 mod net;
+mod overlay;
 mod overrides;
 mod signer;
+#[cfg(test)]
+mod test_utils;
 mod web3;
 
 pub use self::{
-	eth::{format, EstimateGasAdapter, Eth, EthBlockDataCacheTask, EthFilter, EthTask},
-	eth_pubsub::{EthPubSub, EthereumSubIdProvider},
+	eth::{
+		format, EstimateGasAdapter, Eth, EthBlockDataCacheTask, EthConfig, EthFilter, EthTask,
+		LinearPrecompileCost,
+	},
+	eth_pubsub::{EthPubSub, EthereumSubIdProvider, SequentialSubIdProvider},
+	gas_price_oracle::{GasPriceContext, GasPriceOracle, PercentileGasPriceOracle},
+	logs_cache::LogsCache,
 	net::Net,
+	overlay::{GasUsage, OverlayBackend},
 	overrides::{
 		OverrideHandle, RuntimeApiStorageOverride, SchemaV1Override, SchemaV2Override,
 		SchemaV3Override, StorageOverride,
 	},
-	signer::{EthDevSigner, EthSigner},
+	signer::{AsyncEthSigner, EthDevSigner, EthKeystoreSigner, EthSigner},
 	web3::Web3,
 };
 pub use ethereum::TransactionV2 as EthereumTransaction;
@@ -49,10 +61,12 @@ pub use fc_rpc_core::{
 };
 
 pub mod frontier_backend_client {
-	use super::internal_err;
+	use super::{internal_err, OverrideHandle};
+
+	use std::collections::BTreeMap;
 
 	use codec::Decode;
-	use ethereum_types::H256;
+	use ethereum_types::{H256, U256};
 	use jsonrpsee::core::RpcResult;
 	// Substrate
 	use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
@@ -63,7 +77,7 @@ pub mod frontier_backend_client {
 	};
 	use sp_storage::StorageKey;
 	// Frontier
-	use fc_rpc_core::types::BlockNumber;
+	use fc_rpc_core::types::{ArchiveHint, BlockNumber};
 	use fp_storage::{EthereumStorageSchema, PALLET_ETHEREUM_SCHEMA};
 
 	pub fn native_block_id<B: BlockT, C>(
@@ -85,6 +99,11 @@ pub mod frontier_backend_client {
 			BlockNumber::Pending => None,
 			BlockNumber::Safe => Some(BlockId::Hash(client.info().finalized_hash)),
 			BlockNumber::Finalized => Some(BlockId::Hash(client.info().finalized_hash)),
+			BlockNumber::Timestamp(_) => {
+				return Err(internal_err(
+					"block timestamps are only supported by eth_getLogs",
+				))
+			}
 		})
 	}
 
@@ -159,6 +178,115 @@ pub mod frontier_backend_client {
 		}
 	}
 
+	/// Fails with a dedicated error when the state of block `at` can no longer
+	/// be read, typically because the node pruned it, instead of letting the
+	/// read fall back to empty values. The error data is an `ArchiveHint` when
+	/// the state of a later block is still available.
+	pub fn ensure_state_available<B: BlockT, C, BE>(client: &C, at: BlockId<B>) -> RpcResult<()>
+	where
+		B: BlockT<Hash = H256> + Send + Sync + 'static,
+		C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+		BE: Backend<B> + 'static,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		if state_available::<B, C, BE>(client, at) {
+			return Ok(());
+		}
+		let number = client.block_number_from_id(&at).ok().flatten();
+		let message = format!(
+			"state for block {} is unavailable, it may have been pruned",
+			number.map_or_else(|| at.to_string(), |number| number.to_string())
+		);
+		let hint = number
+			.and_then(|number| {
+				earliest_available_state::<B, C, BE>(client, number.unique_saturated_into())
+			})
+			.map(|earliest| {
+				jsonrpsee::core::to_json_raw_value(&ArchiveHint::new(U256::from(earliest)))
+					.expect("fail to serialize data")
+			});
+		Err(jsonrpsee::core::Error::Call(
+			jsonrpsee::types::error::CallError::Custom(
+				jsonrpsee::types::error::ErrorObject::owned(
+					jsonrpsee::types::error::INTERNAL_ERROR_CODE,
+					message,
+					hint,
+				),
+			),
+		))
+	}
+
+	fn state_available<B: BlockT, C, BE>(client: &C, at: BlockId<B>) -> bool
+	where
+		B: BlockT<Hash = H256> + Send + Sync + 'static,
+		C: StorageProvider<B, BE> + Send + Sync + 'static,
+		BE: Backend<B> + 'static,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		// Any key works, a missing one still reads as `None` on available state.
+		client
+			.storage(&at, &StorageKey(PALLET_ETHEREUM_SCHEMA.to_vec()))
+			.is_ok()
+	}
+
+	/// First canonical block after `pruned` with its state available, if any. States
+	/// are pruned oldest first, so the available ones are found by bisection.
+	fn earliest_available_state<B: BlockT, C, BE>(client: &C, pruned: u64) -> Option<u64>
+	where
+		B: BlockT<Hash = H256> + Send + Sync + 'static,
+		C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+		BE: Backend<B> + 'static,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		let available = |number: u64| match client.hash(number.unique_saturated_into()) {
+			Ok(Some(hash)) => state_available::<B, C, BE>(client, BlockId::Hash(hash)),
+			_ => false,
+		};
+		let mut low = pruned.saturating_add(1);
+		let mut high: u64 = client.info().best_number.unique_saturated_into();
+		if low > high || !available(high) {
+			return None;
+		}
+		while low < high {
+			let middle = low + (high - low) / 2;
+			if available(middle) {
+				high = middle;
+			} else {
+				low = middle + 1;
+			}
+		}
+		Some(low)
+	}
+
+	/// Ethereum block hashes of the up to 256 blocks preceding `number`, the
+	/// ones a `BLOCKHASH` executed in block `number` can read.
+	pub fn recent_block_hashes<B: BlockT, C, BE>(
+		client: &C,
+		overrides: &OverrideHandle<B>,
+		number: u64,
+	) -> BTreeMap<U256, H256>
+	where
+		B: BlockT<Hash = H256> + Send + Sync + 'static,
+		C: StorageProvider<B, BE> + Send + Sync + 'static,
+		BE: Backend<B> + 'static,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		let mut hashes = BTreeMap::new();
+		for n in number.saturating_sub(256)..number {
+			let id = BlockId::Number(n.unique_saturated_into());
+			let schema = onchain_storage_schema::<B, C, BE>(client, id);
+			let block = overrides
+				.schemas
+				.get(&schema)
+				.unwrap_or(&overrides.fallback)
+				.current_block(&id);
+			if let Some(block) = block {
+				hashes.insert(U256::from(n), block.header.hash());
+			}
+		}
+		hashes
+	}
+
 	pub fn is_canon<B: BlockT, C>(client: &C, target_hash: H256) -> bool
 	where
 		B: BlockT<Hash = H256> + Send + Sync + 'static,
@@ -172,6 +300,12 @@ pub mod frontier_backend_client {
 		false
 	}
 
+	/// Returns the Ethereum block hash and index of the block including
+	/// `transaction_hash`.
+	///
+	/// With `only_canonical`, inclusions in blocks that were reorged out are ignored,
+	/// so a transaction that is not re-included on the canonical chain is unknown
+	/// (pending again) rather than answered from a stale block.
 	pub fn load_transactions<B: BlockT, C>(
 		client: &C,
 		backend: &fc_db::Backend<B>,
@@ -206,13 +340,16 @@ pub mod frontier_backend_client {
 	}
 }
 
+/// EIP-1474 error code of methods the node does not support.
+pub(crate) const METHOD_NOT_SUPPORTED: i32 = -32004;
+
 pub fn err<T: ToString>(code: i32, message: T, data: Option<&[u8]>) -> jsonrpsee::core::Error {
 	jsonrpsee::core::Error::Call(jsonrpsee::types::error::CallError::Custom(
 		jsonrpsee::types::error::ErrorObject::owned(
 			code,
 			message.to_string(),
 			data.map(|bytes| {
-				jsonrpsee::core::to_json_raw_value(&format!("0x{}", hex::encode(bytes)))
+				jsonrpsee::core::to_json_raw_value(&fc_rpc_core::types::format_hex(bytes))
 					.expect("fail to serialize data")
 			}),
 		),
@@ -257,6 +394,34 @@ pub fn public_key(transaction: &EthereumTransaction) -> Result<[u8; 64], sp_io::
 	sp_io::crypto::secp256k1_ecdsa_recover(&sig, &msg)
 }
 
+/// Formats the logs of a transaction, `first_log_index` being the number of logs
+/// of the preceding transactions of the block. `logIndex` counts the logs of the
+/// whole block while `transactionLogIndex` counts the ones of the transaction.
+pub(crate) fn transaction_logs(
+	block_hash: ethereum_types::H256,
+	block_number: ethereum_types::U256,
+	transaction_hash: ethereum_types::H256,
+	transaction_index: u32,
+	first_log_index: u32,
+	logs: &[ethereum::Log],
+) -> Vec<fc_rpc_core::types::Log> {
+	logs.iter()
+		.enumerate()
+		.map(|(i, log)| fc_rpc_core::types::Log {
+			address: log.address,
+			topics: log.topics.clone(),
+			data: fc_rpc_core::types::Bytes(log.data.clone()),
+			block_hash: Some(block_hash),
+			block_number: Some(block_number),
+			transaction_hash: Some(transaction_hash),
+			transaction_index: Some(transaction_index.into()),
+			log_index: Some((first_log_index + i as u32).into()),
+			transaction_log_index: Some(i.into()),
+			removed: false,
+		})
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use std::{path::PathBuf, sync::Arc};
@@ -395,4 +560,157 @@ mod tests {
 			BlockId::Hash(b2_hash),
 		);
 	}
+
+	#[test]
+	fn reorged_out_transaction_is_not_canonical() {
+		use sp_blockchain::HeaderBackend;
+
+		let tmp = tempdir().expect("create a temporary directory");
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+
+		let mut client = Arc::new(client);
+
+		// Create a temporary frontier secondary DB.
+		let frontier_backend = open_frontier_backend(client.clone(), tmp.into_path()).unwrap();
+
+		let ethereum_block_hash = sp_core::H256::random();
+		let ethereum_transaction_hash = sp_core::H256::random();
+
+		// G -> A1, including the transaction.
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(vec![1], None).unwrap();
+		let a1 = builder.build().unwrap().block;
+		let a1_hash = a1.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, a1)).unwrap();
+
+		let commitment = fc_db::MappingCommitment::<OpaqueBlock> {
+			block_hash: a1_hash,
+			ethereum_block_hash,
+			ethereum_transaction_hashes: vec![ethereum_transaction_hash],
+		};
+		let _ = frontier_backend.mapping().write_hashes(commitment);
+
+		// Mined in A1.
+		assert_eq!(
+			super::frontier_backend_client::load_transactions(
+				client.as_ref(),
+				frontier_backend.as_ref(),
+				ethereum_transaction_hash,
+				true,
+			)
+			.unwrap(),
+			Some((ethereum_block_hash, 0)),
+		);
+
+		// G -> B1 -> B2, not including the transaction. B branch is now canon.
+		let mut parent_hash = client.info().genesis_hash;
+		for change in [2, 3] {
+			let mut builder = client
+				.new_block_at(&BlockId::Hash(parent_hash), Default::default(), false)
+				.unwrap();
+			builder.push_storage_change(vec![change], None).unwrap();
+			let block = builder.build().unwrap().block;
+			parent_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+		assert_eq!(client.info().best_hash, parent_hash);
+
+		// The stale inclusion is no longer reported, the transaction is pending again.
+		assert_eq!(
+			super::frontier_backend_client::load_transactions(
+				client.as_ref(),
+				frontier_backend.as_ref(),
+				ethereum_transaction_hash,
+				true,
+			)
+			.unwrap(),
+			None,
+		);
+		assert_eq!(
+			super::frontier_backend_client::load_hash(
+				client.as_ref(),
+				frontier_backend.as_ref(),
+				ethereum_block_hash
+			)
+			.unwrap(),
+			None,
+		);
+		// Unless non-canonical inclusions are explicitly asked for.
+		assert_eq!(
+			super::frontier_backend_client::load_transactions(
+				client.as_ref(),
+				frontier_backend.as_ref(),
+				ethereum_transaction_hash,
+				false,
+			)
+			.unwrap(),
+			Some((ethereum_block_hash, 0)),
+		);
+	}
+
+	#[test]
+	fn pruned_state_is_reported() {
+		use sp_blockchain::HeaderBackend;
+
+		// Only the state of the last finalized block is kept.
+		let (client, _) = TestClientBuilder::with_pruning_window(1)
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+
+		let mut client = Arc::new(client);
+		let genesis_hash = client.info().genesis_hash;
+
+		for change in [1, 2, 3] {
+			let mut builder = client.new_block(Default::default()).unwrap();
+			builder.push_storage_change(vec![change], None).unwrap();
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import_as_final(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// The latest state, the one balances are read from by default, is available.
+		assert!(
+			super::frontier_backend_client::ensure_state_available::<OpaqueBlock, _, _>(
+				client.as_ref(),
+				BlockId::Hash(client.info().best_hash),
+			)
+			.is_ok()
+		);
+
+		// The genesis state was pruned, reading a balance from it fails loudly.
+		let err = super::frontier_backend_client::ensure_state_available::<OpaqueBlock, _, _>(
+			client.as_ref(),
+			BlockId::Hash(genesis_hash),
+		)
+		.unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("state for block 0 is unavailable, it may have been pruned"));
+
+		// Along with the first block an archive node is not needed for.
+		let data = match err {
+			jsonrpsee::core::Error::Call(jsonrpsee::types::error::CallError::Custom(err)) => {
+				err.data().map(|data| data.get().to_string())
+			}
+			_ => None,
+		}
+		.unwrap();
+		let earliest = data
+			.strip_prefix(r#"{"requiresArchive":true,"earliestAvailableBlock":""#)
+			.and_then(|data| data.strip_suffix(r#""}"#))
+			.and_then(|earliest| u64::from_str_radix(earliest.trim_start_matches("0x"), 16).ok())
+			.unwrap();
+		assert!(earliest > 0 && earliest <= 3);
+		let state_at = |number: u64| {
+			super::frontier_backend_client::ensure_state_available::<OpaqueBlock, _, _>(
+				client.as_ref(),
+				BlockId::Number(number),
+			)
+		};
+		assert!(state_at(earliest).is_ok());
+		assert!(state_at(earliest - 1).is_err());
+	}
 }
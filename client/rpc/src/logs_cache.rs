@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+	num::NonZeroUsize,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+use ethereum_types::H256;
+use lru::LruCache;
+// Frontier
+use fc_rpc_core::types::{Filter, Log};
+
+/// Short lived cache of the `eth_getLogs` results of block ranges.
+///
+/// Entries are keyed by the filter and the range scanned, the least recently used
+/// ones being evicted past `capacity` entries. Only ranges ending at a finalized
+/// block should be inserted; the hash of the last block of the range is checked on
+/// every lookup nonetheless, so that a reorg past it drops the entry rather than
+/// serving stale logs.
+pub struct LogsCache {
+	ttl: Duration,
+	entries: Mutex<LruCache<(Filter, u64, u64), Entry>>,
+}
+
+struct Entry {
+	inserted: Instant,
+	to_hash: H256,
+	logs: Vec<Log>,
+}
+
+impl LogsCache {
+	pub fn new(ttl: Duration, capacity: usize) -> Self {
+		let capacity = NonZeroUsize::new(capacity.max(1)).expect("capacity is not zero; qed");
+		Self {
+			ttl,
+			entries: Mutex::new(LruCache::new(capacity)),
+		}
+	}
+
+	/// Logs cached for `filter` over `from..=to`, `to_hash` being the current hash of `to`.
+	pub fn get(&self, filter: &Filter, from: u64, to: u64, to_hash: H256) -> Option<Vec<Log>> {
+		self.get_at(filter, from, to, to_hash, Instant::now())
+	}
+
+	/// Cache the logs matching `filter` over `from..=to`, `to_hash` being the hash of `to`.
+	pub fn insert(&self, filter: &Filter, from: u64, to: u64, to_hash: H256, logs: Vec<Log>) {
+		self.insert_at(filter, from, to, to_hash, logs, Instant::now())
+	}
+
+	fn get_at(
+		&self,
+		filter: &Filter,
+		from: u64,
+		to: u64,
+		to_hash: H256,
+		now: Instant,
+	) -> Option<Vec<Log>> {
+		let mut entries = self.entries.lock().ok()?;
+		let key = (filter.clone(), from, to);
+		let entry = entries.get(&key)?;
+		if entry.to_hash != to_hash || now.saturating_duration_since(entry.inserted) >= self.ttl {
+			entries.pop(&key);
+			return None;
+		}
+		Some(entry.logs.clone())
+	}
+
+	fn insert_at(
+		&self,
+		filter: &Filter,
+		from: u64,
+		to: u64,
+		to_hash: H256,
+		logs: Vec<Log>,
+		now: Instant,
+	) {
+		if let Ok(mut entries) = self.entries.lock() {
+			// Drop expired entries rather than keeping their logs until evicted.
+			let expired: Vec<_> = entries
+				.iter()
+				.filter(|(_, entry)| now.saturating_duration_since(entry.inserted) >= self.ttl)
+				.map(|(key, _)| key.clone())
+				.collect();
+			for key in expired {
+				entries.pop(&key);
+			}
+			entries.put(
+				(filter.clone(), from, to),
+				Entry {
+					inserted: now,
+					to_hash,
+					logs,
+				},
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use fc_rpc_core::types::{Bytes, VariadicValue};
+
+	fn address_filter(address: Option<VariadicValue<ethereum_types::H160>>) -> Filter {
+		Filter {
+			from_block: None,
+			to_block: None,
+			block_hash: None,
+			transaction_hash: None,
+			address,
+			topics: None,
+		}
+	}
+
+	fn log(block_number: u64) -> Log {
+		Log {
+			address: Default::default(),
+			topics: Vec::new(),
+			data: Bytes(Vec::new()),
+			block_hash: Some(H256::from_low_u64_be(block_number)),
+			block_number: Some(block_number.into()),
+			transaction_hash: None,
+			transaction_index: None,
+			log_index: Some(0.into()),
+			transaction_log_index: None,
+			removed: false,
+		}
+	}
+
+	/// Mirrors the cached scan of `eth_getLogs`, counting the scans reaching the blocks.
+	fn query(
+		cache: &LogsCache,
+		scans: &mut u32,
+		filter: &Filter,
+		to_hash: H256,
+		now: Instant,
+	) -> Vec<Log> {
+		if let Some(logs) = cache.get_at(filter, 1, 2, to_hash, now) {
+			return logs;
+		}
+		*scans += 1;
+		let logs = vec![log(1), log(2)];
+		cache.insert_at(filter, 1, 2, to_hash, logs.clone(), now);
+		logs
+	}
+
+	#[test]
+	fn repeated_queries_hit_the_cache() {
+		let cache = LogsCache::new(Duration::from_secs(10), 8);
+		let filter = address_filter(None);
+		let to_hash = H256::repeat_byte(2);
+		let start = Instant::now();
+		let mut scans = 0;
+
+		let first = query(&cache, &mut scans, &filter, to_hash, start);
+		let second = query(
+			&cache,
+			&mut scans,
+			&filter,
+			to_hash,
+			start + Duration::from_secs(1),
+		);
+		assert_eq!(first, second);
+		assert_eq!(scans, 1);
+
+		// Another filter is scanned on its own.
+		let other = address_filter(Some(VariadicValue::Single(Default::default())));
+		query(
+			&cache,
+			&mut scans,
+			&other,
+			to_hash,
+			start + Duration::from_secs(1),
+		);
+		assert_eq!(scans, 2);
+
+		// A reorg replacing the last block of the range invalidates the entry.
+		query(
+			&cache,
+			&mut scans,
+			&filter,
+			H256::repeat_byte(3),
+			start + Duration::from_secs(2),
+		);
+		assert_eq!(scans, 3);
+
+		// So does the end of its time to live.
+		query(
+			&cache,
+			&mut scans,
+			&filter,
+			H256::repeat_byte(3),
+			start + Duration::from_secs(12),
+		);
+		assert_eq!(scans, 4);
+	}
+
+	#[test]
+	fn least_recently_used_entries_are_evicted() {
+		let cache = LogsCache::new(Duration::from_secs(10), 2);
+		let to_hash = H256::repeat_byte(2);
+		let start = Instant::now();
+		let mut scans = 0;
+		let filters: Vec<Filter> = (0..3u64)
+			.map(|index| {
+				address_filter(Some(VariadicValue::Single(
+					ethereum_types::H160::from_low_u64_be(index),
+				)))
+			})
+			.collect();
+
+		query(&cache, &mut scans, &filters[0], to_hash, start);
+		query(&cache, &mut scans, &filters[1], to_hash, start);
+		// Used again, the first filter is now the most recent entry.
+		query(&cache, &mut scans, &filters[0], to_hash, start);
+		assert_eq!(scans, 2);
+
+		// Past the capacity, the second filter is evicted.
+		query(&cache, &mut scans, &filters[2], to_hash, start);
+		assert_eq!(scans, 3);
+		query(&cache, &mut scans, &filters[0], to_hash, start);
+		assert_eq!(scans, 3);
+		query(&cache, &mut scans, &filters[1], to_hash, start);
+		assert_eq!(scans, 4);
+	}
+}
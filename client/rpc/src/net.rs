@@ -27,7 +27,10 @@ use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 // Frontier
-use fc_rpc_core::{types::PeerCount, NetApiServer};
+use fc_rpc_core::{
+	types::{format_quantity, PeerCount},
+	NetApiServer,
+};
 use fp_rpc::EthereumRuntimeRPCApi;
 
 use crate::internal_err;
@@ -72,7 +75,7 @@ where
 	fn peer_count(&self) -> Result<PeerCount> {
 		let peer_count = self.network.sync_num_connected();
 		Ok(match self.peer_count_as_hex {
-			true => PeerCount::String(format!("0x{:x}", peer_count)),
+			true => PeerCount::String(format_quantity(peer_count as u64)),
 			false => PeerCount::U32(peer_count as u32),
 		})
 	}
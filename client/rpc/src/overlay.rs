@@ -0,0 +1,1114 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ethereum_types::{H160, H256, U256};
+use evm::{
+	backend::{Apply, ApplyBackend, Backend, Basic, Log},
+	executor::stack::{
+		MemoryStackState, PrecompileSet, StackExecutor, StackState, StackSubstateMetadata,
+	},
+	Config, ExitReason,
+};
+// Frontier
+use fc_rpc_core::types::{BlockOverrides, CallRequest, CallStateOverride};
+
+/// Changes recorded on top of a single account of the inner backend.
+#[derive(Clone, Debug, Default)]
+struct OverlayAccount {
+	basic: Option<Basic>,
+	code: Option<Vec<u8>>,
+	storage: BTreeMap<H256, H256>,
+	/// Storage slots not present in `storage` read as zero instead of
+	/// falling through to the inner backend.
+	reset_storage: bool,
+	deleted: bool,
+}
+
+/// An EVM backend reading through to an inner backend, with all writes kept
+/// in an in-memory overlay.
+///
+/// The inner backend is never modified, so the same chain state can be shared
+/// by call, estimate and simulation paths that each need their own scratch
+/// state (state overrides, pending transactions, bundles).
+pub struct OverlayBackend<B> {
+	inner: B,
+	environment: OverlayEnvironment,
+	accounts: BTreeMap<H160, OverlayAccount>,
+	logs: Vec<Log>,
+	assume_sender_balance: bool,
+	/// Sender read with `ASSUMED_SENDER_BALANCE` while `transact` executes, without
+	/// an overlay entry so its existence is the actual one.
+	funded_sender: Option<H160>,
+}
+
+/// Balance senders are topped up to by `OverlayBackend::transact`, leaving
+/// room for the transferred value to be credited without overflowing.
+const ASSUMED_SENDER_BALANCE: U256 = U256([u64::MAX, u64::MAX, u64::MAX, 0]);
+
+/// Gas of a transaction executed by `OverlayBackend::transact_with_gas_usage`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasUsage {
+	/// Base cost of the transaction and of its calldata and access list.
+	pub intrinsic: u64,
+	/// Gas spent executing, before refunds.
+	pub execution: u64,
+	/// Refund applied once the execution ended.
+	pub refund: u64,
+}
+
+impl GasUsage {
+	/// Gas used by the transaction, as charged to its sender.
+	pub fn total(&self) -> u64 {
+		(self.intrinsic + self.execution).saturating_sub(self.refund)
+	}
+}
+
+/// Execution environment values taking precedence over the inner backend's.
+#[derive(Clone, Debug, Default)]
+pub struct OverlayEnvironment {
+	/// Gas price of the executed transaction, read by `GASPRICE`.
+	pub gas_price: Option<U256>,
+	/// Sender of the executed transaction, read by `ORIGIN`.
+	pub origin: Option<H160>,
+	pub block_number: Option<U256>,
+	pub block_coinbase: Option<H160>,
+	/// Block timestamp, in seconds.
+	pub block_timestamp: Option<U256>,
+	pub block_difficulty: Option<U256>,
+	pub block_gas_limit: Option<U256>,
+	/// Base fee of the block, read by `BASEFEE`.
+	pub block_base_fee_per_gas: Option<U256>,
+	/// Hashes of the blocks preceding the executed one, by number. Once any
+	/// is given, numbers not listed read as zero.
+	pub block_hashes: BTreeMap<U256, H256>,
+	/// Chain id read by `CHAINID`.
+	pub chain_id: Option<U256>,
+}
+
+impl OverlayEnvironment {
+	/// Environment of a transaction built from a call request.
+	pub fn for_request(request: &CallRequest) -> Self {
+		Self {
+			gas_price: request.gas_price,
+			origin: Some(request.from.unwrap_or_default()),
+			..Default::default()
+		}
+	}
+
+	/// Takes the block fields from the header of the block executed on.
+	pub fn with_header(self, header: &ethereum::Header) -> Self {
+		Self {
+			block_number: Some(header.number),
+			block_coinbase: Some(header.beneficiary),
+			// Header timestamps are stored in milliseconds.
+			block_timestamp: Some(U256::from(header.timestamp / 1000)),
+			block_difficulty: Some(header.difficulty),
+			block_gas_limit: Some(header.gas_limit),
+			..self
+		}
+	}
+
+	/// Applies the block context overrides of a call, e.g. simulating it at a
+	/// later timestamp.
+	pub fn with_block_overrides(self, overrides: &BlockOverrides) -> Self {
+		Self {
+			block_number: overrides.number.or(self.block_number),
+			block_timestamp: overrides.time.or(self.block_timestamp),
+			block_base_fee_per_gas: overrides.base_fee.or(self.block_base_fee_per_gas),
+			..self
+		}
+	}
+
+	/// Sets the chain id configured for the node.
+	pub fn with_chain_id(self, chain_id: u64) -> Self {
+		Self {
+			chain_id: Some(U256::from(chain_id)),
+			..self
+		}
+	}
+
+	/// Sets the hashes `BLOCKHASH` can read, see
+	/// `frontier_backend_client::recent_block_hashes`.
+	pub fn with_block_hashes(self, block_hashes: BTreeMap<U256, H256>) -> Self {
+		Self {
+			block_hashes,
+			..self
+		}
+	}
+}
+
+impl<B: Backend> OverlayBackend<B> {
+	pub fn new(inner: B) -> Self {
+		Self {
+			inner,
+			environment: Default::default(),
+			accounts: BTreeMap::new(),
+			logs: Vec::new(),
+			assume_sender_balance: true,
+			funded_sender: None,
+		}
+	}
+
+	/// The backend the overlay reads through to.
+	pub fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Overrides the execution environment.
+	pub fn set_environment(&mut self, environment: OverlayEnvironment) {
+		self.environment = environment;
+	}
+
+	/// Whether `transact` executes as if the sender could afford any value
+	/// and fee, as geth does for calls. Enabled by default.
+	pub fn set_assume_sender_balance(&mut self, enabled: bool) {
+		self.assume_sender_balance = enabled;
+	}
+
+	/// Logs emitted by all changes applied to the overlay so far.
+	pub fn logs(&self) -> &[Log] {
+		&self.logs
+	}
+
+	/// Overrides the balance of `address`.
+	pub fn set_balance(&mut self, address: H160, balance: U256) {
+		let mut basic = self.basic(address);
+		basic.balance = balance;
+		self.account_mut(address).basic = Some(basic);
+	}
+
+	/// Overrides the nonce of `address`.
+	pub fn set_nonce(&mut self, address: H160, nonce: U256) {
+		let mut basic = self.basic(address);
+		basic.nonce = nonce;
+		self.account_mut(address).basic = Some(basic);
+	}
+
+	/// Overrides the code of `address`.
+	pub fn set_code(&mut self, address: H160, code: Vec<u8>) {
+		self.account_mut(address).code = Some(code);
+	}
+
+	/// Overrides a single storage slot of `address`.
+	pub fn set_storage(&mut self, address: H160, index: H256, value: H256) {
+		self.account_mut(address).storage.insert(index, value);
+	}
+
+	/// Replaces the whole storage of `address`, slots not given read as zero.
+	pub fn replace_storage(&mut self, address: H160, storage: BTreeMap<H256, H256>) {
+		let account = self.account_mut(address);
+		account.storage = storage;
+		account.reset_storage = true;
+	}
+
+	/// Applies the state overrides of a call to `address`. Its storage is replaced
+	/// by `state` when given, and only patched by `state_diff` otherwise.
+	pub fn apply_state_override(&mut self, address: H160, state_override: &CallStateOverride) {
+		if let Some(balance) = state_override.balance {
+			self.set_balance(address, balance);
+		}
+		if let Some(nonce) = state_override.nonce {
+			self.set_nonce(address, nonce);
+		}
+		if let Some(code) = &state_override.code {
+			self.set_code(address, code.clone().into_vec());
+		}
+		if let Some(state) = &state_override.state {
+			self.replace_storage(address, state.clone());
+		} else if let Some(state_diff) = &state_override.state_diff {
+			for (index, value) in state_diff {
+				self.set_storage(address, *index, *value);
+			}
+		}
+	}
+
+	/// Executes `request` with `gas_limit` on top of the overlay, keeping its
+	/// changes when it succeeds. Returns the exit reason, the returned data and
+	/// the gas used.
+	///
+	/// Unless disabled with `set_assume_sender_balance`, the sender, the zero
+	/// address when `from` is omitted, is treated as funded for the execution.
+	///
+	/// Precompiles are supplied by the caller, so chains adding their own can
+	/// simulate with the same set their runtime executes with.
+	pub fn transact<P: PrecompileSet>(
+		&mut self,
+		config: &Config,
+		precompiles: &P,
+		request: &CallRequest,
+		gas_limit: u64,
+	) -> (ExitReason, Vec<u8>, u64) {
+		let (exit_reason, data, gas_usage) =
+			self.transact_with_gas_usage(config, precompiles, request, gas_limit);
+		(exit_reason, data, gas_usage.total())
+	}
+
+	/// Same as `transact`, breaking the gas used down into its intrinsic,
+	/// execution and refunded parts.
+	pub fn transact_with_gas_usage<P: PrecompileSet>(
+		&mut self,
+		config: &Config,
+		precompiles: &P,
+		request: &CallRequest,
+		gas_limit: u64,
+	) -> (ExitReason, Vec<u8>, GasUsage) {
+		let from = request.from.unwrap_or_default();
+		let value = request.value.unwrap_or_default();
+		let data = request
+			.data
+			.clone()
+			.map(|data| data.into_vec())
+			.unwrap_or_default();
+		let access_list = request
+			.access_list
+			.clone()
+			.unwrap_or_default()
+			.into_iter()
+			.map(|item| (item.address, item.storage_keys))
+			.collect();
+
+		let balance = self.basic(from).balance;
+		let assume_balance = self.assume_sender_balance && balance < ASSUMED_SENDER_BALANCE;
+		let existed = self.exists(from);
+		let previous = self.accounts.get(&from).cloned();
+		if assume_balance {
+			self.funded_sender = Some(from);
+		}
+
+		let intrinsic = intrinsic_gas(config, &data, &access_list, request.to.is_none());
+		let metadata = StackSubstateMetadata::new(gas_limit, config);
+		let state = MemoryStackState::new(metadata, &*self);
+		let mut executor = StackExecutor::new_with_precompiles(state, config, precompiles);
+		let (exit_reason, data) = match request.to {
+			Some(to) => executor.transact_call(from, to, value, data, gas_limit, access_list),
+			None => executor.transact_create(from, value, data, gas_limit, access_list),
+		};
+		let used_gas = executor.used_gas();
+		let total_used_gas = executor.state().metadata().gasometer().total_used_gas();
+		// A transaction failing its intrinsic cost does not execute at all.
+		let intrinsic = intrinsic.min(total_used_gas);
+		let gas_usage = GasUsage {
+			intrinsic,
+			execution: total_used_gas - intrinsic,
+			refund: total_used_gas.saturating_sub(used_gas),
+		};
+
+		if exit_reason.is_succeed() {
+			let (values, logs) = executor.into_state().deconstruct();
+			self.apply(values, logs, true);
+		} else {
+			// Ends the borrow of the overlay by the executor.
+			drop(executor);
+		}
+		if assume_balance {
+			self.funded_sender = None;
+			if exit_reason.is_succeed() {
+				self.restore_sender_balance(from, balance, existed, previous);
+			}
+		}
+		(exit_reason, data, gas_usage)
+	}
+
+	/// Moves the actual `balance` of a funded sender by what its assumed balance
+	/// gained or lost executing, floored at zero. A sender that did not exist and
+	/// ends up empty is put back as it was.
+	fn restore_sender_balance(
+		&mut self,
+		from: H160,
+		balance: U256,
+		existed: bool,
+		previous: Option<OverlayAccount>,
+	) {
+		let assumed = self.basic(from).balance;
+		let balance = if assumed >= ASSUMED_SENDER_BALANCE {
+			balance.saturating_add(assumed - ASSUMED_SENDER_BALANCE)
+		} else {
+			balance.saturating_sub(ASSUMED_SENDER_BALANCE - assumed)
+		};
+		self.set_balance(from, balance);
+
+		if !existed && self.basic(from) == Basic::default() && self.code(from).is_empty() {
+			match previous {
+				Some(account) => self.accounts.insert(from, account),
+				None => self.accounts.remove(&from),
+			};
+		}
+	}
+
+	/// Returns the overlay entry of `address`, reviving it if it was deleted.
+	fn account_mut(&mut self, address: H160) -> &mut OverlayAccount {
+		let account = self.accounts.entry(address).or_default();
+		if account.deleted {
+			*account = OverlayAccount {
+				basic: Some(Basic::default()),
+				code: Some(Vec::new()),
+				reset_storage: true,
+				..Default::default()
+			};
+		}
+		account
+	}
+}
+
+/// Gas charged for a transaction before it executes: its base cost, the cost of
+/// its calldata (`data`) and of its access list.
+pub(crate) fn intrinsic_gas(
+	config: &Config,
+	data: &[u8],
+	access_list: &[(H160, Vec<H256>)],
+	is_create: bool,
+) -> u64 {
+	let base = if is_create {
+		config.gas_transaction_create
+	} else {
+		config.gas_transaction_call
+	};
+	let zero_bytes = data.iter().filter(|byte| **byte == 0).count() as u64;
+	let non_zero_bytes = data.len() as u64 - zero_bytes;
+	let storage_keys = access_list
+		.iter()
+		.map(|(_, keys)| keys.len() as u64)
+		.sum::<u64>();
+	base + zero_bytes * config.gas_transaction_zero_data
+		+ non_zero_bytes * config.gas_transaction_non_zero_data
+		+ access_list.len() as u64 * config.gas_access_list_address
+		+ storage_keys * config.gas_access_list_storage_key
+}
+
+impl<B: Backend> Backend for OverlayBackend<B> {
+	fn gas_price(&self) -> U256 {
+		self.environment
+			.gas_price
+			.unwrap_or_else(|| self.inner.gas_price())
+	}
+	fn origin(&self) -> H160 {
+		self.environment
+			.origin
+			.unwrap_or_else(|| self.inner.origin())
+	}
+	fn block_hash(&self, number: U256) -> H256 {
+		if self.environment.block_hashes.is_empty() {
+			return self.inner.block_hash(number);
+		}
+		// Only the 256 most recent blocks are available.
+		let current = self.block_number();
+		if number >= current || current - number > U256::from(256u64) {
+			return H256::default();
+		}
+		self.environment
+			.block_hashes
+			.get(&number)
+			.copied()
+			.unwrap_or_default()
+	}
+	fn block_number(&self) -> U256 {
+		self.environment
+			.block_number
+			.unwrap_or_else(|| self.inner.block_number())
+	}
+	fn block_coinbase(&self) -> H160 {
+		self.environment
+			.block_coinbase
+			.unwrap_or_else(|| self.inner.block_coinbase())
+	}
+	fn block_timestamp(&self) -> U256 {
+		self.environment
+			.block_timestamp
+			.unwrap_or_else(|| self.inner.block_timestamp())
+	}
+	fn block_difficulty(&self) -> U256 {
+		self.environment
+			.block_difficulty
+			.unwrap_or_else(|| self.inner.block_difficulty())
+	}
+	fn block_gas_limit(&self) -> U256 {
+		self.environment
+			.block_gas_limit
+			.unwrap_or_else(|| self.inner.block_gas_limit())
+	}
+	fn block_base_fee_per_gas(&self) -> U256 {
+		self.environment
+			.block_base_fee_per_gas
+			.unwrap_or_else(|| self.inner.block_base_fee_per_gas())
+	}
+	fn chain_id(&self) -> U256 {
+		self.environment
+			.chain_id
+			.unwrap_or_else(|| self.inner.chain_id())
+	}
+
+	fn exists(&self, address: H160) -> bool {
+		match self.accounts.get(&address) {
+			Some(account) => !account.deleted,
+			None => self.inner.exists(address),
+		}
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		let mut basic = match self.accounts.get(&address) {
+			Some(account) if account.deleted => Basic::default(),
+			Some(OverlayAccount {
+				basic: Some(basic), ..
+			}) => basic.clone(),
+			_ => self.inner.basic(address),
+		};
+		if self.funded_sender == Some(address) {
+			basic.balance = ASSUMED_SENDER_BALANCE;
+		}
+		basic
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		match self.accounts.get(&address) {
+			Some(account) if account.deleted => Vec::new(),
+			Some(OverlayAccount {
+				code: Some(code), ..
+			}) => code.clone(),
+			_ => self.inner.code(address),
+		}
+	}
+
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		match self.accounts.get(&address) {
+			Some(account) if account.deleted => H256::default(),
+			Some(account) => match account.storage.get(&index) {
+				Some(value) => *value,
+				None if account.reset_storage => H256::default(),
+				None => self.inner.storage(address, index),
+			},
+			None => self.inner.storage(address, index),
+		}
+	}
+
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		Some(self.storage(address, index))
+	}
+}
+
+impl<B: Backend> ApplyBackend for OverlayBackend<B> {
+	fn apply<A, I, L>(&mut self, values: A, logs: L, delete_empty: bool)
+	where
+		A: IntoIterator<Item = Apply<I>>,
+		I: IntoIterator<Item = (H256, H256)>,
+		L: IntoIterator<Item = Log>,
+	{
+		for apply in values {
+			match apply {
+				Apply::Modify {
+					address,
+					basic,
+					code,
+					storage,
+					reset_storage,
+				} => {
+					let account = self.account_mut(address);
+					account.basic = Some(basic);
+					if code.is_some() {
+						account.code = code;
+					}
+					if reset_storage {
+						account.storage.clear();
+						account.reset_storage = true;
+					}
+					// Zero values are kept so they keep shadowing the inner backend.
+					account.storage.extend(storage);
+
+					if delete_empty
+						&& self.basic(address) == Basic::default()
+						&& self.code(address).is_empty()
+					{
+						self.accounts.insert(
+							address,
+							OverlayAccount {
+								deleted: true,
+								..Default::default()
+							},
+						);
+					}
+				}
+				Apply::Delete { address } => {
+					self.accounts.insert(
+						address,
+						OverlayAccount {
+							deleted: true,
+							..Default::default()
+						},
+					);
+				}
+			}
+		}
+
+		self.logs.extend(logs);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::vicinity;
+	use evm::{
+		backend::{MemoryAccount, MemoryBackend, MemoryVicinity},
+		ExitError, ExitRevert, ExitSucceed,
+	};
+	use fc_rpc_core::types::Bytes;
+	use fp_evm::{PrecompileHandle, PrecompileOutput, PrecompileResult};
+
+	const CALLER: H160 = H160::repeat_byte(0xca);
+	const CONTRACT: H160 = H160::repeat_byte(0xc0);
+
+	/// Calls `CONTRACT` on `backend`, returning the exit reason, the returned
+	/// data and the gas used.
+	fn call<B: Backend>(backend: &B) -> (ExitReason, Vec<u8>, u64) {
+		let config = Config::london();
+		let gas_limit = 1_000_000;
+		let metadata = StackSubstateMetadata::new(gas_limit, &config);
+		let state = MemoryStackState::new(metadata, backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+		let (exit_reason, data) = executor.transact_call(
+			CALLER,
+			CONTRACT,
+			U256::zero(),
+			Vec::new(),
+			gas_limit,
+			Vec::new(),
+		);
+		(exit_reason, data, executor.used_gas())
+	}
+
+	/// Inner backend holding `code` at `CONTRACT`.
+	fn with_contract(vicinity: &MemoryVicinity, code: Vec<u8>) -> MemoryBackend<'_> {
+		let mut state = state();
+		state.insert(
+			CONTRACT,
+			MemoryAccount {
+				nonce: U256::one(),
+				balance: U256::zero(),
+				storage: BTreeMap::new(),
+				code,
+			},
+		);
+		MemoryBackend::new(vicinity, state)
+	}
+
+	fn state() -> BTreeMap<H160, MemoryAccount> {
+		let mut storage = BTreeMap::new();
+		storage.insert(H256::repeat_byte(1), H256::repeat_byte(0xaa));
+		storage.insert(H256::repeat_byte(2), H256::repeat_byte(0xbb));
+
+		let mut state = BTreeMap::new();
+		state.insert(
+			H160::repeat_byte(1),
+			MemoryAccount {
+				nonce: U256::from(7u64),
+				balance: U256::from(1_000u64),
+				storage,
+				code: vec![0x60, 0x00],
+			},
+		);
+		state
+	}
+
+	#[test]
+	fn reads_through_to_inner_backend() {
+		let vicinity = vicinity();
+		let overlay = OverlayBackend::new(MemoryBackend::new(&vicinity, state()));
+		let address = H160::repeat_byte(1);
+
+		assert!(overlay.exists(address));
+		assert!(!overlay.exists(H160::repeat_byte(2)));
+		assert_eq!(overlay.basic(address), overlay.inner().basic(address));
+		assert_eq!(overlay.code(address), vec![0x60, 0x00]);
+		assert_eq!(
+			overlay.storage(address, H256::repeat_byte(1)),
+			H256::repeat_byte(0xaa)
+		);
+		assert_eq!(overlay.chain_id(), U256::from(42u64));
+	}
+
+	#[test]
+	fn overlay_shadows_inner_backend() {
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(MemoryBackend::new(&vicinity, state()));
+		let address = H160::repeat_byte(1);
+
+		overlay.set_balance(address, U256::from(5u64));
+		overlay.set_code(address, vec![0x00]);
+		overlay.set_storage(address, H256::repeat_byte(1), H256::zero());
+
+		assert_eq!(overlay.basic(address).balance, U256::from(5u64));
+		// Fields not overridden still come from the inner backend.
+		assert_eq!(overlay.basic(address).nonce, U256::from(7u64));
+		assert_eq!(overlay.code(address), vec![0x00]);
+		assert_eq!(overlay.storage(address, H256::repeat_byte(1)), H256::zero());
+		assert_eq!(
+			overlay.storage(address, H256::repeat_byte(2)),
+			H256::repeat_byte(0xbb)
+		);
+
+		overlay.replace_storage(address, BTreeMap::new());
+		assert_eq!(overlay.storage(address, H256::repeat_byte(2)), H256::zero());
+
+		// The inner backend is left untouched.
+		assert_eq!(overlay.inner().basic(address).balance, U256::from(1_000u64));
+		assert_eq!(
+			overlay.inner().storage(address, H256::repeat_byte(1)),
+			H256::repeat_byte(0xaa)
+		);
+	}
+
+	#[test]
+	fn applied_changes_are_read_back() {
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(MemoryBackend::new(&vicinity, state()));
+		let existing = H160::repeat_byte(1);
+		let created = H160::repeat_byte(2);
+
+		overlay.apply(
+			vec![
+				Apply::Modify {
+					address: created,
+					basic: Basic {
+						balance: U256::from(10u64),
+						nonce: U256::one(),
+					},
+					code: Some(vec![0xfe]),
+					storage: vec![(H256::repeat_byte(3), H256::repeat_byte(0xcc))],
+					reset_storage: false,
+				},
+				Apply::Delete { address: existing },
+			],
+			Vec::new(),
+			false,
+		);
+
+		assert!(overlay.exists(created));
+		assert_eq!(overlay.basic(created).balance, U256::from(10u64));
+		assert_eq!(overlay.code(created), vec![0xfe]);
+		assert_eq!(
+			overlay.storage(created, H256::repeat_byte(3)),
+			H256::repeat_byte(0xcc)
+		);
+
+		assert!(!overlay.exists(existing));
+		assert_eq!(overlay.basic(existing), Basic::default());
+		assert!(overlay.code(existing).is_empty());
+		assert_eq!(
+			overlay.storage(existing, H256::repeat_byte(1)),
+			H256::zero()
+		);
+
+		// Writing to a deleted account starts from an empty one.
+		overlay.set_storage(existing, H256::repeat_byte(2), H256::repeat_byte(0xdd));
+		assert!(overlay.exists(existing));
+		assert_eq!(
+			overlay.storage(existing, H256::repeat_byte(1)),
+			H256::zero()
+		);
+		assert_eq!(
+			overlay.storage(existing, H256::repeat_byte(2)),
+			H256::repeat_byte(0xdd)
+		);
+	}
+
+	#[test]
+	fn gas_price_override_is_seen_by_gasprice() {
+		// GASPRICE PUSH1 0x00 SSTORE STOP: storing a non-zero price costs more.
+		let code = vec![0x3a, 0x60, 0x00, 0x55, 0x00];
+		let gas_price = U256::from(1_000_000_000u64);
+
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code.clone()));
+		let (_, _, zero_price_gas) = call(&overlay);
+
+		overlay.set_environment(OverlayEnvironment {
+			gas_price: Some(gas_price),
+			..Default::default()
+		});
+		assert_eq!(overlay.gas_price(), gas_price);
+		let (exit_reason, _, estimated_gas) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+		// Executing directly with that gas price in the environment agrees.
+		let priced_vicinity = MemoryVicinity {
+			gas_price,
+			..vicinity.clone()
+		};
+		let (_, _, executed_gas) = call(&with_contract(&priced_vicinity, code));
+		assert_eq!(estimated_gas, executed_gas);
+		assert!(estimated_gas > zero_price_gas);
+	}
+
+	#[test]
+	fn origin_is_taken_from_the_request_sender() {
+		// Reverts unless ORIGIN == CALLER.
+		let code = vec![
+			0x32, 0x33, 0x14, 0x60, 0x0b, 0x57, 0x60, 0x00, 0x60, 0x00, 0xfd, 0x5b, 0x00,
+		];
+
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code));
+		let (exit_reason, _, _) = call(&overlay);
+		assert!(matches!(exit_reason, ExitReason::Revert(_)));
+
+		overlay.set_environment(OverlayEnvironment::for_request(&CallRequest {
+			from: Some(CALLER),
+			to: Some(CONTRACT),
+			..Default::default()
+		}));
+		assert_eq!(overlay.origin(), CALLER);
+		let (exit_reason, _, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	}
+
+	#[test]
+	fn block_fields_are_taken_from_the_header() {
+		// Reverts unless TIMESTAMP > 1_600_000_000.
+		let code = vec![
+			0x42, 0x63, 0x5f, 0x5e, 0x10, 0x00, 0x10, 0x60, 0x0f, 0x57, 0x60, 0x00, 0x60, 0x00,
+			0xfd, 0x5b, 0x00,
+		];
+		let header = ethereum::Header {
+			parent_hash: H256::default(),
+			ommers_hash: H256::default(),
+			beneficiary: H160::repeat_byte(0xbe),
+			state_root: H256::default(),
+			transactions_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: Default::default(),
+			difficulty: U256::from(3u64),
+			number: U256::from(100u64),
+			gas_limit: U256::from(75_000_000u64),
+			gas_used: U256::zero(),
+			timestamp: 1_700_000_000_000,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: Default::default(),
+		};
+
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code));
+		let (exit_reason, _, _) = call(&overlay);
+		assert!(matches!(exit_reason, ExitReason::Revert(_)));
+
+		overlay.set_environment(OverlayEnvironment::default().with_header(&header));
+		assert_eq!(overlay.block_number(), U256::from(100u64));
+		assert_eq!(overlay.block_coinbase(), H160::repeat_byte(0xbe));
+		assert_eq!(overlay.block_timestamp(), U256::from(1_700_000_000u64));
+		assert_eq!(overlay.block_difficulty(), U256::from(3u64));
+		assert_eq!(overlay.block_gas_limit(), U256::from(75_000_000u64));
+		let (exit_reason, _, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	}
+
+	#[test]
+	fn block_hash_reads_recent_hashes() {
+		// Returns BLOCKHASH(NUMBER - 1).
+		let code = vec![
+			0x43, 0x60, 0x01, 0x90, 0x03, 0x40, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+		];
+		let block_hashes = (0u64..300)
+			.map(|n| (U256::from(n), H256::from_low_u64_be(n + 1)))
+			.collect();
+
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code));
+		overlay.set_environment(
+			OverlayEnvironment {
+				block_number: Some(U256::from(300u64)),
+				..Default::default()
+			}
+			.with_block_hashes(block_hashes),
+		);
+
+		let (exit_reason, data, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(H256::from_slice(&data), H256::from_low_u64_be(300));
+
+		assert_eq!(
+			overlay.block_hash(U256::from(44u64)),
+			H256::from_low_u64_be(45)
+		);
+		// Beyond the 256 most recent blocks, and the current block, read as zero.
+		assert_eq!(overlay.block_hash(U256::from(43u64)), H256::zero());
+		assert_eq!(overlay.block_hash(U256::from(300u64)), H256::zero());
+	}
+
+	#[test]
+	fn chain_id_is_the_configured_one() {
+		// Reverts unless CHAINID == 42.
+		let code = vec![
+			0x46, 0x60, 0x2a, 0x14, 0x60, 0x0c, 0x57, 0x60, 0x00, 0x60, 0x00, 0xfd, 0x5b, 0x00,
+		];
+
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code));
+
+		overlay.set_environment(OverlayEnvironment::default().with_chain_id(42));
+		let (exit_reason, _, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+		overlay.set_environment(OverlayEnvironment::default().with_chain_id(43));
+		assert_eq!(overlay.chain_id(), U256::from(43u64));
+		let (exit_reason, _, _) = call(&overlay);
+		assert!(matches!(exit_reason, ExitReason::Revert(_)));
+	}
+
+	/// Returns its input when called at `ECHO`.
+	struct EchoPrecompile;
+
+	const ECHO: H160 = H160::repeat_byte(0xec);
+
+	impl PrecompileSet for EchoPrecompile {
+		fn execute(&self, handle: &mut impl PrecompileHandle) -> Option<PrecompileResult> {
+			if handle.code_address() != ECHO {
+				return None;
+			}
+			Some(Ok(PrecompileOutput {
+				exit_status: ExitSucceed::Returned,
+				output: handle.input().to_vec(),
+			}))
+		}
+
+		fn is_precompile(&self, address: H160) -> bool {
+			address == ECHO
+		}
+	}
+
+	#[test]
+	fn transact_uses_the_given_precompiles() {
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(MemoryBackend::new(&vicinity, state()));
+		let request = CallRequest {
+			from: Some(CALLER),
+			to: Some(ECHO),
+			data: Some(Bytes(b"echo".to_vec())),
+			..Default::default()
+		};
+
+		let (exit_reason, data, _) =
+			overlay.transact(&Config::london(), &EchoPrecompile, &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(data, b"echo".to_vec());
+
+		// Without the precompile the address holds no code.
+		let (exit_reason, data, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert!(data.is_empty());
+	}
+
+	#[test]
+	fn unfunded_sender_is_assumed_to_afford_the_value() {
+		let vicinity = vicinity();
+		// A payable contract, accepting any value.
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, vec![0x00]));
+		let request = CallRequest {
+			to: Some(CONTRACT),
+			value: Some(U256::from(1_000u64)),
+			..Default::default()
+		};
+
+		let (exit_reason, _, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(overlay.basic(CONTRACT).balance, U256::from(1_000u64));
+		// The assumed balance is not kept.
+		assert_eq!(overlay.basic(H160::zero()).balance, U256::zero());
+
+		overlay.set_assume_sender_balance(false);
+		let (exit_reason, _, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Error(ExitError::OutOfFund));
+	}
+
+	#[test]
+	fn overdrawing_sender_balance_is_floored_at_zero() {
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, vec![0x00]));
+		overlay.set_balance(CALLER, U256::from(10u64));
+		let request = CallRequest {
+			from: Some(CALLER),
+			to: Some(CONTRACT),
+			value: Some(U256::from(1_000u64)),
+			..Default::default()
+		};
+
+		// Spending more than the actual balance, which only the assumed one affords.
+		let (exit_reason, _, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(overlay.basic(CALLER).balance, U256::zero());
+		assert_eq!(overlay.basic(CONTRACT).balance, U256::from(1_000u64));
+	}
+
+	#[test]
+	fn value_received_by_a_funded_sender_is_kept() {
+		let vicinity = vicinity();
+		// CALL(GAS, CALLER, 1, 0, 0, 0, 0): sends 1 back to the sender.
+		let code = vec![
+			0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x01, 0x33, 0x5a, 0xf1, 0x00,
+		];
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code));
+		overlay.set_balance(CONTRACT, U256::from(5u64));
+		overlay.set_balance(CALLER, U256::from(10u64));
+		let request = CallRequest {
+			from: Some(CALLER),
+			to: Some(CONTRACT),
+			value: Some(U256::from(3u64)),
+			..Default::default()
+		};
+
+		let (exit_reason, _, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(overlay.basic(CALLER).balance, U256::from(10u64 - 3 + 1));
+		assert_eq!(overlay.basic(CONTRACT).balance, U256::from(5u64 + 3 - 1));
+	}
+
+	#[test]
+	fn funding_the_sender_does_not_create_it() {
+		let vicinity = vicinity();
+		// REVERT(0, 0)
+		let mut overlay =
+			OverlayBackend::new(with_contract(&vicinity, vec![0x60, 0x00, 0x80, 0xfd]));
+		let request = CallRequest {
+			from: Some(CALLER),
+			to: Some(CONTRACT),
+			value: Some(U256::from(1_000u64)),
+			..Default::default()
+		};
+
+		let (exit_reason, _, _) = overlay.transact(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert!(!overlay.exists(CALLER));
+		assert_eq!(overlay.basic(CALLER), Basic::default());
+	}
+
+	#[test]
+	fn gas_usage_adds_up_to_the_used_gas() {
+		let vicinity = vicinity();
+		// Sets slot 0 and clears it again, which is refunded.
+		let code = vec![
+			0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0x55, 0x00,
+		];
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code.clone()));
+		let request = CallRequest {
+			from: Some(CALLER),
+			to: Some(CONTRACT),
+			data: Some(Bytes(vec![0x00, 0x01])),
+			..Default::default()
+		};
+
+		let (exit_reason, _, gas_usage) =
+			overlay.transact_with_gas_usage(&Config::london(), &(), &request, 100_000);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		// 21000 plus a zero and a non-zero calldata byte.
+		assert_eq!(gas_usage.intrinsic, 21_000 + 4 + 16);
+		// Four pushes, a cold slot set and a warm one reset.
+		assert_eq!(gas_usage.execution, 4 * 3 + 22_100 + 100);
+		// The refund of the reset slot, capped at a fifth of the gas used.
+		assert_eq!(gas_usage.refund, (21_020 + 22_212) / 5);
+		assert_eq!(
+			gas_usage.intrinsic + gas_usage.execution - gas_usage.refund,
+			gas_usage.total()
+		);
+
+		// As reported by `transact`, on the same state.
+		let (_, _, used_gas) = OverlayBackend::new(with_contract(&vicinity, code)).transact(
+			&Config::london(),
+			&(),
+			&request,
+			100_000,
+		);
+		assert_eq!(used_gas, gas_usage.total());
+	}
+
+	#[test]
+	fn block_overrides_apply_to_the_environment() {
+		// Returns whether the block timestamp is past 1000, then the base fee and number:
+		// TIMESTAMP PUSH2 1000 LT PUSH1 0 MSTORE BASEFEE PUSH1 32 MSTORE
+		// NUMBER PUSH1 64 MSTORE PUSH1 96 PUSH1 0 RETURN
+		let code = vec![
+			0x42, 0x61, 0x03, 0xe8, 0x10, 0x60, 0x00, 0x52, 0x48, 0x60, 0x20, 0x52, 0x43, 0x60,
+			0x40, 0x52, 0x60, 0x60, 0x60, 0x00, 0xf3,
+		];
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, code));
+
+		let (exit_reason, data, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(U256::from(&data[0..32]), U256::zero());
+
+		overlay.set_environment(OverlayEnvironment::default().with_block_overrides(
+			&BlockOverrides {
+				number: Some(U256::from(12u64)),
+				time: Some(U256::from(1_001u64)),
+				base_fee: Some(U256::from(7u64)),
+			},
+		));
+		let (exit_reason, data, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		// The time gate is open at the overridden timestamp.
+		assert_eq!(U256::from(&data[0..32]), U256::one());
+		assert_eq!(U256::from(&data[32..64]), U256::from(7u64));
+		assert_eq!(U256::from(&data[64..96]), U256::from(12u64));
+	}
+
+	#[test]
+	fn state_overrides_patch_accounts() {
+		// Returns `value`: PUSH1 value PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+		let returns = |value: u8| vec![0x60, value, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+		let vicinity = vicinity();
+		let mut overlay = OverlayBackend::new(with_contract(&vicinity, returns(1)));
+		assert_eq!(U256::from(&call(&overlay).1[..]), U256::one());
+
+		overlay.apply_state_override(
+			CONTRACT,
+			&CallStateOverride {
+				balance: Some(U256::from(3u64)),
+				code: Some(returns(2).into()),
+				..Default::default()
+			},
+		);
+		let (exit_reason, data, _) = call(&overlay);
+		assert_eq!(exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(U256::from(&data[..]), U256::from(2u64));
+		assert_eq!(overlay.basic(CONTRACT).balance, U256::from(3u64));
+
+		// Only the given slots are patched by a state diff.
+		let address = H160::repeat_byte(1);
+		overlay.apply_state_override(
+			address,
+			&CallStateOverride {
+				state_diff: Some([(H256::repeat_byte(1), H256::zero())].into_iter().collect()),
+				..Default::default()
+			},
+		);
+		assert_eq!(overlay.storage(address, H256::repeat_byte(1)), H256::zero());
+		assert_eq!(
+			overlay.storage(address, H256::repeat_byte(2)),
+			H256::repeat_byte(0xbb)
+		);
+
+		overlay.apply_state_override(
+			address,
+			&CallStateOverride {
+				state: Some(BTreeMap::new()),
+				..Default::default()
+			},
+		);
+		assert_eq!(overlay.storage(address, H256::repeat_byte(2)), H256::zero());
+	}
+}
@@ -16,19 +16,23 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
 use ethereum::TransactionV2 as EthereumTransaction;
 use ethereum_types::{H160, H256};
-use jsonrpsee::core::Error;
-// Substrate
-use sp_core::hashing::keccak_256;
+use hmac::{Hmac, Mac, NewMac};
+use jsonrpsee::core::{async_trait, Error};
+use sha2::Sha512;
 // Frontier
 use fc_rpc_core::types::TransactionMessage;
 
-use crate::internal_err;
+use crate::{crypto, internal_err};
 
 /// A generic Ethereum signer.
 pub trait EthSigner: Send + Sync {
 	/// Available accounts from this signer.
+	///
+	/// The order is stable across calls, as tooling refers to accounts by index.
 	fn accounts(&self) -> Vec<H160>;
 	/// Sign a transaction message using the given account in message.
 	fn sign(
@@ -38,8 +42,68 @@ pub trait EthSigner: Send + Sync {
 	) -> Result<EthereumTransaction, Error>;
 }
 
+/// An Ethereum signer signing asynchronously, such as a hardware wallet or a
+/// remote key management service.
+///
+/// Every `EthSigner` is an `AsyncEthSigner`.
+#[async_trait]
+pub trait AsyncEthSigner: Send + Sync {
+	/// Available accounts from this signer.
+	fn accounts(&self) -> Vec<H160>;
+	/// Sign a transaction message using the given account in message.
+	async fn sign(
+		&self,
+		message: TransactionMessage,
+		address: &H160,
+	) -> Result<EthereumTransaction, Error>;
+}
+
+#[async_trait]
+impl<T: EthSigner> AsyncEthSigner for T {
+	fn accounts(&self) -> Vec<H160> {
+		EthSigner::accounts(self)
+	}
+
+	async fn sign(
+		&self,
+		message: TransactionMessage,
+		address: &H160,
+	) -> Result<EthereumTransaction, Error> {
+		EthSigner::sign(self, message, address)
+	}
+}
+
+/// Accounts of all `signers`, in signer order, each listed once even when several
+/// signers hold its key.
+pub(crate) fn signer_accounts(signers: &[Box<dyn AsyncEthSigner>]) -> Vec<H160> {
+	let mut accounts: Vec<H160> = Vec::new();
+	for account in signers.iter().flat_map(|signer| signer.accounts()) {
+		if !accounts.contains(&account) {
+			accounts.push(account);
+		}
+	}
+	accounts
+}
+
+/// Signs `message` with the first of `signers` owning `address`, so signers
+/// registered first take precedence.
+pub(crate) async fn sign_with(
+	signers: &[Box<dyn AsyncEthSigner>],
+	message: TransactionMessage,
+	address: &H160,
+) -> Result<EthereumTransaction, Error> {
+	for signer in signers {
+		if signer.accounts().contains(address) {
+			return signer.sign(message, address).await;
+		}
+	}
+	Err(internal_err("signer not available"))
+}
+
+/// A signer of development keys, listing accounts in the order of its keys.
 pub struct EthDevSigner {
 	keys: Vec<libsecp256k1::SecretKey>,
+	chain_id: Option<u64>,
 }
 
 impl EthDevSigner {
@@ -51,8 +115,87 @@ impl EthDevSigner {
 				0x11, 0x11, 0x11, 0x11,
 			])
 			.expect("Test key is valid; qed")],
+			chain_id: None,
 		}
 	}
+
+	/// Signs transactions for `chain_id`, whatever the chain id of their message,
+	/// to target a given test network.
+	pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+		self.chain_id = Some(chain_id);
+		self
+	}
+}
+
+/// Mnemonic of the accounts funded by default in Hardhat and Ganache.
+pub const DEV_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+impl EthDevSigner {
+	/// Signer for the first `count` accounts derived from a BIP-39 mnemonic along
+	/// the BIP-44 path `m/44'/60'/0'/0/i`, as wallets and dev tooling do.
+	///
+	/// Use `DEV_MNEMONIC` for the well-known Hardhat/Ganache accounts.
+	pub fn from_mnemonic(phrase: &str, count: u32) -> Option<Self> {
+		let mnemonic = bip39::Mnemonic::from_phrase(phrase, bip39::Language::English).ok()?;
+		let seed = bip39::Seed::new(&mnemonic, "");
+
+		let master = split_extended_key(hmac_sha512(b"Bitcoin seed", seed.as_bytes()))?;
+		let (account_key, account_chain_code) = [44, 60, 0]
+			.iter()
+			.map(|index| index | HARDENED)
+			.chain(Some(0))
+			.try_fold(master, |(key, chain_code), index| {
+				derive_child(&key, &chain_code, index)
+			})?;
+
+		let keys = (0..count)
+			.map(|index| derive_child(&account_key, &account_chain_code, index).map(|(key, _)| key))
+			.collect::<Option<Vec<_>>>()?;
+		Some(Self {
+			keys,
+			chain_id: None,
+		})
+	}
+}
+
+/// Offset of hardened BIP-32 child indexes.
+const HARDENED: u32 = 0x8000_0000;
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+	let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any size; qed");
+	mac.update(data);
+	let mut output = [0u8; 64];
+	output.copy_from_slice(&mac.finalize().into_bytes());
+	output
+}
+
+fn split_extended_key(output: [u8; 64]) -> Option<(libsecp256k1::SecretKey, [u8; 32])> {
+	let key = libsecp256k1::SecretKey::parse_slice(&output[0..32]).ok()?;
+	let mut chain_code = [0u8; 32];
+	chain_code.copy_from_slice(&output[32..64]);
+	Some((key, chain_code))
+}
+
+/// BIP-32 private child key derivation.
+fn derive_child(
+	key: &libsecp256k1::SecretKey,
+	chain_code: &[u8; 32],
+	index: u32,
+) -> Option<(libsecp256k1::SecretKey, [u8; 32])> {
+	let mut data = Vec::with_capacity(37);
+	if index >= HARDENED {
+		data.push(0);
+		data.extend_from_slice(&key.serialize());
+	} else {
+		data.extend_from_slice(
+			&libsecp256k1::PublicKey::from_secret_key(key).serialize_compressed(),
+		);
+	}
+	data.extend_from_slice(&index.to_be_bytes());
+
+	let (mut child, child_chain_code) = split_extended_key(hmac_sha512(chain_code, &data))?;
+	child.tweak_add_assign(key).ok()?;
+	Some((child, child_chain_code))
 }
 
 fn secret_key_address(secret: &libsecp256k1::SecretKey) -> H160 {
@@ -63,7 +206,7 @@ fn secret_key_address(secret: &libsecp256k1::SecretKey) -> H160 {
 fn public_key_address(public: &libsecp256k1::PublicKey) -> H160 {
 	let mut res = [0u8; 64];
 	res.copy_from_slice(&public.serialize()[1..65]);
-	H160::from(H256::from(keccak_256(&res)))
+	crypto::public_key_address(&res)
 }
 
 impl EthSigner for EthDevSigner {
@@ -76,88 +219,488 @@ impl EthSigner for EthDevSigner {
 		message: TransactionMessage,
 		address: &H160,
 	) -> Result<EthereumTransaction, Error> {
-		let mut transaction = None;
-
-		for secret in &self.keys {
-			let key_address = secret_key_address(secret);
-
-			if &key_address == address {
-				match message {
-					TransactionMessage::Legacy(m) => {
-						let signing_message = libsecp256k1::Message::parse_slice(&m.hash()[..])
-							.map_err(|_| internal_err("invalid signing message"))?;
-						let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
-						let v = match m.chain_id {
-							None => 27 + recid.serialize() as u64,
-							Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
-						};
-						let rs = signature.serialize();
-						let r = H256::from_slice(&rs[0..32]);
-						let s = H256::from_slice(&rs[32..64]);
-						transaction =
-							Some(EthereumTransaction::Legacy(ethereum::LegacyTransaction {
-								nonce: m.nonce,
-								gas_price: m.gas_price,
-								gas_limit: m.gas_limit,
-								action: m.action,
-								value: m.value,
-								input: m.input,
-								signature: ethereum::TransactionSignature::new(v, r, s)
-									.ok_or_else(|| {
-										internal_err("signer generated invalid signature")
-									})?,
-							}));
-					}
-					TransactionMessage::EIP2930(m) => {
-						let signing_message = libsecp256k1::Message::parse_slice(&m.hash()[..])
-							.map_err(|_| internal_err("invalid signing message"))?;
-						let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
-						let rs = signature.serialize();
-						let r = H256::from_slice(&rs[0..32]);
-						let s = H256::from_slice(&rs[32..64]);
-						transaction =
-							Some(EthereumTransaction::EIP2930(ethereum::EIP2930Transaction {
-								chain_id: m.chain_id,
-								nonce: m.nonce,
-								gas_price: m.gas_price,
-								gas_limit: m.gas_limit,
-								action: m.action,
-								value: m.value,
-								input: m.input.clone(),
-								access_list: m.access_list,
-								odd_y_parity: recid.serialize() != 0,
-								r,
-								s,
-							}));
-					}
-					TransactionMessage::EIP1559(m) => {
-						let signing_message = libsecp256k1::Message::parse_slice(&m.hash()[..])
-							.map_err(|_| internal_err("invalid signing message"))?;
-						let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
-						let rs = signature.serialize();
-						let r = H256::from_slice(&rs[0..32]);
-						let s = H256::from_slice(&rs[32..64]);
-						transaction =
-							Some(EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
-								chain_id: m.chain_id,
-								nonce: m.nonce,
-								max_priority_fee_per_gas: m.max_priority_fee_per_gas,
-								max_fee_per_gas: m.max_fee_per_gas,
-								gas_limit: m.gas_limit,
-								action: m.action,
-								value: m.value,
-								input: m.input.clone(),
-								access_list: m.access_list,
-								odd_y_parity: recid.serialize() != 0,
-								r,
-								s,
-							}));
-					}
-				}
-				break;
+		let secret = self
+			.keys
+			.iter()
+			.find(|secret| &secret_key_address(secret) == address)
+			.ok_or_else(|| internal_err("signer not available"))?;
+		let message = match self.chain_id {
+			Some(chain_id) => with_chain_id(message, chain_id),
+			None => message,
+		};
+		sign_message(secret, message, address)
+	}
+}
+
+/// `message` for the chain `chain_id`.
+fn with_chain_id(message: TransactionMessage, chain_id: u64) -> TransactionMessage {
+	match message {
+		TransactionMessage::Legacy(mut m) => {
+			m.chain_id = Some(chain_id);
+			TransactionMessage::Legacy(m)
+		}
+		TransactionMessage::EIP2930(mut m) => {
+			m.chain_id = chain_id;
+			TransactionMessage::EIP2930(m)
+		}
+		TransactionMessage::EIP1559(mut m) => {
+			m.chain_id = chain_id;
+			TransactionMessage::EIP1559(m)
+		}
+	}
+}
+
+/// A signer backed by a keystore of secret keys.
+///
+/// Accounts are listed sorted by address, whatever the order the keys were
+/// inserted in.
+#[derive(Default)]
+pub struct EthKeystoreSigner {
+	keys: BTreeMap<H160, libsecp256k1::SecretKey>,
+}
+
+impl EthKeystoreSigner {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a key to the keystore, returning its account.
+	pub fn insert(&mut self, secret: libsecp256k1::SecretKey) -> H160 {
+		let address = secret_key_address(&secret);
+		self.keys.insert(address, secret);
+		address
+	}
+}
+
+impl EthSigner for EthKeystoreSigner {
+	fn accounts(&self) -> Vec<H160> {
+		self.keys.keys().cloned().collect()
+	}
+
+	fn sign(
+		&self,
+		message: TransactionMessage,
+		address: &H160,
+	) -> Result<EthereumTransaction, Error> {
+		let secret = self
+			.keys
+			.get(address)
+			.ok_or_else(|| internal_err("signer not available"))?;
+		sign_message(secret, message, address)
+	}
+}
+
+/// Signs `message` with `secret`, the key of `address`.
+fn sign_message(
+	secret: &libsecp256k1::SecretKey,
+	message: TransactionMessage,
+	address: &H160,
+) -> Result<EthereumTransaction, Error> {
+	build_signed_transaction(secret, message, address, signature_rs)
+}
+
+/// The `r` and `s` components of `signature`.
+fn signature_rs(signature: &libsecp256k1::Signature) -> (H256, H256) {
+	let rs = signature.serialize();
+	(H256::from_slice(&rs[0..32]), H256::from_slice(&rs[32..64]))
+}
+
+/// Signs `message` with `secret`, taking the signature components apart with `rs`,
+/// a hook for tests to fault the construction of the transaction.
+fn build_signed_transaction(
+	secret: &libsecp256k1::SecretKey,
+	message: TransactionMessage,
+	address: &H160,
+	rs: fn(&libsecp256k1::Signature) -> (H256, H256),
+) -> Result<EthereumTransaction, Error> {
+	let transaction = match message {
+		TransactionMessage::Legacy(m) => {
+			let signing_message = libsecp256k1::Message::parse_slice(&m.hash()[..])
+				.map_err(|_| internal_err("invalid signing message"))?;
+			let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
+			let v = match m.chain_id {
+				None => 27 + recid.serialize() as u64,
+				Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
+			};
+			let (r, s) = rs(&signature);
+			EthereumTransaction::Legacy(ethereum::LegacyTransaction {
+				nonce: m.nonce,
+				gas_price: m.gas_price,
+				gas_limit: m.gas_limit,
+				action: m.action,
+				value: m.value,
+				input: m.input,
+				signature: ethereum::TransactionSignature::new(v, r, s)
+					.ok_or_else(|| internal_err("signer generated invalid signature"))?,
+			})
+		}
+		TransactionMessage::EIP2930(m) => {
+			let signing_message = libsecp256k1::Message::parse_slice(&m.hash()[..])
+				.map_err(|_| internal_err("invalid signing message"))?;
+			let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
+			let (r, s) = rs(&signature);
+			EthereumTransaction::EIP2930(ethereum::EIP2930Transaction {
+				chain_id: m.chain_id,
+				nonce: m.nonce,
+				gas_price: m.gas_price,
+				gas_limit: m.gas_limit,
+				action: m.action,
+				value: m.value,
+				input: m.input.clone(),
+				access_list: m.access_list,
+				odd_y_parity: recid.serialize() != 0,
+				r,
+				s,
+			})
+		}
+		TransactionMessage::EIP1559(m) => {
+			let signing_message = libsecp256k1::Message::parse_slice(&m.hash()[..])
+				.map_err(|_| internal_err("invalid signing message"))?;
+			let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
+			let (r, s) = rs(&signature);
+			EthereumTransaction::EIP1559(ethereum::EIP1559Transaction {
+				chain_id: m.chain_id,
+				nonce: m.nonce,
+				max_priority_fee_per_gas: m.max_priority_fee_per_gas,
+				max_fee_per_gas: m.max_fee_per_gas,
+				gas_limit: m.gas_limit,
+				action: m.action,
+				value: m.value,
+				input: m.input.clone(),
+				access_list: m.access_list,
+				odd_y_parity: recid.serialize() != 0,
+				r,
+				s,
+			})
+		}
+	};
+	if cfg!(debug_assertions) {
+		ensure_signed_by(&transaction, address)?;
+	}
+	Ok(transaction)
+}
+
+/// Recovers the sender of a signed transaction.
+fn recover_signer(transaction: &EthereumTransaction) -> Option<H160> {
+	let (hash, r, s, recid) = match transaction {
+		EthereumTransaction::Legacy(t) => (
+			ethereum::LegacyTransactionMessage::from(t.clone()).hash(),
+			*t.signature.r(),
+			*t.signature.s(),
+			t.signature.standard_v(),
+		),
+		EthereumTransaction::EIP2930(t) => (
+			ethereum::EIP2930TransactionMessage::from(t.clone()).hash(),
+			t.r,
+			t.s,
+			t.odd_y_parity as u8,
+		),
+		EthereumTransaction::EIP1559(t) => (
+			ethereum::EIP1559TransactionMessage::from(t.clone()).hash(),
+			t.r,
+			t.s,
+			t.odd_y_parity as u8,
+		),
+	};
+
+	let mut rs = [0u8; 64];
+	rs[0..32].copy_from_slice(&r[..]);
+	rs[32..64].copy_from_slice(&s[..]);
+	let message = libsecp256k1::Message::parse_slice(&hash[..]).ok()?;
+	let signature = libsecp256k1::Signature::parse_standard(&rs).ok()?;
+	let recid = libsecp256k1::RecoveryId::parse(recid).ok()?;
+	libsecp256k1::recover(&message, &signature, &recid)
+		.ok()
+		.map(|public| public_key_address(&public))
+}
+
+/// Checks that a signature produced by a signer recovers to the requested
+/// account, catching signature construction bugs before the transaction
+/// reaches the pool.
+fn ensure_signed_by(transaction: &EthereumTransaction, address: &H160) -> Result<(), Error> {
+	match recover_signer(transaction) {
+		Some(signer) if &signer == address => Ok(()),
+		_ => Err(internal_err(
+			"signer generated signature not matching the requested account",
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ethereum_types::U256;
+	use futures::{
+		channel::{mpsc, oneshot},
+		StreamExt,
+	};
+
+	fn legacy_message() -> TransactionMessage {
+		TransactionMessage::Legacy(ethereum::LegacyTransactionMessage {
+			nonce: U256::zero(),
+			gas_price: U256::from(1_000_000_000u64),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x22)),
+			value: U256::from(1u64),
+			input: Vec::new(),
+			chain_id: Some(42),
+		})
+	}
+
+	#[test]
+	fn derives_well_known_dev_accounts() {
+		let signer = EthDevSigner::from_mnemonic(DEV_MNEMONIC, 20).expect("valid mnemonic; qed");
+		let accounts = EthSigner::accounts(&signer);
+
+		assert_eq!(accounts.len(), 20);
+		// Hardhat's first two default accounts.
+		assert_eq!(
+			accounts[0],
+			"f39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().unwrap()
+		);
+		assert_eq!(
+			accounts[1],
+			"70997970c51812dc3a010c7d01b50e0d17dc79c8".parse().unwrap()
+		);
+
+		assert!(EthDevSigner::from_mnemonic("not a valid mnemonic", 1).is_none());
+	}
+
+	#[test]
+	fn accounts_of_all_signers_are_listed_once() {
+		let dev = || {
+			Box::new(EthDevSigner::from_mnemonic(DEV_MNEMONIC, 2).expect("valid mnemonic; qed"))
+				as Box<dyn AsyncEthSigner>
+		};
+		let dev_accounts = EthSigner::accounts(
+			&EthDevSigner::from_mnemonic(DEV_MNEMONIC, 2).expect("valid mnemonic; qed"),
+		);
+		assert_eq!(dev_accounts.len(), 2);
+
+		assert_eq!(signer_accounts(&[dev()]), dev_accounts);
+		// The same keys registered twice.
+		assert_eq!(signer_accounts(&[dev(), dev()]), dev_accounts);
+		assert!(signer_accounts(&[]).is_empty());
+	}
+
+	#[test]
+	fn accounts_are_derived_with_the_shared_helper() {
+		let secret = libsecp256k1::SecretKey::parse(&[0x46; 32]).unwrap();
+		let mut public_key = [0u8; 64];
+		public_key
+			.copy_from_slice(&libsecp256k1::PublicKey::from_secret_key(&secret).serialize()[1..]);
+
+		let mut signer = EthKeystoreSigner::new();
+		let address = signer.insert(secret);
+		assert_eq!(address, crypto::public_key_address(&public_key));
+		assert_eq!(
+			address,
+			H160::from_slice(&hex::decode("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap())
+		);
+	}
+
+	#[test]
+	fn signed_transaction_recovers_to_requested_account() {
+		let signer = EthDevSigner::new();
+		let address = EthSigner::accounts(&signer)[0];
+
+		let transaction = EthSigner::sign(&signer, legacy_message(), &address)
+			.expect("dev signer owns the address; qed");
+
+		assert_eq!(recover_signer(&transaction), Some(address));
+		assert!(ensure_signed_by(&transaction, &address).is_ok());
+		assert!(ensure_signed_by(&transaction, &H160::repeat_byte(0x33)).is_err());
+	}
+
+	#[test]
+	fn forced_chain_id_is_signed_for() {
+		let signer = EthDevSigner::new().with_chain_id(1337);
+		let address = EthSigner::accounts(&signer)[0];
+
+		// The message is for chain 42.
+		let transaction = EthSigner::sign(&signer, legacy_message(), &address)
+			.expect("dev signer owns the address; qed");
+		match &transaction {
+			EthereumTransaction::Legacy(t) => {
+				assert_eq!(t.signature.chain_id(), Some(1337));
+				assert!(t.signature.v() == 2 * 1337 + 35 || t.signature.v() == 2 * 1337 + 36);
 			}
+			_ => panic!("legacy message signs a legacy transaction"),
 		}
+		assert_eq!(recover_signer(&transaction), Some(address));
+
+		match EthSigner::sign(&signer, dynamic_fee_message(), &address) {
+			Ok(EthereumTransaction::EIP1559(t)) => assert_eq!(t.chain_id, 1337),
+			_ => panic!("dynamic fee message signs a dynamic fee transaction"),
+		}
+	}
+
+	fn dynamic_fee_message() -> TransactionMessage {
+		TransactionMessage::EIP1559(ethereum::EIP1559TransactionMessage {
+			chain_id: 42,
+			nonce: U256::zero(),
+			max_priority_fee_per_gas: U256::zero(),
+			max_fee_per_gas: U256::from(1_000_000_000u64),
+			gas_limit: U256::from(21_000u64),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x22)),
+			value: U256::from(1u64),
+			input: Vec::new(),
+			access_list: Vec::new(),
+		})
+	}
+
+	/// Signature components with a bit of `r` flipped, as a faulty serialization would.
+	#[cfg(debug_assertions)]
+	fn corrupted_rs(signature: &libsecp256k1::Signature) -> (H256, H256) {
+		let (mut r, s) = signature_rs(signature);
+		r.0[31] ^= 0x01;
+		(r, s)
+	}
+
+	// The verification only runs in debug builds.
+	#[cfg(debug_assertions)]
+	#[test]
+	fn corrupted_signature_fails_verification() {
+		let secret = libsecp256k1::SecretKey::parse(&[0x46; 32]).unwrap();
+		let address = secret_key_address(&secret);
+
+		let messages: [fn() -> TransactionMessage; 2] = [legacy_message, dynamic_fee_message];
+		for message in messages {
+			assert!(build_signed_transaction(&secret, message(), &address, signature_rs).is_ok());
+			let err =
+				build_signed_transaction(&secret, message(), &address, corrupted_rs).unwrap_err();
+			assert!(err
+				.to_string()
+				.contains("signer generated signature not matching the requested account"));
+		}
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	fn signer_holding_a_wrong_key_fails_verification() {
+		let mut signer = EthKeystoreSigner::new();
+		let address = H160::repeat_byte(0x33);
+		signer.keys.insert(
+			address,
+			libsecp256k1::SecretKey::parse(&[0x46; 32]).unwrap(),
+		);
+
+		let err = EthSigner::sign(&signer, legacy_message(), &address).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("signer generated signature not matching the requested account"));
+	}
+
+	type SignRequest = (
+		TransactionMessage,
+		H160,
+		oneshot::Sender<Result<EthereumTransaction, Error>>,
+	);
+
+	/// Signer forwarding requests to a remote key holder over a channel.
+	struct ChannelSigner {
+		address: H160,
+		requests: mpsc::UnboundedSender<SignRequest>,
+	}
+
+	#[async_trait]
+	impl AsyncEthSigner for ChannelSigner {
+		fn accounts(&self) -> Vec<H160> {
+			vec![self.address]
+		}
+
+		async fn sign(
+			&self,
+			message: TransactionMessage,
+			address: &H160,
+		) -> Result<EthereumTransaction, Error> {
+			let (sender, receiver) = oneshot::channel();
+			self.requests
+				.unbounded_send((message, *address, sender))
+				.map_err(|_| internal_err("remote signer unavailable"))?;
+			receiver
+				.await
+				.map_err(|_| internal_err("remote signer dropped the request"))?
+		}
+	}
+
+	#[test]
+	fn each_signer_signs_for_its_accounts() {
+		let dev = EthDevSigner::new();
+		let dev_address = EthSigner::accounts(&dev)[0];
+		let mut keystore = EthKeystoreSigner::new();
+		let keystore_address =
+			keystore.insert(libsecp256k1::SecretKey::parse(&[0x46; 32]).unwrap());
+		assert_ne!(dev_address, keystore_address);
+
+		let signers: Vec<Box<dyn AsyncEthSigner>> = vec![Box::new(dev), Box::new(keystore)];
+		for address in [dev_address, keystore_address] {
+			let transaction =
+				futures::executor::block_on(sign_with(&signers, legacy_message(), &address))
+					.expect("a signer owns the address; qed");
+			assert_eq!(recover_signer(&transaction), Some(address));
+		}
+
+		let err = futures::executor::block_on(sign_with(
+			&signers,
+			legacy_message(),
+			&H160::repeat_byte(0x33),
+		))
+		.unwrap_err();
+		assert!(err.to_string().contains("signer not available"));
+	}
+
+	#[test]
+	fn awaits_async_signer() {
+		let remote = EthDevSigner::from_mnemonic(DEV_MNEMONIC, 1).expect("valid mnemonic; qed");
+		let address = EthSigner::accounts(&remote)[0];
+		let (requests, mut pending) = mpsc::unbounded::<SignRequest>();
+		let handle = std::thread::spawn(move || {
+			while let Some((message, address, response)) =
+				futures::executor::block_on(pending.next())
+			{
+				std::thread::sleep(std::time::Duration::from_millis(50));
+				let _ = response.send(EthSigner::sign(&remote, message, &address));
+			}
+		});
+
+		let signers: Vec<Box<dyn AsyncEthSigner>> = vec![
+			Box::new(EthDevSigner::new()),
+			Box::new(ChannelSigner { address, requests }),
+		];
+		let transaction =
+			futures::executor::block_on(sign_with(&signers, legacy_message(), &address))
+				.expect("remote signer owns the address; qed");
+		assert_eq!(recover_signer(&transaction), Some(address));
+
+		let unknown = H160::repeat_byte(0x33);
+		assert!(
+			futures::executor::block_on(sign_with(&signers, legacy_message(), &unknown)).is_err()
+		);
+
+		drop(signers);
+		handle
+			.join()
+			.expect("remote signer exits once the channel closes; qed");
+	}
+
+	#[test]
+	fn keystore_accounts_are_sorted_by_address() {
+		let mut signer = EthKeystoreSigner::new();
+		let mut inserted: Vec<H160> = [0x42u8, 0x07, 0x99, 0x13, 0x5a]
+			.iter()
+			.map(|byte| {
+				let secret = libsecp256k1::SecretKey::parse(&[*byte; 32]).expect("valid key; qed");
+				signer.insert(secret)
+			})
+			.collect();
+
+		inserted.sort();
+		assert_eq!(EthSigner::accounts(&signer), inserted);
+		assert_eq!(EthSigner::accounts(&signer), EthSigner::accounts(&signer));
 
-		transaction.ok_or_else(|| internal_err("signer not available"))
+		let transaction = EthSigner::sign(&signer, legacy_message(), &inserted[0])
+			.expect("keystore owns the address; qed");
+		assert_eq!(recover_signer(&transaction), Some(inserted[0]));
 	}
 }
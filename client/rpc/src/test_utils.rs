@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2022 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fixtures shared by the unit tests of the crate.
+
+use ethereum::BlockV2 as EthereumBlock;
+use ethereum_types::{H160, H256, U256};
+use evm::backend::MemoryVicinity;
+
+/// The environment of the calls of the tests, on chain 42.
+pub(crate) fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: H160::default(),
+		chain_id: U256::from(42u64),
+		block_hashes: Vec::new(),
+		block_number: U256::zero(),
+		block_coinbase: H160::default(),
+		block_timestamp: U256::zero(),
+		block_difficulty: U256::zero(),
+		block_gas_limit: U256::from(15_000_000u64),
+		block_base_fee_per_gas: U256::zero(),
+	}
+}
+
+/// Header of block `number`, all its other fields zero or empty.
+pub(crate) fn partial_header(number: u64) -> ethereum::PartialHeader {
+	ethereum::PartialHeader {
+		parent_hash: H256::zero(),
+		beneficiary: Default::default(),
+		state_root: H256::zero(),
+		receipts_root: H256::zero(),
+		logs_bloom: Default::default(),
+		difficulty: U256::zero(),
+		number: U256::from(number),
+		gas_limit: U256::zero(),
+		gas_used: U256::zero(),
+		timestamp: 0,
+		extra_data: Vec::new(),
+		mix_hash: H256::zero(),
+		nonce: Default::default(),
+	}
+}
+
+/// Block `number` without transactions.
+pub(crate) fn empty_block(number: u64) -> EthereumBlock {
+	EthereumBlock::new(partial_header(number), Vec::new(), Vec::new())
+}
@@ -23,13 +23,12 @@ use jsonrpsee::core::RpcResult as Result;
 // Substrate
 use sp_api::{Core, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
-use sp_core::keccak_256;
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 // Frontier
 use fc_rpc_core::{types::Bytes, Web3ApiServer};
 use fp_rpc::EthereumRuntimeRPCApi;
 
-use crate::internal_err;
+use crate::{crypto::keccak256, internal_err};
 
 /// Web3 API implementation.
 pub struct Web3<B, C> {
@@ -70,6 +69,6 @@ where
 	}
 
 	fn sha3(&self, input: Bytes) -> Result<H256> {
-		Ok(H256::from(keccak_256(&input.into_vec())))
+		Ok(keccak256(&input.into_vec()))
 	}
 }
@@ -173,9 +173,15 @@ where
 		let fee = T::OnChargeTransaction::withdraw_fee(&source, total_fee)
 			.map_err(|e| RunnerError { error: e, weight })?;
 
-		// Execute the EVM call.
+		// Execute the EVM call. Non-transactional calls paying no fee, like an eth_call
+		// without a gas price, also read a zero `GASPRICE`. Transactional ones, such as
+		// XCM calls whose fees are withdrawn elsewhere, read the base fee.
 		let vicinity = Vicinity {
-			gas_price: base_fee,
+			gas_price: if !is_transactional && total_fee_per_gas.is_zero() {
+				U256::zero()
+			} else {
+				base_fee
+			},
 			origin: source,
 		};
 
@@ -466,6 +466,76 @@ fn runner_non_transactional_calls_with_non_balance_accounts_is_ok_without_gas_pr
 	});
 }
 
+#[test]
+fn runner_non_transactional_calls_without_gas_price_read_a_zero_gas_price() {
+	new_test_ext().execute_with(|| {
+		let non_balance_account =
+			H160::from_str("7700000000000000000000000000000000000001").unwrap();
+		// GASPRICE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+		let contract = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+		AccountCodes::<Test>::insert(
+			contract,
+			vec![0x3a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3],
+		);
+
+		let info = <Test as Config>::Runner::call(
+			non_balance_account,
+			contract,
+			Vec::new(),
+			U256::zero(),
+			1000000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			false, // non-transactional
+			true,  // must be validated
+			&<Test as Config>::config().clone(),
+		)
+		.expect("Non transactional call succeeds");
+		assert!(info.exit_reason.is_succeed());
+		assert_eq!(U256::from_big_endian(&info.value), U256::zero());
+		assert_eq!(
+			EVM::account_basic(&non_balance_account).0.balance,
+			U256::zero()
+		);
+	});
+}
+
+#[test]
+fn runner_transactional_calls_without_fee_read_the_base_fee() {
+	new_test_ext().execute_with(|| {
+		// Fees of XCM calls are withdrawn beforehand, the call itself paying none.
+		let non_balance_account =
+			H160::from_str("7700000000000000000000000000000000000001").unwrap();
+		// GASPRICE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+		let contract = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+		AccountCodes::<Test>::insert(
+			contract,
+			vec![0x3a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3],
+		);
+
+		let info = <Test as Config>::Runner::call(
+			non_balance_account,
+			contract,
+			Vec::new(),
+			U256::zero(),
+			1000000,
+			Some(U256::zero()),
+			None,
+			None,
+			Vec::new(),
+			true,  // transactional
+			false, // already validated
+			&<Test as Config>::config().clone(),
+		)
+		.expect("Transactional call succeeds");
+		assert!(info.exit_reason.is_succeed());
+		let (base_fee, _) = <Test as Config>::FeeCalculator::min_gas_price();
+		assert_eq!(U256::from_big_endian(&info.value), base_fee);
+	});
+}
+
 #[test]
 fn runner_non_transactional_calls_with_non_balance_accounts_is_err_with_gas_price() {
 	// In non transactional calls where `Some(gas_price)` is defined, expect it to be
@@ -37,9 +37,21 @@ pub struct TransactionStatus {
 	pub logs_bloom: Bloom,
 }
 
+/// Changes to the state of an account applied before executing a call.
+#[derive(Eq, PartialEq, Clone, Default, RuntimeDebug, Encode, Decode, TypeInfo)]
+pub struct StateOverride {
+	pub balance: Option<U256>,
+	pub nonce: Option<U256>,
+	pub code: Option<Vec<u8>>,
+	/// Storage replacing the whole storage of the account.
+	pub state: Option<Vec<(H256, H256)>>,
+	/// Storage slots replacing the ones of the account.
+	pub state_diff: Option<Vec<(H256, H256)>>,
+}
+
 sp_api::decl_runtime_apis! {
 	/// API necessary for Ethereum-compatibility layer.
-	#[api_version(4)]
+	#[api_version(5)]
 	pub trait EthereumRuntimeRPCApi {
 		/// Returns runtime defined pallet_evm::ChainId.
 		fn chain_id() -> u64;
@@ -164,6 +176,9 @@ sp_api::decl_runtime_apis! {
 		) -> Vec<ethereum::TransactionV2>;
 		/// Return the elasticity multiplier.
 		fn elasticity() -> Option<Permill>;
+		/// Applies the changes to the state of the accounts, for the calls made next
+		/// with the same runtime api instance. Available from version 5.
+		fn apply_state_overrides(state_overrides: Vec<(H160, StateOverride)>);
 	}
 
 	#[api_version(2)]
@@ -38,6 +38,74 @@ pub struct RunCmd {
 	#[clap(long, default_value = "2048")]
 	pub fee_history_limit: u64,
 
+	/// Maximum size in bytes of the data returned by eth_call.
+	#[clap(long, default_value = "10485760")]
+	pub max_call_return_data_size: usize,
+
+	/// Maximum gas allowed to eth_call and eth_estimateGas executions, defaults to the block gas limit
+	#[clap(long)]
+	pub rpc_gas_cap: Option<u64>,
+
+	/// Maximum length in bytes of the revert reason in eth_call and eth_estimateGas error messages
+	#[clap(long, default_value = "4096")]
+	pub max_revert_reason_len: usize,
+
+	/// Maximum number of calls of an eth_multicall request
+	#[clap(long, default_value = "100")]
+	pub max_multicall_calls: usize,
+
+	/// Time to live in seconds of cached eth_getLogs results of finalized ranges, no caching by default
+	#[clap(long)]
+	pub logs_cache_ttl: Option<u64>,
+
+	/// Block number from which blocks report empty withdrawals, as expected from post-Shanghai blocks
+	#[clap(long)]
+	pub withdrawals_block: Option<u64>,
+
+	/// Reject eth_sendRawTransaction transactions more than this many nonces ahead of the sender's next nonce
+	#[clap(long)]
+	pub max_nonce_gap: Option<u64>,
+
+	/// Fail eth_call requests sending data to an address without code instead of returning empty data
+	#[clap(long)]
+	pub require_callee_code: bool,
+
+	/// Maximum number of logs of a block scanned by eth_getLogs, queries reaching a block with more fail
+	#[clap(long)]
+	pub max_block_logs: Option<u32>,
+
+	/// Address returned by eth_coinbase when the block author maps to no Ethereum address
+	#[clap(long)]
+	pub default_coinbase: Option<sp_core::H160>,
+
+	/// Comma separated eth_subscribe kinds to allow (e.g. newHeads,syncing), all of them by default
+	#[clap(long, value_delimiter = ',')]
+	pub eth_subscription_kinds: Option<Vec<fc_rpc_core::types::pubsub::Kind>>,
+
+	/// Enable the eth_getPendingReceipt extension, speculatively executing pending transactions
+	#[clap(long)]
+	pub pending_receipts: bool,
+
+	/// Maximum wall time in milliseconds of the eth_estimateGas binary search
+	#[clap(long)]
+	pub estimate_gas_timeout: Option<u64>,
+
+	/// Maximum depth of a reorg the logs subscription sends removed logs for, deeper reorgs sending a resync signal
+	#[clap(long, default_value = "64")]
+	pub max_logs_reorg_depth: u32,
+
+	/// Only serve the logs of finalized blocks from eth_getLogs, clamping ranges to the finalized block.
+	#[clap(long)]
+	pub eth_finalized_logs_only: bool,
+
+	/// Lowest priority fee per gas suggested by eth_maxPriorityFeePerGas, in wei
+	#[clap(long, default_value = "0")]
+	pub eth_priority_fee_floor: u64,
+
+	/// Reject transactions sent from accounts with code, per EIP-3607
+	#[clap(long)]
+	pub reject_senders_with_code: bool,
+
 	/// The dynamic-fee pallet target gas price set by block author
 	#[clap(long, default_value = "1")]
 	pub target_gas_price: u64,
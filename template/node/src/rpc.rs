@@ -21,13 +21,16 @@ use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 use sp_runtime::traits::BlakeTwo256;
 // Frontier
 use fc_rpc::{
-	EthBlockDataCacheTask, OverrideHandle, RuntimeApiStorageOverride, SchemaV1Override,
-	SchemaV2Override, SchemaV3Override, StorageOverride,
+	EthBlockDataCacheTask, EthConfig, LinearPrecompileCost, LogsCache, OverrideHandle,
+	PercentileGasPriceOracle, RuntimeApiStorageOverride, SchemaV1Override, SchemaV2Override,
+	SchemaV3Override, StorageOverride,
 };
 use fc_rpc_core::types::{FeeHistoryCache, FeeHistoryCacheLimit, FilterPool};
 use fp_storage::EthereumStorageSchema;
 // Runtime
-use frontier_template_runtime::{opaque::Block, AccountId, Balance, Hash, Index};
+use frontier_template_runtime::{
+	opaque::Block, AccountId, Balance, FrontierPrecompiles, Hash, Index, Runtime,
+};
 
 /// Full client dependencies.
 pub struct FullDeps<C, P, A: ChainApi> {
@@ -55,6 +58,8 @@ pub struct FullDeps<C, P, A: ChainApi> {
 	pub fee_history_cache: FeeHistoryCache,
 	/// Maximum fee history cache size.
 	pub fee_history_cache_limit: FeeHistoryCacheLimit,
+	/// Settings of the Ethereum RPC servers.
+	pub eth_rpc_config: EthRpcConfig,
 	/// Ethereum data access overrides.
 	pub overrides: Arc<OverrideHandle<Block>>,
 	/// Cache for Ethereum block data.
@@ -65,6 +70,25 @@ pub struct FullDeps<C, P, A: ChainApi> {
 		Option<futures::channel::mpsc::Sender<sc_consensus_manual_seal::rpc::EngineCommand<Hash>>>,
 }
 
+/// Settings of the Ethereum RPC servers, taken from the command line.
+#[derive(Clone)]
+pub struct EthRpcConfig {
+	/// Settings of the Eth API.
+	pub eth: EthConfig,
+	/// Maximum number of logs of a block scanned by eth_getLogs.
+	pub max_block_logs: Option<u32>,
+	/// Whether eth_getLogs only serves the logs of finalized blocks.
+	pub finalized_logs_only: bool,
+	/// Cache of eth_getLogs results.
+	pub logs_cache: Option<Arc<LogsCache>>,
+	/// Subscription kinds allowed by eth_subscribe.
+	pub subscription_kinds: Option<Vec<fc_rpc_core::types::pubsub::Kind>>,
+	/// Maximum depth of a reorg followed log by log by the logs subscription.
+	pub max_logs_reorg_depth: u32,
+	/// Lowest priority fee per gas suggested by eth_maxPriorityFeePerGas.
+	pub priority_fee_floor: u64,
+}
+
 pub fn overrides_handle<C, BE>(client: Arc<C>) -> Arc<OverrideHandle<Block>>
 where
 	C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE> + AuxStore,
@@ -120,8 +144,8 @@ where
 	A: ChainApi<Block = Block> + 'static,
 {
 	use fc_rpc::{
-		Eth, EthApiServer, EthDevSigner, EthFilter, EthFilterApiServer, EthPubSub,
-		EthPubSubApiServer, EthSigner, Net, NetApiServer, Web3, Web3ApiServer,
+		AsyncEthSigner, Eth, EthApiServer, EthDevSigner, EthFilter, EthFilterApiServer, EthPubSub,
+		EthPubSubApiServer, Net, NetApiServer, Web3, Web3ApiServer,
 	};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
@@ -140,6 +164,7 @@ where
 		max_past_logs,
 		fee_history_cache,
 		fee_history_cache_limit,
+		eth_rpc_config,
 		overrides,
 		block_data_cache,
 		#[cfg(feature = "manual-seal")]
@@ -151,14 +176,14 @@ where
 
 	let mut signers = Vec::new();
 	if enable_dev_signer {
-		signers.push(Box::new(EthDevSigner::new()) as Box<dyn EthSigner>);
+		signers.push(Box::new(EthDevSigner::new()) as Box<dyn AsyncEthSigner>);
 	}
 
 	io.merge(
 		Eth::new(
 			client.clone(),
 			pool.clone(),
-			graph,
+			graph.clone(),
 			Some(frontier_template_runtime::TransactionConverter),
 			network.clone(),
 			signers,
@@ -170,6 +195,21 @@ where
 			fee_history_cache,
 			fee_history_cache_limit,
 			10,
+			Box::new(PercentileGasPriceOracle {
+				priority_fee_floor: eth_rpc_config.priority_fee_floor.into(),
+				..Default::default()
+			}),
+			EthConfig {
+				precompile_addresses: FrontierPrecompiles::<Runtime>::used_addresses()
+					.into_iter()
+					.collect(),
+				evm_config: <Runtime as pallet_evm::Config>::config().clone(),
+				precompile_linear_costs: FrontierPrecompiles::<Runtime>::linear_costs()
+					.into_iter()
+					.map(|(address, base, word)| (address, LinearPrecompileCost { base, word }))
+					.collect(),
+				..eth_rpc_config.eth
+			},
 		)
 		.into_rpc(),
 	)?;
@@ -179,10 +219,15 @@ where
 			EthFilter::new(
 				client.clone(),
 				backend,
+				graph,
 				filter_pool,
 				500_usize, // max stored filters
 				max_past_logs,
 				block_data_cache,
+				eth_rpc_config.max_block_logs,
+				eth_rpc_config.logs_cache,
+				eth_rpc_config.finalized_logs_only,
+				deny_unsafe,
 			)
 			.into_rpc(),
 		)?;
@@ -195,6 +240,8 @@ where
 			network.clone(),
 			subscription_task_executor,
 			overrides,
+			eth_rpc_config.subscription_kinds,
+			eth_rpc_config.max_logs_reorg_depth,
 		)
 		.into_rpc(),
 	)?;
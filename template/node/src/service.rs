@@ -81,6 +81,37 @@ pub(crate) fn db_config_dir(config: &Configuration) -> PathBuf {
 		})
 }
 
+/// Settings of the Ethereum RPC servers given on the command line.
+fn eth_rpc_config(cli: &Cli) -> crate::rpc::EthRpcConfig {
+	crate::rpc::EthRpcConfig {
+		eth: fc_rpc::EthConfig {
+			max_call_return_data_size: cli.run.max_call_return_data_size,
+			rpc_gas_cap: cli.run.rpc_gas_cap,
+			max_revert_reason_len: cli.run.max_revert_reason_len,
+			withdrawals_block: cli.run.withdrawals_block,
+			max_nonce_gap: cli.run.max_nonce_gap,
+			require_callee_code: cli.run.require_callee_code,
+			default_coinbase: cli.run.default_coinbase,
+			pending_receipts: cli.run.pending_receipts,
+			estimate_gas_timeout: cli.run.estimate_gas_timeout.map(Duration::from_millis),
+			reject_senders_with_code: cli.run.reject_senders_with_code,
+			max_multicall_calls: cli.run.max_multicall_calls,
+			..Default::default()
+		},
+		max_block_logs: cli.run.max_block_logs,
+		finalized_logs_only: cli.run.eth_finalized_logs_only,
+		logs_cache: cli.run.logs_cache_ttl.map(|ttl| {
+			Arc::new(fc_rpc::LogsCache::new(
+				Duration::from_secs(ttl),
+				64, // max cached results
+			))
+		}),
+		subscription_kinds: cli.run.eth_subscription_kinds.clone(),
+		max_logs_reorg_depth: cli.run.max_logs_reorg_depth,
+		priority_fee_floor: cli.run.eth_priority_fee_floor,
+	}
+}
+
 pub fn new_partial(
 	config: &Configuration,
 	cli: &Cli,
@@ -367,6 +398,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
 		let overrides = overrides.clone();
 		let fee_history_cache = fee_history_cache.clone();
 		let max_past_logs = cli.run.max_past_logs;
+		let eth_rpc_config = eth_rpc_config(cli);
 
 		Box::new(move |deny_unsafe, subscription_task_executor| {
 			let deps = crate::rpc::FullDeps {
@@ -384,6 +416,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
 				fee_history_cache_limit,
 				overrides: overrides.clone(),
 				block_data_cache: block_data_cache.clone(),
+				eth_rpc_config: eth_rpc_config.clone(),
 			};
 
 			crate::rpc::create_full(deps, subscription_task_executor).map_err(Into::into)
@@ -594,6 +627,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
 		let overrides = overrides.clone();
 		let fee_history_cache = fee_history_cache.clone();
 		let max_past_logs = cli.run.max_past_logs;
+		let eth_rpc_config = eth_rpc_config(cli);
 
 		Box::new(move |deny_unsafe, subscription_task_executor| {
 			let deps = crate::rpc::FullDeps {
@@ -611,6 +645,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
 				fee_history_cache_limit,
 				overrides: overrides.clone(),
 				block_data_cache: block_data_cache.clone(),
+				eth_rpc_config: eth_rpc_config.clone(),
 				command_sink: Some(command_sink.clone()),
 			};
 
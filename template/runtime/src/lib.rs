@@ -58,7 +58,7 @@ pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
 
 mod precompiles;
-use precompiles::FrontierPrecompiles;
+pub use precompiles::FrontierPrecompiles;
 
 /// Type of block number.
 pub type BlockNumber = u32;
@@ -738,6 +738,44 @@ impl_runtime_apis! {
 		fn elasticity() -> Option<Permill> {
 			Some(BaseFee::elasticity())
 		}
+
+		fn apply_state_overrides(state_overrides: Vec<(H160, fp_rpc::StateOverride)>) {
+			use frame_support::traits::Currency;
+			use pallet_evm::AddressMapping;
+
+			for (address, state_override) in state_overrides {
+				let account_id =
+					<Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address);
+				if let Some(balance) = state_override.balance {
+					let _ = Balances::make_free_balance_be(
+						&account_id,
+						balance.unique_saturated_into(),
+					);
+				}
+				if let Some(nonce) = state_override.nonce {
+					frame_system::Account::<Runtime>::mutate(&account_id, |account| {
+						account.nonce = nonce.unique_saturated_into();
+					});
+				}
+				if let Some(code) = state_override.code {
+					if code.is_empty() {
+						pallet_evm::AccountCodes::<Runtime>::remove(address);
+					} else {
+						EVM::create_account(address, code);
+					}
+				}
+				if let Some(state) = state_override.state {
+					#[allow(deprecated)]
+					let _ = pallet_evm::AccountStorages::<Runtime>::remove_prefix(address, None);
+					for (index, value) in state {
+						pallet_evm::AccountStorages::<Runtime>::insert(address, index, value);
+					}
+				}
+				for (index, value) in state_override.state_diff.unwrap_or_default() {
+					pallet_evm::AccountStorages::<Runtime>::insert(address, index, value);
+				}
+			}
+		}
 	}
 
 	impl fp_rpc::ConvertTransactionRuntimeApi<Block> for Runtime {
@@ -1,4 +1,6 @@
-use pallet_evm::{Precompile, PrecompileHandle, PrecompileResult, PrecompileSet};
+use pallet_evm::{
+	LinearCostPrecompile, Precompile, PrecompileHandle, PrecompileResult, PrecompileSet,
+};
 use sp_core::H160;
 use sp_std::marker::PhantomData;
 
@@ -26,6 +28,22 @@ where
 			hash(1025),
 		]
 	}
+	/// Base and per word gas costs of the used precompiles whose cost only depends
+	/// on the length of their input.
+	pub fn linear_costs() -> [(H160, u64, u64); 6] {
+		[
+			(hash(1), ECRecover::BASE, ECRecover::WORD),
+			(hash(2), Sha256::BASE, Sha256::WORD),
+			(hash(3), Ripemd160::BASE, Ripemd160::WORD),
+			(hash(4), Identity::BASE, Identity::WORD),
+			(hash(1024), Sha3FIPS256::BASE, Sha3FIPS256::WORD),
+			(
+				hash(1025),
+				ECRecoverPublicKey::BASE,
+				ECRecoverPublicKey::WORD,
+			),
+		]
+	}
 }
 impl<R> PrecompileSet for FrontierPrecompiles<R>
 where
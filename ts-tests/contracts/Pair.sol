@@ -0,0 +1,7 @@
+pragma solidity 0.8.2;
+
+contract Pair {
+    function pair() public view returns(uint256, address) {
+        return (42, msg.sender);
+    }
+}
@@ -27,4 +27,23 @@ describeWithFrontier("Frontier RPC (BlockNumber tags)", (context) => {
 	step("`safe` is an alias for `finalized` in Polkadot", async function () {
 		expect((await context.web3.eth.getBlock("safe")).number).to.equal(1);
 	});
+
+	step("`eth_blockNumber` reports the best, not the finalized, block", async function () {
+		expect(await context.web3.eth.getBlockNumber()).to.equal(2);
+	});
+
+	step("`eth_blockNumber` agrees with `latest` across imports", async function () {
+		this.timeout(20000);
+		for (let i = 0; i < 10; i++) {
+			// Import without the settle delay of `createAndFinalizeBlock`.
+			const response = await customRequest(context.web3, "engine_createBlock", [true, false, null]);
+			expect(response.result).to.not.be.undefined;
+			const [number, latest] = await Promise.all([
+				context.web3.eth.getBlockNumber(),
+				context.web3.eth.getBlock("latest"),
+			]);
+			expect(number).to.equal(3 + i);
+			expect(latest.number).to.equal(number);
+		}
+	});
 });
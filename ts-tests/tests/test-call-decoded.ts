@@ -0,0 +1,51 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+
+import Pair from "../build/contracts/Pair.json";
+import { GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY, FIRST_CONTRACT_ADDRESS } from "./config";
+import { createAndFinalizeBlock, customRequest, describeWithFrontier } from "./util";
+
+describeWithFrontier("Frontier RPC (Decoded call)", (context) => {
+	before("create the contract", async function () {
+		this.timeout(15000);
+		const tx = await context.web3.eth.accounts.signTransaction(
+			{
+				from: GENESIS_ACCOUNT,
+				data: Pair.bytecode,
+				value: "0x00",
+				gasPrice: "0x3B9ACA00",
+				gas: "0x100000",
+			},
+			GENESIS_ACCOUNT_PRIVATE_KEY
+		);
+		await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+		await createAndFinalizeBlock(context.web3);
+	});
+
+	const pairRequest = () => ({
+		from: GENESIS_ACCOUNT,
+		to: FIRST_CONTRACT_ADDRESS,
+		data: context.web3.eth.abi.encodeFunctionSignature("pair()"),
+	});
+
+	step("decodes the returned (uint256, address)", async function () {
+		const result = await customRequest(context.web3, "eth_callDecoded", [
+			pairRequest(),
+			["uint256", "address"],
+		]);
+		expect(result.result).to.deep.equal(["0x2a", GENESIS_ACCOUNT.toLowerCase()]);
+	});
+
+	step("fails on return data not matching the output types", async function () {
+		const result = await customRequest(context.web3, "eth_callDecoded", [
+			pairRequest(),
+			["uint256", "address", "bool"],
+		]);
+		expect(result.error.message).to.contain("does not decode as the output types");
+	});
+
+	step("rejects unsupported output types", async function () {
+		const result = await customRequest(context.web3, "eth_callDecoded", [pairRequest(), ["int256"]]);
+		expect(result.error.message).to.contain("unsupported ABI type int256");
+	});
+});
@@ -70,6 +70,14 @@ describeWithFrontier("Frontier RPC (Contract)", (context) => {
 
 		expect(getStoragePending.result).to.be.eq(expectedStorage);
 
+		// Until mined, the write only shows in the pending state.
+		let getStorageLatest = await customRequest(context.web3, "eth_getStorageAt", [
+			contractAddress,
+			"0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc",
+			"latest",
+		]);
+		expect(getStorageLatest.result).to.be.eq(getStorage0.result);
+
 		await createAndFinalizeBlock(context.web3);
 
 		let getStorage1 = await customRequest(context.web3, "eth_getStorageAt", [
@@ -2,7 +2,7 @@ import { assert, expect } from "chai";
 import { step } from "mocha-steps";
 import { BLOCK_GAS_LIMIT, GENESIS_ACCOUNT } from "./config";
 
-import { describeWithFrontier, customRequest } from "./util";
+import { describeWithFrontier, customRequest, createAndFinalizeBlock } from "./util";
 
 import Test from "../build/contracts/Test.json";
 
@@ -88,4 +88,43 @@ describeWithFrontier("Frontier RPC (RPC execution)", (context) => {
 			"provided gas limit is too high (can be up to 10x the block gas limit)"
 		);
 	});
+
+	step("should read the previous block hash during eth_call", async function () {
+		await createAndFinalizeBlock(context.web3);
+		await createAndFinalizeBlock(context.web3);
+
+		// Init code returning BLOCKHASH(NUMBER - 1).
+		const result = await customRequest(context.web3, "eth_call", [
+			{
+				from: GENESIS_ACCOUNT,
+				data: "0x43600190034060005260206000f3",
+			},
+		]);
+
+		const previous = await context.web3.eth.getBlock((await context.web3.eth.getBlockNumber()) - 1);
+		expect(result.result).to.be.equal(previous.hash);
+	});
+
+	step("should run independent calls against the same block with eth_multicall", async function () {
+		const result = await customRequest(context.web3, "eth_multicall", [
+			[
+				// Sha256, Ripemd160 and Identity precompiles.
+				{ from: GENESIS_ACCOUNT, to: "0x0000000000000000000000000000000000000002", data: "0x" },
+				{ from: GENESIS_ACCOUNT, to: "0x0000000000000000000000000000000000000003", data: "0x" },
+				{ from: GENESIS_ACCOUNT, to: "0x0000000000000000000000000000000000000004", data: "0x1234" },
+			],
+		]);
+
+		expect(result.result).to.deep.equal([
+			{
+				success: true,
+				returnData: "0xe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+			},
+			{
+				success: true,
+				returnData: "0x0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31",
+			},
+			{ success: true, returnData: "0x1234" },
+		]);
+	});
 });
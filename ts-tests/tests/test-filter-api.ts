@@ -53,6 +53,18 @@ describeWithFrontier("Frontier RPC (EthFilterApi)", (context) => {
 		expect(createFilter.result).to.be.eq("0x3");
 	});
 
+	step("should list the installed filters", async function () {
+		let r = await customRequest(context.web3, "debug_getFilters", []);
+		expect(r.result.map((filter) => [filter.id, filter.type])).to.be.deep.eq([
+			["0x1", "log"],
+			["0x2", "log"],
+			["0x3", "block"],
+		]);
+		const block = await context.web3.eth.getBlockNumber();
+		expect(r.result[2].lastPollBlock).to.be.eq(context.web3.utils.numberToHex(block));
+		expect(r.result[2].idleSeconds).to.be.a("number");
+	});
+
 	step("should return unsupported error for Pending Transaction filter creation", async function () {
 		let r = await customRequest(context.web3, "eth_newPendingTransactionFilter", []);
 		expect(r.error).to.include({
@@ -3,7 +3,7 @@ import { AbiItem } from "web3-utils";
 
 import InvalidOpcode from "../build/contracts/InvalidOpcode.json";
 import Test from "../build/contracts/Test.json";
-import { GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY, FIRST_CONTRACT_ADDRESS } from "./config";
+import { BLOCK_GAS_LIMIT, GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY, FIRST_CONTRACT_ADDRESS } from "./config";
 import { describeWithFrontier, createAndFinalizeBlock, customRequest } from "./util";
 
 // (!) The implementation must match the one in the rpc handler.
@@ -187,6 +187,21 @@ describeWithFrontier("Frontier RPC (Gas)", (context) => {
 		await createAndFinalizeBlock(context.web3);
 		expect((createReceipt as any).error.message).to.equal("exceeds block gas limit");
 	});
+	it("tx gas limit larger BLOCK_GAS_LIMIT", async function () {
+		const tx = await context.web3.eth.accounts.signTransaction(
+			{
+				from: GENESIS_ACCOUNT,
+				data: Test.bytecode,
+				gas: BLOCK_GAS_LIMIT + 1,
+				gasPrice: "0x3B9ACA00",
+			},
+			GENESIS_ACCOUNT_PRIVATE_KEY
+		);
+		const createReceipt = await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+		expect((createReceipt as any).error.message).to.equal("exceeds block gas limit");
+		// Rejected on submission, it is not left in the pool.
+		expect(await context.web3.eth.getTransaction(tx.transactionHash)).to.be.null;
+	});
 });
 
 describeWithFrontier("Frontier RPC (Invalid opcode estimate gas)", (context) => {
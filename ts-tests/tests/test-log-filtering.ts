@@ -83,4 +83,38 @@ describeWithFrontier("Frontier RPC (Log filtering)", (context) => {
 			expect(request.result.length).to.be.eq(0);
 		}
 	});
+
+	step("EthApi::getLogs - should return the logs of a single transaction.", async function () {
+		let tx = await sendTransaction(context);
+		await createAndFinalizeBlock(context.web3);
+		let receipt = await context.web3.eth.getTransactionReceipt(tx.transactionHash);
+
+		let byTransaction = await customRequest(context.web3, "eth_getLogs", [
+			{ transactionHash: tx.transactionHash },
+		]);
+		let byRange = await customRequest(context.web3, "eth_getLogs", [
+			{ fromBlock: receipt.blockNumber, toBlock: receipt.blockNumber },
+		]);
+
+		const expected = byRange.result.filter((log) => log.transactionHash == tx.transactionHash);
+		expect(expected.length).to.be.greaterThan(0);
+		expect(byTransaction.result).to.deep.equal(expected);
+		expect(byTransaction.result[0].blockHash).to.be.equal(receipt.blockHash);
+	});
+
+	step("EthApi::getLogs - should return the logs of ready transactions as pending.", async function () {
+		let tx = await sendTransaction(context);
+
+		let pending = await customRequest(context.web3, "eth_getLogs", [{ fromBlock: "pending", toBlock: "pending" }]);
+		expect(pending.result.length).to.be.eq(1);
+		expect(pending.result[0]).to.include({
+			transactionHash: tx.transactionHash,
+			blockHash: null,
+			blockNumber: null,
+		});
+
+		await createAndFinalizeBlock(context.web3);
+		let receipt = await context.web3.eth.getTransactionReceipt(tx.transactionHash);
+		expect(pending.result[0].topics).to.deep.equal(receipt.logs[0].topics);
+	});
 });
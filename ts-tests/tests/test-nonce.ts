@@ -33,4 +33,33 @@ describeWithFrontier("Frontier RPC (Nonce)", (context) => {
 		expect(await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT, "pending")).to.eq(1);
 		expect(await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT, "earliest")).to.eq(0);
 	});
+
+	step("count is the same at `latest` and the best block number", async function () {
+		this.timeout(10_000);
+		const tx = await context.web3.eth.accounts.signTransaction(
+			{
+				from: GENESIS_ACCOUNT,
+				to: TEST_ACCOUNT,
+				value: "0x200",
+				gasPrice: "0x3B9ACA00",
+				gas: "0x100000",
+				nonce: 1,
+			},
+			GENESIS_ACCOUNT_PRIVATE_KEY
+		);
+		await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+		await createAndFinalizeBlock(context.web3);
+
+		const best = await context.web3.eth.getBlockNumber();
+		expect(await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT, "latest")).to.eq(2);
+		expect(await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT, best)).to.eq(2);
+		expect(await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT, best - 1)).to.eq(1);
+	});
+
+	step("count is the nonce of the sender, not its transactions", async function () {
+		// The test account received both transfers but never sent one.
+		const best = await context.web3.eth.getBlockNumber();
+		expect(await context.web3.eth.getTransactionCount(TEST_ACCOUNT, "latest")).to.eq(0);
+		expect(await context.web3.eth.getTransactionCount(TEST_ACCOUNT, best)).to.eq(0);
+	});
 });
@@ -0,0 +1,43 @@
+import { expect } from "chai";
+
+import { GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY } from "./config";
+import { createAndFinalizeBlock, customRequest, describeWithFrontier } from "./util";
+
+describeWithFrontier("Frontier RPC (Pending Receipt)", (context) => {
+	const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+
+	it("should return a speculative receipt matching the mined one", async function () {
+		this.timeout(15000);
+		const tx = await context.web3.eth.accounts.signTransaction(
+			{
+				from: GENESIS_ACCOUNT,
+				to: TEST_ACCOUNT,
+				value: "0x200", // Must be higher than ExistentialDeposit
+				gasPrice: "0x3B9ACA00",
+				gas: "0x100000",
+			},
+			GENESIS_ACCOUNT_PRIVATE_KEY
+		);
+		const txHash = (await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction])).result;
+
+		const pendingReceipt = (await customRequest(context.web3, "eth_getPendingReceipt", [txHash])).result;
+		expect(pendingReceipt).to.include({
+			transactionHash: txHash,
+			from: GENESIS_ACCOUNT.toLowerCase(),
+			to: TEST_ACCOUNT,
+			blockHash: null,
+			blockNumber: null,
+			status: "0x1",
+			speculative: true,
+		});
+
+		await createAndFinalizeBlock(context.web3);
+
+		const receipt = (await customRequest(context.web3, "eth_getTransactionReceipt", [txHash])).result;
+		expect(pendingReceipt.status).to.equal(receipt.status);
+		expect(pendingReceipt.gasUsed).to.equal(receipt.gasUsed);
+
+		// Once mined, the transaction is no longer pending.
+		expect((await customRequest(context.web3, "eth_getPendingReceipt", [txHash])).result).to.be.null;
+	});
+});
@@ -18,6 +18,8 @@ describeWithFrontier("Frontier RPC (Constructor Revert)", (context) => {
 	const GOOD_BYTECODE =
 		"6080604052348015600f57600080fd5b506001601a57600080fd5b603f8060276000396000f3fe6080604052600080fdfea2646970667358221220c70bc8b03cdfdf57b5f6c4131b836f9c2c4df01b8202f530555333f2a00e4b8364736f6c63430006060033";
 
+	const txHashes = [];
+
 	it("should provide a tx receipt after successful deployment", async function () {
 		this.timeout(15000);
 
@@ -33,6 +35,7 @@ describeWithFrontier("Frontier RPC (Constructor Revert)", (context) => {
 		);
 
 		const txHash = (await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction])).result;
+		txHashes.push(txHash);
 
 		// Verify the receipt exists after the block is created
 		await createAndFinalizeBlock(context.web3);
@@ -62,6 +65,7 @@ describeWithFrontier("Frontier RPC (Constructor Revert)", (context) => {
 		);
 
 		const txHash = (await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction])).result;
+		txHashes.push(txHash);
 
 		// Verify the receipt exists after the block is created
 		await createAndFinalizeBlock(context.web3);
@@ -74,4 +78,16 @@ describeWithFrontier("Frontier RPC (Constructor Revert)", (context) => {
 			status: false,
 		});
 	});
+
+	it("should provide tx receipts in batch, in the requested order", async function () {
+		const unknownHash = "0x" + "ab".repeat(32);
+		const receipts = (
+			await customRequest(context.web3, "eth_getTransactionReceipts", [[txHashes[1], unknownHash, txHashes[0]]])
+		).result;
+
+		expect(receipts.length).to.equal(3);
+		expect(receipts[0]).to.include({ transactionHash: txHashes[1], status: "0x0" });
+		expect(receipts[1]).to.be.null;
+		expect(receipts[2]).to.include({ transactionHash: txHashes[0], status: "0x1" });
+	});
 });
@@ -1,7 +1,7 @@
 import { expect } from "chai";
 
 import { CHAIN_ID } from "./config";
-import { describeWithFrontier } from "./util";
+import { customRequest, describeWithFrontier } from "./util";
 
 // All test for the RPC
 
@@ -24,4 +24,24 @@ describeWithFrontier("Frontier RPC (Constant)", (context) => {
 		// as we are running manual sealing consensus.
 		expect(await context.web3.eth.getCoinbase()).to.equal("0x0000000000000000000000000000000000000000");
 	});
+
+	it("should not support proof-of-work", async function () {
+		const requests: [string, any[]][] = [
+			["eth_getWork", []],
+			["eth_submitHashrate", ["0x500000", "0x59daa26581d0acd1fce254fb7e85952f4c09d0915afd33d3886cd914bc7d283c"]],
+			[
+				"eth_submitWork",
+				[
+					"0x0000000000000001",
+					"0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef",
+					"0xd1fe5700000000000000000000000000d1fe5700000000000000000000000000",
+				],
+			],
+		];
+		for (const [method, params] of requests) {
+			const response = await customRequest(context.web3, method, params);
+			expect(response.error.code).to.equal(-32004);
+			expect(response.error.message).to.equal("proof-of-work is not supported on this chain");
+		}
+	});
 });
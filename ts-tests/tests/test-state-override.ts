@@ -0,0 +1,75 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+
+import { FIRST_CONTRACT_ADDRESS, GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY } from "./config";
+import { createAndFinalizeBlock, customRequest, describeWithFrontier } from "./util";
+
+describeWithFrontier("Frontier RPC (State override)", (context) => {
+	// Runtime code returning `value`: PUSH1 value PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	const returning = (value: string) => `0x60${value}60005260206000f3`;
+	// Deploys the code returning 1.
+	const CONSTANT_CONTRACT_BYTECODE = "0x600a600c600039600a6000f3" + returning("01").slice(2);
+	const ONE = "0x0000000000000000000000000000000000000000000000000000000000000001";
+	const TWO = "0x0000000000000000000000000000000000000000000000000000000000000002";
+
+	step("contract should be deployed", async function () {
+		const tx = await context.web3.eth.accounts.signTransaction(
+			{
+				from: GENESIS_ACCOUNT,
+				data: CONSTANT_CONTRACT_BYTECODE,
+				value: "0x00",
+				gasPrice: "0x3B9ACA00",
+				gas: "0x100000",
+			},
+			GENESIS_ACCOUNT_PRIVATE_KEY
+		);
+		await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+		await createAndFinalizeBlock(context.web3);
+
+		expect(await context.web3.eth.getCode(FIRST_CONTRACT_ADDRESS)).to.be.eq(returning("01"));
+	});
+
+	step("call should return the value of the deployed code", async function () {
+		const result = await customRequest(context.web3, "eth_call", [
+			{ from: GENESIS_ACCOUNT, to: FIRST_CONTRACT_ADDRESS },
+			"latest",
+		]);
+		expect(result.result).to.be.eq(ONE);
+	});
+
+	step("call should return the value of the overridden code", async function () {
+		const result = await customRequest(context.web3, "eth_call", [
+			{ from: GENESIS_ACCOUNT, to: FIRST_CONTRACT_ADDRESS },
+			"latest",
+			{ [FIRST_CONTRACT_ADDRESS]: { code: returning("02") } },
+		]);
+		expect(result.result).to.be.eq(TWO);
+	});
+
+	step("overridden code should not be persisted", async function () {
+		expect(await context.web3.eth.getCode(FIRST_CONTRACT_ADDRESS)).to.be.eq(returning("01"));
+		const result = await customRequest(context.web3, "eth_call", [
+			{ from: GENESIS_ACCOUNT, to: FIRST_CONTRACT_ADDRESS },
+			"latest",
+		]);
+		expect(result.result).to.be.eq(ONE);
+	});
+
+	step("call with overrides should execute the runtime precompiles", async function () {
+		const result = await customRequest(context.web3, "eth_call", [
+			{ from: GENESIS_ACCOUNT, to: "0x0000000000000000000000000000000000000004", data: "0x1234" },
+			"latest",
+			{ [FIRST_CONTRACT_ADDRESS]: { code: returning("02") } },
+		]);
+		expect(result.result).to.be.eq("0x1234");
+	});
+
+	step("call should fail on both state and stateDiff overrides", async function () {
+		const result = await customRequest(context.web3, "eth_call", [
+			{ from: GENESIS_ACCOUNT, to: FIRST_CONTRACT_ADDRESS },
+			"latest",
+			{ [FIRST_CONTRACT_ADDRESS]: { state: {}, stateDiff: {} } },
+		]);
+		expect(result.error.message).to.match(/has both state and stateDiff overrides/);
+	});
+});
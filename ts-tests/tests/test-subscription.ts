@@ -83,6 +83,29 @@ describeWithFrontierWs("Frontier RPC (Subscription)", (context) => {
 		});
 		expect(data.nonce).to.eql("0x0000000000000000");
 
+		const block = await context.web3.eth.getBlock(data.number);
+		for (const field of [
+			"hash",
+			"parentHash",
+			"sha3Uncles",
+			"miner",
+			"stateRoot",
+			"transactionsRoot",
+			"receiptsRoot",
+			"logsBloom",
+			"difficulty",
+			"number",
+			"gasLimit",
+			"gasUsed",
+			"timestamp",
+			"extraData",
+			"mixHash",
+			"nonce",
+			"baseFeePerGas",
+		]) {
+			expect(data[field], field).to.eql(block[field]);
+		}
+
 		done();
 	}).timeout(40000);
 
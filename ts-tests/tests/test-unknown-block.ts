@@ -0,0 +1,25 @@
+import { expect } from "chai";
+
+import { GENESIS_ACCOUNT } from "./config";
+import { customRequest, describeWithFrontier } from "./util";
+
+describeWithFrontier("Frontier RPC (Unknown Block)", (context) => {
+	const UNKNOWN_BLOCK_HASH = "0x" + "ab".repeat(32);
+
+	it("should return null for a lookup of an unknown block", async function () {
+		const block = await customRequest(context.web3, "eth_getBlockByHash", [UNKNOWN_BLOCK_HASH, false]);
+		expect(block.error).to.be.undefined;
+		expect(block.result).to.be.null;
+	});
+
+	it("should fail a state query at an unknown block", async function () {
+		const byHash = await customRequest(context.web3, "eth_getBalance", [
+			GENESIS_ACCOUNT,
+			{ blockHash: UNKNOWN_BLOCK_HASH },
+		]);
+		expect(byHash.error.message).to.equal("header not found");
+
+		const byNumber = await customRequest(context.web3, "eth_getBalance", [GENESIS_ACCOUNT, "0x1000"]);
+		expect(byNumber.error.message).to.equal("header not found");
+	});
+});
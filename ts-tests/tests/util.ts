@@ -78,6 +78,7 @@ export async function startFrontierNode(provider?: string): Promise<{
 		`--sealing=Manual`,
 		`--no-grandpa`,
 		`--force-authoring`,
+		`--pending-receipts`, // Opt-in eth_getPendingReceipt extension
 		`-l${FRONTIER_LOG}`,
 		`--port=${PORT}`,
 		`--rpc-port=${RPC_PORT}`,